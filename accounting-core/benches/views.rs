@@ -0,0 +1,163 @@
+//! Slicing 100k transactions by month, then by node, then by tag: with views that
+//! select by position (`Transactions::filter`), and with copies of the transactions
+//! (how `filter` used to work). Prints the peak memory of both before timing them.
+//!
+//! Run with `cargo bench -p accounting-core --bench views`.
+use accounting_core::canonical::{account::*, identify::*, transaction::*};
+use chrono::{Datelike, Days, NaiveDate};
+use criterion::{black_box, Criterion};
+use iban::Iban;
+use rust_decimal::Decimal;
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    collections::BTreeMap,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Keeps track of the bytes in use, and the most that were in use at once.
+struct Counting;
+static IN_USE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let in_use = IN_USE.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(in_use, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        IN_USE.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+const TRANSACTIONS: usize = 100_000;
+const NODES: usize = 50;
+const TAG: &str = "#boodschappen";
+
+fn transactions() -> Vec<Transaction> {
+    let checking = Node::ProperAccount(Account {
+        iban: Iban::parse("NL91ABNA0417164300").unwrap(),
+        name: String::from("Checking"),
+    });
+    let start = NaiveDate::from_ymd_opt(2022, 1, 1).unwrap();
+    (0..TRANSACTIONS)
+        .map(|i| Transaction {
+            date: start + Days::new(i as u64 / 100),
+            source: checking.clone(),
+            sink: Node::Terminal(format!("TERM{:04}", i % NODES)),
+            amount: Decimal::new(100 + (i as i64 * 37) % 10_000, 2),
+            inherent_tags: if i % 3 == 0 {
+                TAG.into()
+            } else {
+                String::new()
+            },
+            description: format!("Betaalautomaat Term: TERM{:04} Transactie {}", i % NODES, i),
+            sequence: Some(Sequence {
+                of: checking.id(),
+                number: i as u64,
+            }),
+            reference: None,
+            end_to_end: None,
+            mandate: None,
+            balance: None,
+            code: Some(String::from("BA")),
+            occurrence: 0,
+            rule_tags: BTreeMap::new(),
+            category: None,
+        })
+        .collect()
+}
+
+fn months(transactions: &Transactions) -> Vec<(i32, u32)> {
+    let mut months: Vec<(i32, u32)> = transactions
+        .iter()
+        .map(|t| (t.date.year(), t.date.month()))
+        .collect();
+    months.dedup();
+    months
+}
+
+fn in_month(transaction: &Transaction, (year, month): (i32, u32)) -> bool {
+    transaction.date.year() == year && transaction.date.month() == month
+}
+
+fn sink_is(transaction: &Transaction, node: &Node) -> bool {
+    transaction.sink == *node
+}
+
+/// Every month, every node in it, the transactions of that node with `TAG`.
+fn with_views(transactions: &Transactions, months: &[(i32, u32)], nodes: &[Node]) -> usize {
+    let mut found = 0;
+    for &month in months {
+        let in_month = transactions.filter(|t| in_month(t, month));
+        for node in nodes {
+            let of_node = in_month.filter(|t| sink_is(t, node));
+            let tagged = of_node.filter(|t| t.tags().contains(TAG));
+            found += tagged.len();
+        }
+    }
+    found
+}
+
+/// `with_views`, copying the transactions at every step.
+fn with_copies(transactions: &Transactions, months: &[(i32, u32)], nodes: &[Node]) -> usize {
+    fn copy<'t>(
+        transactions: impl Iterator<Item = &'t Transaction>,
+        by: impl Fn(&Transaction) -> bool,
+    ) -> Box<[Transaction]> {
+        transactions.filter(|t| by(t)).cloned().collect()
+    }
+    let mut found = 0;
+    for &month in months {
+        let in_month = copy(transactions.iter(), |t| in_month(t, month));
+        for node in nodes {
+            let of_node = copy(in_month.iter(), |t| sink_is(t, node));
+            let tagged = copy(of_node.iter(), |t| t.tags().contains(TAG));
+            found += tagged.len();
+        }
+    }
+    found
+}
+
+/// The most bytes in use at once while running `slice`, on top of what was in use before.
+fn peak_memory(slice: impl FnOnce() -> usize) -> (usize, usize) {
+    let before = IN_USE.load(Ordering::Relaxed);
+    PEAK.store(before, Ordering::Relaxed);
+    let found = black_box(slice());
+    (found, PEAK.load(Ordering::Relaxed) - before)
+}
+
+fn main() {
+    let transactions = Transactions::new(transactions());
+    let months = months(&transactions);
+    let nodes: Vec<Node> = (0..NODES)
+        .map(|i| Node::Terminal(format!("TERM{:04}", i)))
+        .collect();
+
+    let (views, views_peak) = peak_memory(|| with_views(&transactions, &months, &nodes));
+    let (copies, copies_peak) = peak_memory(|| with_copies(&transactions, &months, &nodes));
+    assert_eq!(views, copies);
+    println!(
+        "Slicing {} transactions by {} months, {} nodes and a tag ({} found)",
+        TRANSACTIONS,
+        months.len(),
+        NODES,
+        views
+    );
+    println!("peak memory, views:  {:>10} bytes", views_peak);
+    println!("peak memory, copies: {:>10} bytes", copies_peak);
+
+    let mut criterion = Criterion::default().configure_from_args();
+    let mut group = criterion.benchmark_group("slice by month, node and tag");
+    group.sample_size(10);
+    group.bench_function("views", |b| {
+        b.iter(|| with_views(&transactions, &months, &nodes))
+    });
+    group.bench_function("copies", |b| {
+        b.iter(|| with_copies(&transactions, &months, &nodes))
+    });
+    group.finish();
+    criterion.final_summary();
+}
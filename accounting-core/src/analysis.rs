@@ -0,0 +1,1405 @@
+pub mod summaries {
+    use crate::canonical::{category::Category, identify::*, state::Owner, transaction::*};
+    use chrono::{Datelike, Days, Months, NaiveDate};
+    use rust_decimal::Decimal;
+    use serde::{Deserialize, Serialize};
+    use std::{
+        collections::{BTreeMap, HashMap},
+        ops::AddAssign,
+    };
+
+    pub fn node_frequencies<'a, P: View<'a>>(
+        transactions: &'a Transactions<'a, P>,
+    ) -> HashMap<ID<Node>, u64> {
+        let mut result = HashMap::new();
+        for transaction in transactions.iter() {
+            result
+                .entry(transaction.source.id())
+                .and_modify(|freq| *freq += 1)
+                .or_insert(1);
+            result
+                .entry(transaction.sink.id())
+                .and_modify(|freq| *freq += 1)
+                .or_insert(1);
+        }
+        result
+    }
+
+    /// Money that came in from, and went out to, nodes the owner doesn't own.
+    #[derive(Debug, Default, PartialEq, Eq)]
+    pub struct Totals {
+        pub income: Decimal,
+        pub expenses: Decimal,
+    }
+
+    /// Add up what `owner` received and paid. Internal transfers between the owner's
+    /// own nodes are neither, so they don't count (whether one or both legs are there).
+    pub fn income_and_expenses<'a, P: View<'a>>(
+        transactions: &'a Transactions<'a, P>,
+        owner: &Owner,
+    ) -> Totals {
+        let mut totals = Totals::default();
+        for transaction in transactions.iter() {
+            if owner.is_internal(transaction) {
+                continue;
+            }
+            if owner.owns(&transaction.sink.id()) {
+                totals.income += transaction.amount;
+            } else if owner.owns(&transaction.source.id()) {
+                totals.expenses += transaction.amount;
+            }
+        }
+        totals
+    }
+
+    /// Money between the owner and one node the owner doesn't own, or one category.
+    #[derive(Debug, Default, Clone, PartialEq, Eq)]
+    pub struct Flow {
+        /// From the node to the owner.
+        pub incoming: Decimal,
+        /// From the owner to the node.
+        pub outgoing: Decimal,
+    }
+    impl Flow {
+        pub fn net(&self) -> Decimal {
+            self.incoming - self.outgoing
+        }
+
+        /// The flow of `transaction` for `owner`, and the node on the other side. None
+        /// for internal transfers, and for transactions the owner isn't part of.
+        fn of<'t>(transaction: &'t Transaction, owner: &Owner) -> Option<(&'t Node, Self)> {
+            if owner.is_internal(transaction) {
+                None
+            } else if owner.owns(&transaction.sink.id()) {
+                let incoming = transaction.amount;
+                Some((
+                    &transaction.source,
+                    Self {
+                        incoming,
+                        ..Self::default()
+                    },
+                ))
+            } else if owner.owns(&transaction.source.id()) {
+                let outgoing = transaction.amount;
+                Some((
+                    &transaction.sink,
+                    Self {
+                        outgoing,
+                        ..Self::default()
+                    },
+                ))
+            } else {
+                None
+            }
+        }
+    }
+    impl AddAssign<&Flow> for Flow {
+        fn add_assign(&mut self, other: &Flow) {
+            self.incoming += other.incoming;
+            self.outgoing += other.outgoing;
+        }
+    }
+
+    /// What `owner` received from and paid to every node it doesn't own. Internal
+    /// transfers are left out, like in `income_and_expenses`.
+    pub fn cash_flow_per_node<'a, P: View<'a>>(
+        transactions: &'a Transactions<'a, P>,
+        owner: &Owner,
+    ) -> HashMap<ID<Node>, Flow> {
+        let mut flows: HashMap<ID<Node>, Flow> = HashMap::new();
+        for (node, flow) in transactions.iter().filter_map(|t| Flow::of(t, owner)) {
+            *flows.entry(node.id()).or_default() += &flow;
+        }
+        flows
+    }
+
+    /// The money of one category of the owner's tree.
+    #[derive(Debug, Default, Clone, PartialEq, Eq)]
+    pub struct CategoryFlow {
+        /// Of the transactions in exactly this category.
+        pub own: Flow,
+        /// Of the transactions in this category and all of its subcategories.
+        pub total: Flow,
+    }
+
+    /// What `owner` received and paid per category (see `Transaction::category`), with
+    /// subtotals that roll up into every parent. Every category of the owner's tree is
+    /// there, in tree order, also without transactions. Transactions without a category
+    /// are under `None`. Internal transfers are left out, like in `income_and_expenses`.
+    pub fn cash_flow_per_category<'a, P: View<'a>>(
+        transactions: &'a Transactions<'a, P>,
+        owner: &Owner,
+    ) -> BTreeMap<Option<Category>, CategoryFlow> {
+        let mut flows: BTreeMap<Option<Category>, CategoryFlow> = owner
+            .category_tree()
+            .into_iter()
+            .map(|category| (Some(category), CategoryFlow::default()))
+            .collect();
+        for transaction in transactions.iter() {
+            if let Some((_, flow)) = Flow::of(transaction, owner) {
+                add_to_category(&mut flows, transaction.category.as_ref(), &flow);
+            }
+        }
+        flows
+    }
+
+    /// Add `flow` to `category` and to the totals of its parents.
+    fn add_to_category(
+        flows: &mut BTreeMap<Option<Category>, CategoryFlow>,
+        category: Option<&Category>,
+        flow: &Flow,
+    ) {
+        let Some(category) = category else {
+            let uncategorized = flows.entry(None).or_default();
+            uncategorized.own += flow;
+            uncategorized.total += flow;
+            return;
+        };
+        flows.entry(Some(category.clone())).or_default().own += flow;
+        for parent in category.lineage() {
+            flows.entry(Some(parent)).or_default().total += flow;
+        }
+    }
+
+    /// How long the periods of a cash flow report are. Weeks start on Monday.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Period {
+        Week,
+        Month,
+        Year,
+    }
+    impl Period {
+        /// The first day of the period that `date` is in.
+        pub fn start(&self, date: NaiveDate) -> NaiveDate {
+            match self {
+                Self::Week => date - Days::new(date.weekday().num_days_from_monday().into()),
+                Self::Month => date.with_day(1).unwrap(),
+                Self::Year => date.with_ordinal(1).unwrap(),
+            }
+        }
+
+        /// The first day of the period after the one that `date` is in.
+        pub fn next(&self, date: NaiveDate) -> NaiveDate {
+            let start = self.start(date);
+            match self {
+                Self::Week => start + Days::new(7),
+                Self::Month => start + Months::new(1),
+                Self::Year => start + Months::new(12),
+            }
+        }
+
+        /// The last day of the period that `date` is in.
+        pub fn end(&self, date: NaiveDate) -> NaiveDate {
+            self.next(date) - Days::new(1)
+        }
+    }
+
+    /// The money of one period of a cash flow report.
+    #[derive(Debug, Default, Clone, PartialEq, Eq)]
+    pub struct PeriodFlow {
+        /// Of all of the owner's income and expenses.
+        pub total: Flow,
+        /// Per node the owner doesn't own, like in `cash_flow_per_node`.
+        pub nodes: HashMap<ID<Node>, Flow>,
+        /// Per category with transactions in the period, and the parents of those, like
+        /// in `cash_flow_per_category`.
+        pub categories: BTreeMap<Option<Category>, CategoryFlow>,
+    }
+
+    /// What `owner` received and paid in every `period`, by the first day of the period,
+    /// from the first transaction to the last. Periods without transactions are there
+    /// too, so the report has no gaps. Internal transfers are left out, like in
+    /// `income_and_expenses`.
+    pub fn cash_flow_per_period<'a, P: View<'a>>(
+        transactions: &'a Transactions<'a, P>,
+        owner: &Owner,
+        period: Period,
+    ) -> BTreeMap<NaiveDate, PeriodFlow> {
+        let mut periods: BTreeMap<NaiveDate, PeriodFlow> = BTreeMap::new();
+        for transaction in transactions.iter() {
+            let Some((node, flow)) = Flow::of(transaction, owner) else {
+                continue;
+            };
+            let report = periods.entry(period.start(transaction.date)).or_default();
+            report.total += &flow;
+            *report.nodes.entry(node.id()).or_default() += &flow;
+            add_to_category(&mut report.categories, transaction.category.as_ref(), &flow);
+        }
+        if let (Some(&first), Some(&last)) = (periods.keys().next(), periods.keys().next_back()) {
+            let mut start = first;
+            while start < last {
+                periods.entry(start).or_default();
+                start = period.next(start);
+            }
+        }
+        periods
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::canonical::fixtures::*;
+        use rust_decimal_macros::dec;
+
+        fn in_category(transaction: Transaction, category: &str) -> Transaction {
+            Transaction {
+                category: Some(category.parse().unwrap()),
+                ..transaction
+            }
+        }
+
+        fn category(category: &str) -> Option<Category> {
+            Some(category.parse().unwrap())
+        }
+
+        fn flow(incoming: Decimal, outgoing: Decimal) -> Flow {
+            Flow { incoming, outgoing }
+        }
+
+        #[test]
+        fn categories_roll_up_into_parents() {
+            let mut owner = owner(vec![checking(), joint()]);
+            owner.categories = ["Food > Groceries", "Food > Dining", "Housing"]
+                .iter()
+                .map(|category| category.parse().unwrap())
+                .collect();
+            let day = date(2024, 1, 2);
+            let transactions = Transactions::new(vec![
+                in_category(
+                    transaction(day, &checking(), &shop(), dec!(40)),
+                    "Food > Groceries",
+                ),
+                in_category(
+                    transaction(day, &checking(), &shop(), dec!(20)),
+                    "Food > Dining",
+                ),
+                in_category(transaction(day, &shop(), &checking(), dec!(5)), "Food"),
+                transaction(day, &shop(), &joint(), dec!(100)),
+                // Internal: neither income nor an expense.
+                in_category(transaction(day, &checking(), &joint(), dec!(50)), "Food"),
+            ]);
+            let flows = cash_flow_per_category(&transactions, &owner);
+
+            let order: Vec<_> = flows.keys().cloned().collect();
+            assert_eq!(
+                order,
+                [
+                    None,
+                    category("Food"),
+                    category("Food > Dining"),
+                    category("Food > Groceries"),
+                    category("Housing"),
+                ]
+            );
+            let food = &flows[&category("Food")];
+            assert_eq!(food.own, flow(dec!(5), dec!(0)));
+            assert_eq!(food.total, flow(dec!(5), dec!(60)));
+            assert_eq!(food.total.net(), dec!(-55));
+            let groceries = &flows[&category("Food > Groceries")];
+            assert_eq!(groceries.own, groceries.total);
+            assert_eq!(groceries.total, flow(dec!(0), dec!(40)));
+            assert_eq!(flows[&category("Housing")], CategoryFlow::default());
+            assert_eq!(flows[&None].total, flow(dec!(100), dec!(0)));
+        }
+
+        #[test]
+        fn categories_outside_the_tree_still_count() {
+            let transactions = Transactions::new(vec![in_category(
+                transaction(date(2024, 1, 2), &checking(), &shop(), dec!(7)),
+                "Leisure > Books",
+            )]);
+            let flows = cash_flow_per_category(&transactions, &owner(vec![checking()]));
+            assert_eq!(flows[&category("Leisure")].total, flow(dec!(0), dec!(7)));
+            assert_eq!(flows[&category("Leisure")].own, Flow::default());
+            assert_eq!(
+                flows[&category("Leisure > Books")].own,
+                flow(dec!(0), dec!(7))
+            );
+        }
+
+        #[test]
+        fn periods_start_on_monday_the_first_and_new_year() {
+            // 2024-01-07 is a Sunday.
+            assert_eq!(Period::Week.start(date(2024, 1, 7)), date(2024, 1, 1));
+            assert_eq!(Period::Week.start(date(2024, 1, 8)), date(2024, 1, 8));
+            assert_eq!(Period::Week.start(date(2025, 1, 1)), date(2024, 12, 30));
+            assert_eq!(Period::Week.end(date(2024, 12, 30)), date(2025, 1, 5));
+            assert_eq!(Period::Month.next(date(2024, 1, 31)), date(2024, 2, 1));
+            assert_eq!(Period::Month.end(date(2024, 2, 10)), date(2024, 2, 29));
+            assert_eq!(Period::Year.start(date(2024, 7, 1)), date(2024, 1, 1));
+            assert_eq!(Period::Year.end(date(2024, 7, 1)), date(2024, 12, 31));
+        }
+
+        #[test]
+        fn weekly_report_splits_on_monday_without_gaps() {
+            let owner = owner(vec![checking()]);
+            let transactions = Transactions::new(vec![
+                transaction(date(2024, 1, 7), &checking(), &shop(), dec!(10)),
+                transaction(date(2024, 1, 8), &checking(), &shop(), dec!(20)),
+                transaction(date(2024, 1, 8), &shop(), &checking(), dec!(5)),
+                transaction(date(2024, 1, 25), &checking(), &shop(), dec!(1)),
+            ]);
+            let weeks = cash_flow_per_period(&transactions, &owner, Period::Week);
+            let starts: Vec<NaiveDate> = weeks.keys().copied().collect();
+            assert_eq!(
+                starts,
+                [
+                    date(2024, 1, 1),
+                    date(2024, 1, 8),
+                    date(2024, 1, 15),
+                    date(2024, 1, 22)
+                ]
+            );
+            assert_eq!(weeks[&date(2024, 1, 1)].total, flow(dec!(0), dec!(10)));
+            let second = &weeks[&date(2024, 1, 8)];
+            assert_eq!(second.total, flow(dec!(5), dec!(20)));
+            assert_eq!(second.nodes[&shop().id()], flow(dec!(5), dec!(20)));
+            assert_eq!(second.categories[&None].total, flow(dec!(5), dec!(20)));
+            assert_eq!(weeks[&date(2024, 1, 15)], PeriodFlow::default());
+        }
+    }
+}
+
+pub mod balances {
+    use super::transfers;
+    use crate::canonical::{identify::*, state::Owner, transaction::*};
+    use chrono::NaiveDate;
+    use itertools::Itertools;
+    use rust_decimal::Decimal;
+    use std::{
+        collections::{BTreeMap, HashMap, HashSet},
+        fmt,
+    };
+
+    /// A place where the balance chain of an account doesn't add up: the balance
+    /// after `previous`, plus or minus the amount of `next`, isn't the balance after
+    /// `next`. Rows are missing between the two, or an export overlaps wrongly.
+    #[derive(Debug)]
+    pub struct Break<'t> {
+        pub account: ID<Node>,
+        pub previous: &'t Transaction,
+        pub next: &'t Transaction,
+        /// The balance after `next` according to `previous`.
+        pub expected: Decimal,
+        /// The balance after `next` according to the bank.
+        pub actual: Decimal,
+    }
+    impl<'t> Break<'t> {
+        /// What the missing (or surplus, when negative) transactions add up to.
+        pub fn difference(&self) -> Decimal {
+            self.actual - self.expected
+        }
+    }
+    impl<'t> fmt::Display for Break<'t> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(
+                f,
+                "{}: balance after {} ({}) is {}, so {} ({}) should end at {}, but the bank says {} ({:+})",
+                self.account,
+                self.previous.id(),
+                self.previous.date,
+                balance_of(self.previous),
+                self.next.id(),
+                self.next.date,
+                self.expected,
+                self.actual,
+                self.difference()
+            )
+        }
+    }
+
+    fn balance_of(transaction: &Transaction) -> Decimal {
+        transaction
+            .balance
+            .as_ref()
+            .map_or(Decimal::ZERO, |b| b.amount)
+    }
+
+    /// The amount of `transaction` from the perspective of the account its balance is of.
+    fn signed_amount(transaction: &Transaction, account: ID<Node>) -> Decimal {
+        if transaction.sink.id() == account {
+            transaction.amount
+        } else {
+            -transaction.amount
+        }
+    }
+
+    /// Walk the transactions with a known balance of every account, day by day, and
+    /// return every break in their balance chains.
+    ///
+    /// `transactions` must be sorted by date. The order within a day doesn't matter: it
+    /// is reconstructed with `chain_order`.
+    pub fn check_chain<'t, I>(transactions: I) -> Vec<Break<'t>>
+    where
+        I: IntoIterator<Item = &'t Transaction>,
+    {
+        let mut accounts: HashMap<ID<Node>, Vec<(NaiveDate, Vec<&'t Transaction>)>> =
+            HashMap::new();
+        for transaction in transactions {
+            let Some(balance) = &transaction.balance else {
+                continue;
+            };
+            let days = accounts.entry(balance.of).or_default();
+            match days.last_mut() {
+                Some((date, day)) if *date == transaction.date => day.push(transaction),
+                _ => days.push((transaction.date, vec![transaction])),
+            }
+        }
+
+        let mut breaks = Vec::new();
+        for (account, days) in accounts {
+            let mut previous: Option<&Transaction> = None;
+            for (_, day) in days {
+                let after = previous.map(balance_of);
+                for next in chain_order(day, after, |transaction| transaction) {
+                    if let Some(previous) = previous {
+                        let expected = balance_of(previous) + signed_amount(next, account);
+                        if expected != balance_of(next) {
+                            breaks.push(Break {
+                                account,
+                                previous,
+                                next,
+                                expected,
+                                actual: balance_of(next),
+                            });
+                        }
+                    }
+                    previous = Some(next);
+                }
+            }
+        }
+        breaks.sort_by_key(|b| (b.next.date, b.account.to_string()));
+        breaks
+    }
+
+    /// The balance of every account with known balances at the end of every day.
+    ///
+    /// The order of transactions within a day isn't known, so the day's closing
+    /// balance is the one that none of the day's other transactions started from.
+    pub fn closing_balances<'t, I>(transactions: I) -> HashMap<(NaiveDate, ID<Node>), Decimal>
+    where
+        I: IntoIterator<Item = &'t Transaction>,
+    {
+        let mut days: BTreeMap<NaiveDate, Vec<(ID<Node>, Decimal, Decimal)>> = BTreeMap::new();
+        for transaction in transactions {
+            if let Some(balance) = &transaction.balance {
+                let amount = if transaction.sink.id() == balance.of {
+                    transaction.amount
+                } else {
+                    -transaction.amount
+                };
+                let opening = balance.amount - amount;
+                days.entry(transaction.date).or_default().push((
+                    balance.of,
+                    opening,
+                    balance.amount,
+                ));
+            }
+        }
+
+        let mut closing = HashMap::new();
+        for (date, balances) in days {
+            for (of, entries) in balances.into_iter().into_group_map_by(|(of, _, _)| *of) {
+                let last = entries
+                    .iter()
+                    .rev()
+                    .find(|(_, _, after)| !entries.iter().any(|(_, before, _)| before == after))
+                    .or(entries.last());
+                if let Some((_, _, after)) = last {
+                    closing.insert((date, of), *after);
+                }
+            }
+        }
+        closing
+    }
+
+    /// The balance of one owned node at the end of every day.
+    #[derive(Debug, Default, Clone, PartialEq, Eq)]
+    pub struct Series {
+        /// Whether a known balance fixes the series. Without one, the node is taken to
+        /// be empty before the first day.
+        pub anchored: bool,
+        pub balances: BTreeMap<NaiveDate, Decimal>,
+    }
+
+    /// The balances of all of an owner's nodes, and what they add up to.
+    #[derive(Debug, Default, Clone, PartialEq, Eq)]
+    pub struct Timeline {
+        /// A series for every owned node, also for nodes without transactions.
+        pub nodes: HashMap<ID<Node>, Series>,
+        pub net_worth: BTreeMap<NaiveDate, Decimal>,
+    }
+
+    /// Reconstruct the balance of every node `owner` owns on every day, from the first
+    /// transaction (or known balance) to the last.
+    ///
+    /// Balances move with the transactions of the node, and are reset to the balance the
+    /// bank reported (see `closing_balances`) or to one of the owner's
+    /// `opening_balances` wherever one is known. Before the first known balance they are
+    /// worked out backwards from it. Nodes the bank doesn't report balances of, like
+    /// savings sub-accounts, follow the transfers to and from them.
+    ///
+    /// When both legs of a transfer are there (see `transfers::match_transfers`), each
+    /// only counts for the account that booked it, so the transfer isn't counted twice.
+    pub fn timeline<'t, I>(transactions: I, owner: &Owner) -> Timeline
+    where
+        I: IntoIterator<Item = &'t Transaction>,
+    {
+        let transactions: Vec<&Transaction> = transactions.into_iter().collect();
+        let mut other_leg: HashSet<(ID<Transaction>, ID<Node>)> = HashSet::new();
+        let matched =
+            transfers::match_transfers(transactions.iter().copied(), owner, transfers::WINDOW);
+        for transfer in matched {
+            other_leg.insert((transfer.sent.id(), transfer.sent.sink.id()));
+            other_leg.insert((transfer.received.id(), transfer.received.source.id()));
+        }
+
+        let mut movements: HashMap<ID<Node>, BTreeMap<NaiveDate, Decimal>> = HashMap::new();
+        for transaction in &transactions {
+            let sides = [
+                (transaction.sink.id(), transaction.amount),
+                (transaction.source.id(), -transaction.amount),
+            ];
+            for (node, amount) in sides {
+                if !owner.owns(&node)
+                    || (owner.is_internal(transaction)
+                        && other_leg.contains(&(transaction.id(), node)))
+                {
+                    continue;
+                }
+                *movements
+                    .entry(node)
+                    .or_default()
+                    .entry(transaction.date)
+                    .or_default() += amount;
+            }
+        }
+
+        // Known balances at the end of a day. The bank's take precedence over the owner's.
+        let mut known: HashMap<ID<Node>, BTreeMap<NaiveDate, Decimal>> = HashMap::new();
+        for ((date, node), amount) in closing_balances(transactions.iter().copied()) {
+            if owner.owns(&node) {
+                known.entry(node).or_default().insert(date, amount);
+            }
+        }
+        for opening in &owner.opening_balances {
+            if let Some(day_before) = opening.date.pred_opt() {
+                known
+                    .entry(opening.node)
+                    .or_default()
+                    .entry(day_before)
+                    .or_insert(opening.amount);
+            }
+        }
+
+        let dates = transactions.iter().map(|transaction| transaction.date);
+        let known_dates = known.values().flat_map(|balances| balances.keys().copied());
+        let days: Vec<NaiveDate> = match dates.chain(known_dates).minmax().into_option() {
+            Some((first, last)) => first.iter_days().take_while(|day| *day <= last).collect(),
+            None => Vec::new(),
+        };
+
+        let mut timeline = Timeline::default();
+        for node in &owner.owns {
+            let id = node.id();
+            let moved = movements.remove(&id).unwrap_or_default();
+            let known = known.remove(&id).unwrap_or_default();
+            let moved_on = |day: &NaiveDate| moved.get(day).copied().unwrap_or_default();
+            // The balance before the first day.
+            let mut balance = match known.first_key_value() {
+                Some((date, amount)) => {
+                    let before: Decimal = days
+                        .iter()
+                        .take_while(|day| *day <= date)
+                        .map(moved_on)
+                        .sum();
+                    amount - before
+                }
+                None => Decimal::ZERO,
+            };
+            let balances = days
+                .iter()
+                .map(|day| {
+                    balance = known.get(day).copied().unwrap_or(balance + moved_on(day));
+                    (*day, balance)
+                })
+                .collect();
+            timeline.nodes.insert(
+                id,
+                Series {
+                    anchored: !known.is_empty(),
+                    balances,
+                },
+            );
+        }
+        for series in timeline.nodes.values() {
+            for (day, balance) in &series.balances {
+                *timeline.net_worth.entry(*day).or_default() += balance;
+            }
+        }
+        timeline
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::canonical::{fixtures::*, state::OpeningBalance};
+        use rust_decimal_macros::dec;
+
+        /// Three transactions of the checking account over two days, the first day out of
+        /// order, with the balance after the last one.
+        fn chain(last_balance: Decimal) -> Vec<Transaction> {
+            vec![
+                with_balance(
+                    transaction(date(2024, 1, 2), &shop(), &checking(), dec!(5)),
+                    &checking(),
+                    dec!(95),
+                ),
+                with_balance(
+                    transaction(date(2024, 1, 2), &checking(), &shop(), dec!(10)),
+                    &checking(),
+                    dec!(90),
+                ),
+                with_balance(
+                    transaction(date(2024, 1, 3), &checking(), &shop(), dec!(20)),
+                    &checking(),
+                    last_balance,
+                ),
+            ]
+        }
+
+        #[test]
+        fn unbroken_chain_in_any_order_within_a_day() {
+            assert!(check_chain(&chain(dec!(75))).is_empty());
+        }
+
+        #[test]
+        fn missing_transaction_breaks_chain() {
+            let transactions = chain(dec!(70));
+            let breaks = check_chain(&transactions);
+            let [gap] = &breaks[..] else {
+                panic!("expecting one break, got {:?}", breaks)
+            };
+            assert_eq!(gap.account, checking().id());
+            assert_eq!(gap.previous, &transactions[0]);
+            assert_eq!(gap.next, &transactions[2]);
+            assert_eq!(gap.expected, dec!(75));
+            assert_eq!(gap.actual, dec!(70));
+            assert_eq!(gap.difference(), dec!(-5));
+        }
+
+        #[test]
+        fn closing_balance_ends_the_days_chain() {
+            let closing = closing_balances(&chain(dec!(75)));
+            assert_eq!(closing[&(date(2024, 1, 2), checking().id())], dec!(95));
+            assert_eq!(closing[&(date(2024, 1, 3), checking().id())], dec!(75));
+        }
+
+        fn series(timeline: &Timeline, node: &Node) -> Vec<Decimal> {
+            timeline.nodes[&node.id()]
+                .balances
+                .values()
+                .copied()
+                .collect()
+        }
+
+        #[test]
+        fn timeline_anchors_on_bank_and_opening_balances() {
+            let mut owner = owner(vec![checking(), savings(), joint()]);
+            owner.opening_balances = vec![OpeningBalance {
+                node: savings().id(),
+                date: date(2024, 1, 1),
+                amount: dec!(1000),
+            }];
+            let transactions = [
+                with_balance(
+                    transaction(date(2024, 1, 2), &shop(), &checking(), dec!(100)),
+                    &checking(),
+                    dec!(600),
+                ),
+                with_balance(
+                    transaction(date(2024, 1, 3), &checking(), &savings(), dec!(200)),
+                    &checking(),
+                    dec!(400),
+                ),
+                with_balance(
+                    transaction(date(2024, 1, 5), &checking(), &shop(), dec!(50)),
+                    &checking(),
+                    dec!(350),
+                ),
+            ];
+            let timeline = timeline(&transactions, &owner);
+
+            // From the day before the opening balance to the last transaction.
+            let days: Vec<NaiveDate> = timeline.net_worth.keys().copied().collect();
+            assert_eq!(days.first(), Some(&date(2023, 12, 31)));
+            assert_eq!(days.last(), Some(&date(2024, 1, 5)));
+
+            // Worked out backwards from the first balance the bank reported.
+            assert_eq!(
+                series(&timeline, &checking()),
+                [
+                    dec!(500),
+                    dec!(500),
+                    dec!(600),
+                    dec!(400),
+                    dec!(400),
+                    dec!(350)
+                ]
+            );
+            // The sub-account follows the transfers to it, from its opening balance.
+            assert_eq!(
+                series(&timeline, &savings()),
+                [
+                    dec!(1000),
+                    dec!(1000),
+                    dec!(1000),
+                    dec!(1200),
+                    dec!(1200),
+                    dec!(1200)
+                ]
+            );
+            let joint = &timeline.nodes[&joint().id()];
+            assert!(!joint.anchored);
+            assert!(joint.balances.values().all(Decimal::is_zero));
+            assert!(timeline.nodes[&savings().id()].anchored);
+
+            assert_eq!(timeline.net_worth[&date(2023, 12, 31)], dec!(1500));
+            assert_eq!(timeline.net_worth[&date(2024, 1, 5)], dec!(1550));
+        }
+
+        #[test]
+        fn timeline_counts_both_legs_of_a_transfer_once() {
+            let owner = owner(vec![checking(), joint()]);
+            let transactions = [
+                with_balance(
+                    transaction(date(2024, 1, 2), &checking(), &joint(), dec!(100)),
+                    &checking(),
+                    dec!(400),
+                ),
+                with_balance(
+                    transaction(date(2024, 1, 3), &checking(), &joint(), dec!(100)),
+                    &joint(),
+                    dec!(150),
+                ),
+            ];
+            let timeline = timeline(&transactions, &owner);
+            assert_eq!(series(&timeline, &checking()), [dec!(400), dec!(400)]);
+            assert_eq!(series(&timeline, &joint()), [dec!(50), dec!(150)]);
+        }
+    }
+}
+
+pub mod transfers {
+    use crate::canonical::{identify::*, state::Owner, transaction::*};
+    use chrono::Days;
+    use rust_decimal::Decimal;
+    use std::collections::HashMap;
+
+    /// How far apart the two legs of a transfer can be booked: money sent on a Friday
+    /// evening may only arrive on Monday.
+    pub const WINDOW: Days = Days::new(4);
+
+    /// Source, sink and amount: what the two legs of a transfer have in common.
+    type Leg = (ID<Node>, ID<Node>, Decimal);
+
+    /// One movement of money between two accounts of the owner, as it appears in the
+    /// exports of both: once booked by each account.
+    ///
+    /// The legs are linked rather than merged into one transaction, because each
+    /// carries the balance of its own account.
+    #[derive(Debug)]
+    pub struct Transfer<'t> {
+        /// The leg booked by the account the money left.
+        pub sent: &'t Transaction,
+        /// The leg booked by the account the money arrived at.
+        pub received: &'t Transaction,
+    }
+    impl<'t> Transfer<'t> {
+        /// The leg with the balance of the source is the sent one. Without balances,
+        /// the earlier one is.
+        fn new(earlier: &'t Transaction, later: &'t Transaction) -> Self {
+            let booked_by_source = |transaction: &Transaction| {
+                transaction
+                    .balance
+                    .as_ref()
+                    .is_some_and(|balance| balance.of == transaction.source.id())
+            };
+            if booked_by_source(later) && !booked_by_source(earlier) {
+                Self {
+                    sent: later,
+                    received: earlier,
+                }
+            } else {
+                Self {
+                    sent: earlier,
+                    received: later,
+                }
+            }
+        }
+    }
+
+    /// Pair up the legs of transfers between the nodes of `owner`: transactions with the
+    /// same owned source, the same owned sink and the same amount, booked at most `window`
+    /// apart by different accounts. Every leg pairs with the nearest one in time.
+    ///
+    /// Legs without a balance can't tell which account booked them, so those pair with
+    /// any matching leg. Legs without a partner (the other account's export wasn't
+    /// imported) don't show up in the result.
+    pub fn match_transfers<'t, I>(transactions: I, owner: &Owner, window: Days) -> Vec<Transfer<'t>>
+    where
+        I: IntoIterator<Item = &'t Transaction>,
+    {
+        let mut candidates: HashMap<Leg, Vec<&'t Transaction>> = HashMap::new();
+        for transaction in transactions {
+            if owner.is_internal(transaction) {
+                let key = (
+                    transaction.source.id(),
+                    transaction.sink.id(),
+                    transaction.amount.normalize(),
+                );
+                candidates.entry(key).or_default().push(transaction);
+            }
+        }
+
+        let mut transfers = Vec::new();
+        for mut legs in candidates.into_values() {
+            legs.sort_by_key(|leg| leg.date);
+            let mut paired = vec![false; legs.len()];
+            for earlier in 0..legs.len() {
+                if paired[earlier] {
+                    continue;
+                }
+                let partner = (earlier + 1..legs.len())
+                    .take_while(|&later| legs[later].date <= legs[earlier].date + window)
+                    .find(|&later| !paired[later] && booked_apart(legs[earlier], legs[later]));
+                if let Some(later) = partner {
+                    paired[earlier] = true;
+                    paired[later] = true;
+                    transfers.push(Transfer::new(legs[earlier], legs[later]));
+                }
+            }
+        }
+        transfers.sort_by_key(|transfer| (transfer.sent.date, transfer.sent.id().to_string()));
+        transfers
+    }
+
+    /// Whether `a` and `b` weren't booked by the same account, as far as is known.
+    fn booked_apart(a: &Transaction, b: &Transaction) -> bool {
+        match (&a.balance, &b.balance) {
+            (Some(a), Some(b)) => a.of != b.of,
+            _ => true,
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::canonical::fixtures::*;
+        use chrono::NaiveDate;
+        use rust_decimal_macros::dec;
+
+        /// 100 from checking to joint on `date`, as booked by `by`.
+        fn leg(date: NaiveDate, by: &Node) -> Transaction {
+            with_balance(
+                transaction(date, &checking(), &joint(), dec!(100)),
+                by,
+                dec!(500),
+            )
+        }
+
+        fn match_legs(transactions: &[Transaction]) -> Vec<Transfer<'_>> {
+            match_transfers(transactions, &owner(vec![checking(), joint()]), WINDOW)
+        }
+
+        #[test]
+        fn legs_within_window_match() {
+            // Booked by the receiving account first: still the checking leg is sent.
+            let transactions = [
+                leg(date(2024, 1, 2), &joint()),
+                leg(date(2024, 1, 5), &checking()),
+            ];
+            let transfers = match_legs(&transactions);
+            let [transfer] = &transfers[..] else {
+                panic!("expecting one transfer, got {:?}", transfers)
+            };
+            assert_eq!(transfer.sent, &transactions[1]);
+            assert_eq!(transfer.received, &transactions[0]);
+        }
+
+        #[test]
+        fn legs_outside_window_dont_match() {
+            let transactions = [
+                leg(date(2024, 1, 2), &checking()),
+                leg(date(2024, 1, 7), &joint()),
+            ];
+            assert!(match_legs(&transactions).is_empty());
+        }
+
+        #[test]
+        fn legs_booked_by_one_account_dont_pair() {
+            let transactions = [
+                leg(date(2024, 1, 2), &checking()),
+                leg(date(2024, 1, 3), &checking()),
+                leg(date(2024, 1, 3), &joint()),
+            ];
+            let transfers = match_legs(&transactions);
+            let [transfer] = &transfers[..] else {
+                panic!("expecting one transfer, got {:?}", transfers)
+            };
+            assert_eq!(transfer.sent, &transactions[0]);
+            assert_eq!(transfer.received, &transactions[2]);
+        }
+
+        #[test]
+        fn only_internal_transactions_match() {
+            let transactions = [
+                transaction(date(2024, 1, 2), &checking(), &shop(), dec!(100)),
+                transaction(date(2024, 1, 2), &checking(), &shop(), dec!(100)),
+            ];
+            assert!(match_legs(&transactions).is_empty());
+        }
+    }
+}
+
+pub mod recurring {
+    use crate::{
+        canonical::{identify::*, state::Owner, transaction::*},
+        from_files::serializers::sepa::SepaDescription,
+    };
+    use chrono::{Days, Months, NaiveDate};
+    use rust_decimal::Decimal;
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    /// Amounts of payments without a mandate belong to the same series when they differ
+    /// at most this fraction (20%) from the previous one.
+    const SIMILAR: Decimal = Decimal::from_parts(2, 0, 0, false, 1);
+
+    /// A series that missed this many occurrences in a row at the end has ended.
+    pub const ENDED_AFTER: usize = 3;
+
+    /// How often a recurring payment comes back.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Interval {
+        Weekly,
+        Monthly,
+        Quarterly,
+        Yearly,
+    }
+    impl Interval {
+        const ALL: [Self; 4] = [Self::Weekly, Self::Monthly, Self::Quarterly, Self::Yearly];
+
+        /// The date `times` intervals after `date`.
+        pub fn after(&self, date: NaiveDate, times: u32) -> NaiveDate {
+            match self {
+                Self::Weekly => date + Days::new(7 * u64::from(times)),
+                Self::Monthly => date + Months::new(times),
+                Self::Quarterly => date + Months::new(3 * times),
+                Self::Yearly => date + Months::new(12 * times),
+            }
+        }
+
+        /// How many days an occurrence may be early or late: collections move to the
+        /// next working day, and invoices aren't always sent on the same day.
+        fn tolerance(&self) -> Days {
+            Days::new(match self {
+                Self::Weekly => 1,
+                Self::Monthly => 4,
+                Self::Quarterly => 7,
+                Self::Yearly => 14,
+            })
+        }
+
+        /// The number of intervals from `from` to `to`, if `to` is (close to) a whole
+        /// number of them after `from`.
+        fn steps(&self, from: NaiveDate, to: NaiveDate) -> Option<u32> {
+            (1..)
+                .map(|times| (times, self.after(from, times)))
+                .take_while(|(_, expected)| *expected <= to + self.tolerance())
+                .find(|(_, expected)| *expected + self.tolerance() >= to)
+                .map(|(times, _)| times)
+        }
+    }
+
+    /// The amount of a recurring payment changed.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct PriceChange {
+        /// Of the first occurrence with the new amount.
+        pub date: NaiveDate,
+        pub from: Decimal,
+        pub to: Decimal,
+    }
+
+    /// Payments to or from one counterparty that come back at a regular interval, with
+    /// (roughly) the same amount: subscriptions, rent, salary.
+    #[derive(Debug)]
+    pub struct Recurring<'t> {
+        /// The node on the other side, see `Owner::counterparty`.
+        pub counterparty: &'t Node,
+        /// For the owner: `Outgoing` for payments, `Incoming` for income.
+        pub direction: Direction,
+        pub interval: Interval,
+        /// Mandate ID and creditor ID of a SEPA direct debit. Every mandate is a series of
+        /// its own, also when the creditor collects several.
+        pub mandate: Option<String>,
+        pub creditor: Option<String>,
+        /// The payments that keep to the interval, by date.
+        pub occurrences: Vec<&'t Transaction>,
+        /// Payments between occurrences that don't keep to the interval: a double
+        /// charge, a collection again after a reversal, a one-off extra. By date.
+        pub extra: Vec<&'t Transaction>,
+        pub price_changes: Vec<PriceChange>,
+        /// Dates an occurrence was expected on but didn't come, up to the last
+        /// transaction. Not the ones after the series ended.
+        pub missed: Vec<NaiveDate>,
+        /// Whether the last `ENDED_AFTER` expected occurrences (or more) didn't come.
+        pub ended: bool,
+        /// The first date after the last transaction that the next occurrence is
+        /// expected on. None when the series ended.
+        pub next_date: Option<NaiveDate>,
+        /// The amount of the last occurrence.
+        pub next_amount: Decimal,
+    }
+
+    /// Find the recurring payments of `owner` among `transactions`, ordered by their next
+    /// date, with the series that ended last. Internal transfers aren't payments, so they
+    /// don't count.
+    ///
+    /// Transactions are grouped by the `Node::id()` of the counterparty and the direction,
+    /// and by mandate for direct debits (see `Transaction::mandate`, or `SepaDescription`
+    /// for exports that only have it in the description). A mandate's amount can change freely. Without a mandate, a
+    /// payment only continues a series if its amount is close to the previous one, so
+    /// different products at one counterparty make different series.
+    pub fn detect<'t, I>(transactions: I, owner: &Owner) -> Vec<Recurring<'t>>
+    where
+        I: IntoIterator<Item = &'t Transaction>,
+    {
+        type Key = (ID<Node>, Direction, Option<String>);
+        let mut groups: HashMap<Key, (&'t Node, Option<String>, Vec<&'t Transaction>)> =
+            HashMap::new();
+        let mut end = None;
+        for transaction in transactions {
+            end = end.max(Some(transaction.date));
+            let direction = match (
+                owner.owns(&transaction.source.id()),
+                owner.owns(&transaction.sink.id()),
+            ) {
+                (true, false) => Direction::Outgoing,
+                (false, true) => Direction::Incoming,
+                _ => continue,
+            };
+            let counterparty = owner.counterparty(transaction);
+            let (mandate, creditor) = match &transaction.mandate {
+                Some(mandate) => (Some(mandate.id.clone()), mandate.creditor.clone()),
+                None => {
+                    let sepa = SepaDescription::parse(&transaction.description);
+                    (sepa.mandate, sepa.creditor)
+                }
+            };
+            groups
+                .entry((counterparty.id(), direction, mandate))
+                .or_insert_with(|| (counterparty, creditor, Vec::new()))
+                .2
+                .push(transaction);
+        }
+        let Some(end) = end else {
+            return Vec::new();
+        };
+
+        let mut found = Vec::new();
+        for ((_, direction, mandate), (counterparty, creditor, mut payments)) in groups {
+            payments.sort_by_key(|payment| payment.date);
+            let series = match mandate {
+                Some(_) => vec![payments],
+                None => by_amount(payments),
+            };
+            for payments in series {
+                let Some((interval, fit)) = fit(&payments, mandate.is_some()) else {
+                    continue;
+                };
+                found.push(recurring(
+                    counterparty,
+                    direction,
+                    interval,
+                    (mandate.clone(), creditor.clone()),
+                    fit,
+                    end,
+                ));
+            }
+        }
+        found.sort_by_key(|recurring| {
+            (
+                recurring.next_date.is_none(),
+                recurring.next_date,
+                recurring.counterparty.id().to_string(),
+            )
+        });
+        found
+    }
+
+    /// Split `payments` (by date) into series of similar amounts. A payment continues the
+    /// series whose last amount is closest to its own, if that's close enough.
+    fn by_amount(payments: Vec<&Transaction>) -> Vec<Vec<&Transaction>> {
+        let mut series: Vec<Vec<&Transaction>> = Vec::new();
+        for payment in payments {
+            let difference = |last: &Transaction| (payment.amount - last.amount).abs();
+            let closest = series
+                .iter_mut()
+                .filter(|series| {
+                    let last = series.last().unwrap();
+                    difference(last) <= last.amount * SIMILAR
+                })
+                .min_by_key(|series| difference(series.last().unwrap()));
+            match closest {
+                Some(series) => series.push(payment),
+                None => series.push(vec![payment]),
+            }
+        }
+        series
+    }
+
+    /// `payments` split into the ones that keep to an interval and the extra ones.
+    struct Fit<'t> {
+        occurrences: Vec<&'t Transaction>,
+        extra: Vec<&'t Transaction>,
+        /// Number of steps of the interval between every two occurrences. None for gaps
+        /// that aren't a whole number of them: the schedule moved.
+        steps: Vec<Option<u32>>,
+    }
+
+    /// Walk `payments` (by date) with `interval`. A payment within the tolerance of the
+    /// previous occurrence is extra, like one off the schedule when the payment after it
+    /// is on schedule again (or there is none).
+    fn fit_with<'t>(interval: Interval, payments: &[&'t Transaction]) -> Fit<'t> {
+        let mut fit = Fit {
+            occurrences: payments.iter().copied().take(1).collect(),
+            extra: Vec::new(),
+            steps: Vec::new(),
+        };
+        for (index, payment) in payments.iter().enumerate().skip(1) {
+            let previous = fit.occurrences.last().unwrap().date;
+            if payment.date <= previous + interval.tolerance() {
+                fit.extra.push(payment);
+                continue;
+            }
+            let steps = interval.steps(previous, payment.date);
+            let back_on_schedule = || {
+                payments
+                    .get(index + 1)
+                    .is_none_or(|next| interval.steps(previous, next.date).is_some())
+            };
+            if steps.is_none() && back_on_schedule() {
+                fit.extra.push(payment);
+            } else {
+                fit.occurrences.push(payment);
+                fit.steps.push(steps);
+            }
+        }
+        fit
+    }
+
+    /// The shortest interval that `payments` keep to: most gaps between occurrences are
+    /// a whole number of it, and at least half of those are exactly one.
+    ///
+    /// Fewer than three occurrences can't tell a series from chance, unless they are
+    /// collected with a mandate: then two are enough.
+    fn fit<'t>(payments: &[&'t Transaction], mandate: bool) -> Option<(Interval, Fit<'t>)> {
+        let minimum = if mandate { 2 } else { 3 };
+        Interval::ALL.into_iter().find_map(|interval| {
+            let fit = fit_with(interval, payments);
+            let fitting: Vec<u32> = fit.steps.iter().flatten().copied().collect();
+            let single = fitting.iter().filter(|steps| **steps == 1).count();
+            let keeps_to = fit.occurrences.len() >= minimum
+                && 2 * fitting.len() >= fit.steps.len()
+                && single > 0
+                && 2 * single >= fitting.len();
+            keeps_to.then_some((interval, fit))
+        })
+    }
+
+    fn recurring<'t>(
+        counterparty: &'t Node,
+        direction: Direction,
+        interval: Interval,
+        (mandate, creditor): (Option<String>, Option<String>),
+        fit: Fit<'t>,
+        end: NaiveDate,
+    ) -> Recurring<'t> {
+        let Fit {
+            occurrences,
+            extra,
+            steps,
+        } = fit;
+        let mut price_changes = Vec::new();
+        let mut missed = Vec::new();
+        for (pair, steps) in occurrences.windows(2).zip(steps) {
+            let (previous, next) = (pair[0], pair[1]);
+            if previous.amount != next.amount {
+                price_changes.push(PriceChange {
+                    date: next.date,
+                    from: previous.amount,
+                    to: next.amount,
+                });
+            }
+            missed
+                .extend((1..steps.unwrap_or(1)).map(|times| interval.after(previous.date, times)));
+        }
+        let last = occurrences.last().unwrap();
+        let overdue: Vec<NaiveDate> = (1..)
+            .map(|times| interval.after(last.date, times))
+            .take_while(|expected| *expected + interval.tolerance() < end)
+            .collect();
+        let ended = overdue.len() >= ENDED_AFTER;
+        let next_date = match ended {
+            true => None,
+            false => {
+                let next_date = interval.after(last.date, overdue.len() as u32 + 1);
+                missed.extend(overdue);
+                Some(next_date)
+            }
+        };
+        Recurring {
+            counterparty,
+            direction,
+            interval,
+            mandate,
+            creditor,
+            extra,
+            price_changes,
+            missed,
+            ended,
+            next_date,
+            next_amount: last.amount,
+            occurrences,
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::canonical::fixtures::*;
+        use rust_decimal_macros::dec;
+
+        fn monthly(months: std::ops::RangeInclusive<u32>, day: u32) -> Vec<Transaction> {
+            months
+                .map(|month| transaction(date(2024, month, day), &checking(), &shop(), dec!(10)))
+                .collect()
+        }
+
+        #[test]
+        fn same_day_duplicate_is_extra() {
+            let mut transactions = monthly(1..=5, 15);
+            transactions.push(transaction(
+                date(2024, 3, 15),
+                &checking(),
+                &shop(),
+                dec!(10),
+            ));
+            let found = detect(&transactions, &owner(vec![checking()]));
+            assert_eq!(found.len(), 1);
+            let series = &found[0];
+            assert_eq!(series.interval, Interval::Monthly);
+            assert_eq!(series.direction, Direction::Outgoing);
+            assert_eq!(series.occurrences.len(), 5);
+            assert_eq!(series.extra.len(), 1);
+            assert!(series.missed.is_empty());
+            assert_eq!(series.next_date, Some(date(2024, 6, 15)));
+        }
+
+        #[test]
+        fn irregular_payment_is_extra() {
+            let mut transactions = monthly(1..=6, 1);
+            transactions.push(transaction(
+                date(2024, 3, 20),
+                &checking(),
+                &shop(),
+                dec!(11),
+            ));
+            let found = detect(&transactions, &owner(vec![checking()]));
+            assert_eq!(found.len(), 1);
+            let series = &found[0];
+            assert_eq!(series.interval, Interval::Monthly);
+            assert_eq!(series.occurrences.len(), 6);
+            assert_eq!(series.extra[0].date, date(2024, 3, 20));
+            assert!(series.price_changes.is_empty());
+            assert!(series.missed.is_empty());
+        }
+
+        #[test]
+        fn price_change_under_mandate() {
+            let transactions: Vec<Transaction> = [(1, dec!(10)), (2, dec!(10)), (3, dec!(12.50))]
+                .into_iter()
+                .map(|(month, amount)| Transaction {
+                    description: String::from("Mandate ID: M-1 Creditor ID: NL00ZZZ123"),
+                    ..transaction(date(2024, month, 5), &checking(), &shop(), amount)
+                })
+                .collect();
+            let found = detect(&transactions, &owner(vec![checking()]));
+            assert_eq!(found.len(), 1);
+            let series = &found[0];
+            assert_eq!(series.mandate.as_deref(), Some("M-1"));
+            assert_eq!(series.creditor.as_deref(), Some("NL00ZZZ123"));
+            assert_eq!(
+                series.price_changes,
+                vec![PriceChange {
+                    date: date(2024, 3, 5),
+                    from: dec!(10),
+                    to: dec!(12.50),
+                }]
+            );
+            assert_eq!(series.next_amount, dec!(12.50));
+        }
+
+        #[test]
+        fn every_mandate_is_a_series() {
+            // One mandate in the transaction's fields, one only in its description.
+            let transactions: Vec<Transaction> = [1, 2]
+                .into_iter()
+                .flat_map(|month| {
+                    [
+                        Transaction {
+                            mandate: Some(Mandate {
+                                id: String::from("M-1"),
+                                creditor: Some(String::from("NL00ZZZ123")),
+                            }),
+                            description: String::from("Mandate ID: M-9 Creditor ID: NL00ZZZ999"),
+                            ..transaction(date(2024, month, 5), &checking(), &shop(), dec!(10))
+                        },
+                        Transaction {
+                            description: String::from("Mandate ID: M-2 Creditor ID: NL00ZZZ123"),
+                            ..transaction(date(2024, month, 20), &checking(), &shop(), dec!(10))
+                        },
+                    ]
+                })
+                .collect();
+            let found = detect(&transactions, &owner(vec![checking()]));
+            let mandates: Vec<_> = found
+                .iter()
+                .map(|series| (series.mandate.as_deref(), series.occurrences.len()))
+                .collect();
+            assert_eq!(mandates, [(Some("M-1"), 2), (Some("M-2"), 2)]);
+            assert!(found.iter().all(|series| series.counterparty == &shop()));
+            assert_eq!(found[0].creditor.as_deref(), Some("NL00ZZZ123"));
+        }
+
+        #[test]
+        fn stopped_series_ended() {
+            let mut transactions = monthly(1..=4, 1);
+            transactions.push(transaction(
+                date(2024, 9, 1),
+                &checking(),
+                &joint(),
+                dec!(99),
+            ));
+            let found = detect(&transactions, &owner(vec![checking()]));
+            assert_eq!(found.len(), 1);
+            let series = &found[0];
+            assert!(series.ended);
+            assert_eq!(series.next_date, None);
+            assert!(series.missed.is_empty());
+        }
+    }
+}
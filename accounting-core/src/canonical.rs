@@ -0,0 +1,1838 @@
+pub mod transaction {
+    use super::{account::*, category::Category, identify::*, query::Query, state::Owner};
+    use chrono::NaiveDate;
+    use regex::Regex;
+    use rust_decimal::Decimal;
+    use serde::{Deserialize, Serialize};
+    use std::{
+        cmp::Ordering,
+        collections::{BTreeMap, HashMap, HashSet},
+        fmt::{Debug, Display},
+        marker::PhantomData,
+        ops::Range,
+        slice,
+        sync::LazyLock,
+    };
+
+    /// Represents a point between which money flows during transactions.
+    #[derive(Hash, Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+    pub enum Node {
+        /// A fully qualified bank account with an IBAN.
+        ProperAccount(Account),
+        /// A sub-account to a `ProperAccount`. Doesn't have its own IBAN.
+        /// Most likely a savings or brokerage account.
+        SubAccount(SubAccount),
+        /// A payment terminal in a shop or restaurant or something. You used
+        /// your card or phone to pay something.
+        Terminal(String),
+        /// Make those numbers real and turn them into cold, hard cash.
+        Atm(String),
+        /// Other, hard to identify nodes. For example: bank charges for services, deposits.
+        Other(String),
+    }
+    impl Node {
+        pub fn name(&self) -> String {
+            match self {
+                Node::Atm(_) => String::from("ATM"),
+                Node::ProperAccount(acc) => acc.name.to_string(),
+                Node::SubAccount(acc) => acc.name.to_string(),
+                Node::Terminal(_) => String::from("Payment Terminal"),
+                Node::Other(id) => id.clone(),
+            }
+        }
+        pub fn display_details(&self) -> String {
+            match self {
+                Node::Atm(id) => format!("^{}^ {} (ATM)", self.id(), id),
+                Node::ProperAccount(acc) => format!("{}", acc),
+                Node::SubAccount(acc) => format!("{}", acc),
+                Node::Terminal(id) => format!("*{}* {} (Payment Terminal)", self.id(), id),
+                Node::Other(id) => id.to_string(),
+            }
+        }
+    }
+    impl Identify for Node {
+        type IdType = Node;
+        /// Accounts encode like the account itself, so a `Node` shares its ID
+        /// with the `Account` or `SubAccount` it wraps.
+        fn encode(&self, encoder: &mut IdEncoder) {
+            match self {
+                Node::ProperAccount(acc) => acc.encode(encoder),
+                Node::SubAccount(acc) => acc.encode(encoder),
+                Node::Terminal(id) | Node::Atm(id) | Node::Other(id) => {
+                    encoder.str(id);
+                }
+            }
+        }
+    }
+    impl Display for Node {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}", self.clone().display_details())
+        }
+    }
+
+    /// Denotes the direction of the transaction. Can only be determined with respect to a specific node.
+    #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize)]
+    pub enum Direction {
+        /// AKA: "Credit", "Bij"
+        Incoming,
+        /// AKA: "Debit", "Af"
+        Outgoing,
+    }
+
+    /// Remove transactions with the ID of an earlier one from the vector, keeping the
+    /// first of each in its original place. The store deduplicates by ID as well.
+    fn deduplicate(transactions: &mut Vec<Transaction>) -> &mut Vec<Transaction> {
+        let first: Vec<bool> = {
+            let mut seen = HashSet::new();
+            transactions.iter().map(|t| seen.insert(t.id())).collect()
+        };
+        let mut first = first.into_iter();
+        transactions.retain(|_| first.next().unwrap_or(false));
+        transactions
+    }
+
+    fn date_increasing(a: &Transaction, b: &Transaction) -> Ordering {
+        a.date.cmp(&b.date)
+    }
+
+    /// Within each day, put the transactions of one account that have a sequence number
+    /// in the order of their number. They trade places among the positions they already
+    /// had: everything else keeps the order it had (i.e.: the order of the files).
+    /// Numbers of different accounts are never compared.
+    ///
+    /// `transactions` must be sorted by date.
+    fn follow_sequences(transactions: &mut Vec<Transaction>) {
+        let mut order: Vec<usize> = (0..transactions.len()).collect();
+        let mut start = 0;
+        for day in transactions.chunk_by(|a, b| a.date == b.date) {
+            let mut accounts: HashMap<ID<Node>, Vec<(u64, usize)>> = HashMap::new();
+            for (index, transaction) in day.iter().enumerate() {
+                if let Some(sequence) = &transaction.sequence {
+                    accounts
+                        .entry(sequence.of)
+                        .or_default()
+                        .push((sequence.number, start + index));
+                }
+            }
+            for mut entries in accounts.into_values() {
+                let positions: Vec<usize> = entries.iter().map(|(_, index)| *index).collect();
+                entries.sort();
+                for (position, (_, index)) in positions.into_iter().zip(entries) {
+                    order[position] = index;
+                }
+            }
+            start += day.len();
+        }
+
+        let mut slots: Vec<Option<Transaction>> = transactions.drain(..).map(Some).collect();
+        transactions.extend(order.into_iter().filter_map(|index| slots[index].take()));
+    }
+
+    /// A SEPA direct debit mandate: the permission of the debtor for `creditor` to collect.
+    #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+    pub struct Mandate {
+        /// Mandate ID, unique per creditor.
+        pub id: String,
+        /// Creditor ID, e.g.: "NL00ZZZ000000000000".
+        pub creditor: Option<String>,
+    }
+
+    /// Position of a transaction in the bank's own bookkeeping of account `of`, for
+    /// exports that provide one (e.g.: Rabobank's "Volgnr"). Increases with every
+    /// transaction of the account, so it orders its transactions on the same day exactly.
+    #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+    pub struct Sequence {
+        pub of: ID<Node>,
+        pub number: u64,
+    }
+
+    /// Number the transactions of a single export that share an ID, so they get an ID of
+    /// their own (see `Transaction::occurrence`). Identical transactions are numbered in
+    /// the order of their balance chain when they have a balance, which is the order they
+    /// happened in, and in the order of `transactions` otherwise.
+    pub fn number_occurrences(transactions: &mut [Transaction]) {
+        let mut identical: HashMap<ID<Transaction>, Vec<usize>> = HashMap::new();
+        for (index, transaction) in transactions.iter_mut().enumerate() {
+            transaction.occurrence = 0;
+            identical.entry(transaction.id()).or_default().push(index);
+        }
+        for indices in identical.into_values().filter(|indices| indices.len() > 1) {
+            let entries = indices.iter().map(|&index| (index, &transactions[index]));
+            let chain: Vec<usize> = chain_order(entries.collect(), None, |(_, t)| t)
+                .into_iter()
+                .map(|(index, _)| index)
+                .collect();
+            for (occurrence, index) in chain.into_iter().enumerate() {
+                transactions[index].occurrence = occurrence as u64;
+            }
+        }
+    }
+
+    /// Reorder the transactions of every day so the balance chain of every account
+    /// runs without gaps, continuing from the previous day. Only the transactions
+    /// of one account trade places, among the positions they already had: everything
+    /// else, transactions without a balance included, keeps the order it had (i.e.:
+    /// the order of the files), as do transactions that don't fit the chain.
+    ///
+    /// `transactions` must be sorted by date.
+    fn chain_days(transactions: &mut Vec<Transaction>) {
+        let mut order: Vec<usize> = (0..transactions.len()).collect();
+        let mut after: HashMap<ID<Node>, Decimal> = HashMap::new();
+        let mut start = 0;
+        for day in transactions.chunk_by(|a, b| a.date == b.date) {
+            let mut accounts: HashMap<ID<Node>, Vec<(usize, &Transaction)>> = HashMap::new();
+            for (index, transaction) in day.iter().enumerate() {
+                if let Some(balance) = &transaction.balance {
+                    accounts
+                        .entry(balance.of)
+                        .or_default()
+                        .push((start + index, transaction));
+                }
+            }
+            for (account, entries) in accounts {
+                let positions: Vec<usize> = entries.iter().map(|(index, _)| *index).collect();
+                let chain = chain_order(entries, after.get(&account).copied(), |(_, t)| t);
+                if let Some((_, last)) = chain.last() {
+                    after.extend(last.balance.as_ref().map(|b| (account, b.amount)));
+                }
+                for (position, (index, _)) in positions.into_iter().zip(chain) {
+                    order[position] = index;
+                }
+            }
+            start += day.len();
+        }
+
+        let mut slots: Vec<Option<Transaction>> = transactions.drain(..).map(Some).collect();
+        transactions.extend(order.into_iter().filter_map(|index| slots[index].take()));
+    }
+
+    /// Put the transactions of one account on one day in the order of their balance chain,
+    /// starting from the balance `after` the previous day. Transactions that don't fit the
+    /// chain keep their relative order. `transaction` gets the transaction out of an item.
+    pub fn chain_order<T>(
+        mut day: Vec<T>,
+        mut after: Option<Decimal>,
+        transaction: impl Fn(&T) -> &Transaction,
+    ) -> Vec<T> {
+        let closing = |item: &T| transaction(item).balance.as_ref().map(|b| b.amount);
+        let opening = |item: &T| transaction(item).balance_before();
+        let mut ordered = Vec::with_capacity(day.len());
+        while !day.is_empty() {
+            let continues =
+                after.and_then(|after| day.iter().position(|item| opening(item) == Some(after)));
+            // Otherwise the start of a chain: a transaction that no other one leads up to.
+            let starts = || {
+                day.iter().position(|item| {
+                    let opening = opening(item);
+                    !day.iter().any(|other| closing(other) == opening)
+                })
+            };
+            let next = day.remove(continues.or_else(starts).unwrap_or(0));
+            after = closing(&next);
+            ordered.push(next);
+        }
+        ordered
+    }
+
+    /// The balance of `of`, one of the nodes of a transaction, as reported by the bank.
+    #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+    pub struct Balance {
+        pub of: ID<Node>,
+        pub amount: Decimal,
+    }
+
+    /// A uniform representation of monetary transactions, decoupled from the format provided
+    /// by the bank transaction exports.
+    #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+    pub struct Transaction {
+        /// The date on which the transaction is registered.
+        pub date: NaiveDate,
+        /// The source of the money that is transferred in this transaction.
+        pub source: Node,
+        /// The destination of the money that is transferred in this transaction.
+        pub sink: Node,
+        /// The amount of money that is transferred in this transaction. Always positive: sign is only meaningful
+        /// with respect to one of the participating nodes.
+        pub amount: Decimal,
+        /// A set of tags that can be derived directly from the data of the raw csv transaction.
+        pub inherent_tags: String,
+        /// An inconsistantly formatted string describing some properties of the transaction.
+        pub description: String,
+        /// Position of the transaction in the bank's bookkeeping of one of its nodes.
+        pub sequence: Option<Sequence>,
+        /// The bank's own reference to the transaction, for exports that provide one
+        /// (e.g.: camt.053's "AcctSvcrRef", OFX's "FITID"). Tells apart transactions that
+        /// are otherwise identical, while overlapping downloads still deduplicate.
+        pub reference: Option<String>,
+        /// The reference the initiator gave the transaction, which travels with it from
+        /// bank to bank.
+        pub end_to_end: Option<String>,
+        /// The mandate a direct debit was collected under. Not part of the ID: every
+        /// collection under the mandate has the same.
+        pub mandate: Option<Mandate>,
+        /// Balance of the exported account right after this transaction, for exports that
+        /// provide one (e.g.: ING's "Resulting balance"). Not part of the ID: it describes
+        /// the account, not the transaction.
+        pub balance: Option<Balance>,
+        /// The bank's code for the kind of transaction, for exports that provide one
+        /// (e.g.: ING's "GT" for online banking, OFX's "POS"). Not part of the ID: it's
+        /// how the bank booked the transaction.
+        pub code: Option<String>,
+        /// How many transactions identical to this one came before it in the same export:
+        /// two coffees of the same price at the same terminal on the same day are two
+        /// transactions. The same row in overlapping exports gets the same number, so it
+        /// still deduplicates. See `number_occurrences`.
+        pub occurrence: u64,
+        /// Tags the owner's rules added, each with the name of the rule that added it.
+        /// Set by `rules::apply`, not part of the ID.
+        pub rule_tags: BTreeMap<String, String>,
+        /// The owner's category for the transaction, if any. Set by `rules::apply`, not
+        /// part of the ID.
+        pub category: Option<Category>,
+    }
+    impl Identify for Transaction {
+        type IdType = Self;
+        fn encode(&self, encoder: &mut IdEncoder) {
+            encoder
+                .str(&self.date.format("%Y-%m-%d").to_string())
+                .id(self.source.id())
+                .id(self.sink.id())
+                .str(&self.amount.normalize().to_string())
+                .str(&self.inherent_tags)
+                .str(&self.description)
+                // The account is the source or sink, so just its number.
+                .optional(1, self.sequence.as_ref(), |encoder, sequence| {
+                    encoder.u64(sequence.number)
+                })
+                .optional(2, self.reference.as_deref(), IdEncoder::str)
+                .optional(3, self.end_to_end.as_deref(), IdEncoder::str)
+                // The first of identical transactions keeps the ID it had on its own.
+                .optional(4, Some(self.occurrence).filter(|&n| n > 0), IdEncoder::u64);
+        }
+    }
+    impl Transaction {
+        /// Return a set containing tags that can be derived from the transaction itself, and
+        /// the tags the owner's rules added. Empty set if there are no tags.
+        pub fn tags(&self) -> HashSet<String> {
+            // Compiled once: views filter on tags for every transaction.
+            static TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(#.+?)\b").unwrap());
+            TAG.find_iter(self.inherent_tags.as_str())
+                .map(|m| String::from(m.as_str().trim()))
+                .chain(self.rule_tags.keys().cloned())
+                .collect()
+        }
+        /// Return `None` if `perspective.id()` doesn't match either `Incoming == sink.id()` or `Outgoing == source.id()`.
+        pub fn direction(&self, perspective: &Node) -> Option<Direction> {
+            if self.sink.id() == perspective.id() {
+                Some(Direction::Incoming)
+            } else if self.source.id() == perspective.id() {
+                Some(Direction::Outgoing)
+            } else {
+                None
+            }
+        }
+        /// The balance of the account `balance` is of, right before this transaction.
+        pub fn balance_before(&self) -> Option<Decimal> {
+            let balance = self.balance.as_ref()?;
+            let amount = if self.sink.id() == balance.of {
+                self.amount
+            } else {
+                -self.amount
+            };
+            Some(balance.amount - amount)
+        }
+    }
+
+    /// Represents a view on transactions: a selection of the transactions that were read,
+    /// in the order they were read in. Views borrow the transactions rather than copy them,
+    /// so slicing the same transactions many ways is cheap.
+    pub trait View<'a> {
+        /// Every transaction the view selects from.
+        fn all(&'a self) -> &'a [Transaction];
+        /// Increasing positions in `all()` of the transactions in the view. `None` when
+        /// the view has all of them.
+        fn selection(&'a self) -> Option<&'a [usize]>;
+    }
+
+    /// A list of unique Transactions sorted by increasing date. Within a day, transactions
+    /// follow the bank's sequence numbers and balance chains where they are known, and
+    /// the order they were read in otherwise.
+    pub struct Transactions<'a, Perspective = Source<'a>>
+    where
+        Perspective: View<'a>,
+    {
+        perspective: Perspective,
+        marker: PhantomData<&'a ()>,
+    }
+
+    /// Base perspective to have on transactions. Just everything as is.
+    pub struct Source<'a> {
+        data: Box<[Transaction]>,
+        marker: PhantomData<&'a ()>,
+    }
+    impl<'a> View<'a> for Source<'a> {
+        fn all(&'a self) -> &'a [Transaction] {
+            &self.data
+        }
+        fn selection(&'a self) -> Option<&'a [usize]> {
+            None
+        }
+    }
+    impl<'a, Perspective: View<'a>> Transactions<'a, Perspective> {
+        pub fn iter(&'a self) -> Iter<'a> {
+            let all = self.perspective.all();
+            let positions = match self.perspective.selection() {
+                Some(selection) => Positions::Selected(selection.iter()),
+                None => Positions::All(0..all.len()),
+            };
+            Iter { all, positions }
+        }
+        pub fn len(&'a self) -> usize {
+            self.iter().len()
+        }
+        pub fn is_empty(&'a self) -> bool {
+            self.len() == 0
+        }
+        /// The transactions of this view for which `by` holds, in the same order. The
+        /// result is a view too, so filters chain. Only positions are stored: the
+        /// transactions themselves stay where they are.
+        pub fn filter<F: Fn(&Transaction) -> bool>(
+            &'a self,
+            by: F,
+        ) -> Transactions<'a, Filtered<'a>> {
+            let all = self.perspective.all();
+            let selection = self.iter().positions.filter(|&position| by(&all[position]));
+            Transactions {
+                perspective: Filtered {
+                    all,
+                    selection: selection.collect(),
+                },
+                marker: PhantomData,
+            }
+        }
+        /// The transactions of this view that match `query`, from `owner`'s perspective.
+        pub fn query(&'a self, query: &Query, owner: &Owner) -> Transactions<'a, Filtered<'a>> {
+            self.filter(|transaction| query.matches(transaction, owner))
+        }
+    }
+    impl<'a, Perspective: View<'a>> IntoIterator for &'a Transactions<'a, Perspective> {
+        type Item = &'a Transaction;
+        type IntoIter = Iter<'a>;
+        fn into_iter(self) -> Iter<'a> {
+            self.iter()
+        }
+    }
+    impl<'a> Transactions<'a, Source<'a>> {
+        pub fn new(mut transactions: Vec<Transaction>) -> Self {
+            deduplicate(&mut transactions);
+            transactions.sort_by(date_increasing);
+            follow_sequences(&mut transactions);
+            chain_days(&mut transactions);
+
+            Self {
+                perspective: Source {
+                    data: transactions.into_boxed_slice(),
+                    marker: PhantomData,
+                },
+                marker: PhantomData,
+            }
+        }
+        /// Every transaction, as a slice.
+        pub fn data(&'a self) -> &'a [Transaction] {
+            &self.perspective.data
+        }
+        /// For data derived from the transactions, like `rule_tags`. Changing what makes up
+        /// the ID, or the order, breaks the properties of `Transactions`.
+        pub(crate) fn data_mut(&mut self) -> &mut [Transaction] {
+            &mut self.perspective.data
+        }
+    }
+
+    /// The transactions of another view for which a filter holds, by position.
+    pub struct Filtered<'a> {
+        all: &'a [Transaction],
+        selection: Box<[usize]>,
+    }
+    impl<'a> View<'a> for Filtered<'a> {
+        fn all(&'a self) -> &'a [Transaction] {
+            self.all
+        }
+        fn selection(&'a self) -> Option<&'a [usize]> {
+            Some(&self.selection)
+        }
+    }
+
+    /// The transactions of a view, in order. See `Transactions::iter`.
+    #[derive(Clone)]
+    pub struct Iter<'a> {
+        all: &'a [Transaction],
+        positions: Positions<'a>,
+    }
+    #[derive(Clone)]
+    enum Positions<'a> {
+        All(Range<usize>),
+        Selected(slice::Iter<'a, usize>),
+    }
+    impl Iterator for Positions<'_> {
+        type Item = usize;
+        fn next(&mut self) -> Option<usize> {
+            match self {
+                Positions::All(range) => range.next(),
+                Positions::Selected(selection) => selection.next().copied(),
+            }
+        }
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            match self {
+                Positions::All(range) => range.size_hint(),
+                Positions::Selected(selection) => selection.size_hint(),
+            }
+        }
+    }
+    impl DoubleEndedIterator for Positions<'_> {
+        fn next_back(&mut self) -> Option<usize> {
+            match self {
+                Positions::All(range) => range.next_back(),
+                Positions::Selected(selection) => selection.next_back().copied(),
+            }
+        }
+    }
+    impl<'a> Iterator for Iter<'a> {
+        type Item = &'a Transaction;
+        fn next(&mut self) -> Option<&'a Transaction> {
+            self.positions.next().map(|position| &self.all[position])
+        }
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            self.positions.size_hint()
+        }
+    }
+    impl DoubleEndedIterator for Iter<'_> {
+        fn next_back(&mut self) -> Option<Self::Item> {
+            self.positions
+                .next_back()
+                .map(|position| &self.all[position])
+        }
+    }
+    impl ExactSizeIterator for Iter<'_> {}
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::canonical::fixtures::*;
+        use rust_decimal_macros::dec;
+
+        fn numbered(of: &Node, number: u64, amount: Decimal) -> Transaction {
+            Transaction {
+                sequence: Some(Sequence {
+                    of: of.id(),
+                    number,
+                }),
+                ..transaction(date(2024, 1, 2), of, &shop(), amount)
+            }
+        }
+
+        fn amounts<'a, P: View<'a>>(transactions: &'a Transactions<'a, P>) -> Vec<Decimal> {
+            transactions.iter().map(|t| t.amount).collect()
+        }
+
+        /// Payments from the checking account on one day, with the balance after each.
+        fn payments(balances: &[(Decimal, Decimal)]) -> Vec<Transaction> {
+            balances
+                .iter()
+                .map(|(amount, balance)| {
+                    with_balance(
+                        transaction(date(2024, 1, 2), &checking(), &shop(), *amount),
+                        &checking(),
+                        *balance,
+                    )
+                })
+                .collect()
+        }
+
+        fn balances<'t>(transactions: impl IntoIterator<Item = &'t Transaction>) -> Vec<Decimal> {
+            transactions
+                .into_iter()
+                .map(|transaction| transaction.balance.as_ref().unwrap().amount)
+                .collect()
+        }
+
+        #[test]
+        fn days_follow_sequence_numbers() {
+            let transactions = Transactions::new(vec![
+                numbered(&checking(), 8, dec!(2)),
+                transaction(date(2024, 1, 1), &checking(), &shop(), dec!(1)),
+                numbered(&checking(), 7, dec!(3)),
+            ]);
+            assert_eq!(amounts(&transactions), [dec!(1), dec!(3), dec!(2)]);
+        }
+
+        #[test]
+        fn sequence_numbers_of_different_accounts_are_not_compared() {
+            let transactions = Transactions::new(vec![
+                numbered(&joint(), 9, dec!(1)),
+                numbered(&checking(), 8, dec!(2)),
+                transaction(date(2024, 1, 2), &checking(), &shop(), dec!(3)),
+                numbered(&joint(), 3, dec!(4)),
+                numbered(&checking(), 2, dec!(5)),
+            ]);
+            assert_eq!(
+                amounts(&transactions),
+                [dec!(4), dec!(5), dec!(3), dec!(1), dec!(2)]
+            );
+        }
+
+        #[test]
+        fn duplicates_are_removed_keeping_file_order() {
+            let first = transaction(date(2024, 1, 2), &checking(), &shop(), dec!(1));
+            let second = transaction(date(2024, 1, 2), &joint(), &shop(), dec!(2));
+            let transactions = Transactions::new(vec![first.clone(), second, first]);
+            assert_eq!(amounts(&transactions), [dec!(1), dec!(2)]);
+        }
+
+        #[test]
+        fn chain_order_starts_where_nothing_leads_up_to() {
+            let day = payments(&[
+                (dec!(3), dec!(85)),
+                (dec!(10), dec!(90)),
+                (dec!(2), dec!(88)),
+            ]);
+            let ordered = chain_order(day.iter().collect(), None, |transaction| transaction);
+            assert_eq!(balances(ordered), [dec!(90), dec!(88), dec!(85)]);
+        }
+
+        #[test]
+        fn chain_order_continues_from_previous_day() {
+            // Both 90 -> 80 -> 90 and 80 -> 90 -> 80 are chains, the previous day decides.
+            let day = payments(&[(dec!(10), dec!(80)), (dec!(-10), dec!(90))]);
+            let ordered = chain_order(day.iter().collect(), Some(dec!(80)), |t| t);
+            assert_eq!(balances(ordered), [dec!(90), dec!(80)]);
+            let ordered = chain_order(day.iter().collect(), Some(dec!(90)), |t| t);
+            assert_eq!(balances(ordered), [dec!(80), dec!(90)]);
+        }
+
+        #[test]
+        fn chain_order_keeps_misfits_in_place() {
+            let day = payments(&[
+                (dec!(1), dec!(50)),
+                (dec!(2), dec!(88)),
+                (dec!(1), dec!(40)),
+            ]);
+            let ordered = chain_order(day.iter().collect(), Some(dec!(90)), |t| t);
+            assert_eq!(balances(ordered), [dec!(88), dec!(50), dec!(40)]);
+        }
+
+        #[test]
+        fn days_follow_balance_chains_around_other_transactions() {
+            let mut day = payments(&[(dec!(2), dec!(88)), (dec!(10), dec!(90))]);
+            day.insert(1, transaction(date(2024, 1, 2), &joint(), &shop(), dec!(7)));
+            let transactions = Transactions::new(day);
+            assert_eq!(amounts(&transactions), [dec!(10), dec!(7), dec!(2)]);
+        }
+
+        fn coffee() -> Transaction {
+            transaction(date(2024, 1, 2), &checking(), &shop(), dec!(3))
+        }
+
+        #[test]
+        fn identical_transactions_in_one_export_are_numbered() {
+            let mut export = vec![coffee(), coffee(), coffee()];
+            number_occurrences(&mut export);
+            let occurrences: Vec<u64> = export.iter().map(|t| t.occurrence).collect();
+            assert_eq!(occurrences, [0, 1, 2]);
+            assert_eq!(Transactions::new(export).iter().count(), 3);
+        }
+
+        #[test]
+        fn identical_transactions_are_numbered_in_chain_order() {
+            let mut export = payments(&[(dec!(3), dec!(94)), (dec!(3), dec!(97))]);
+            number_occurrences(&mut export);
+            assert_eq!(export[0].occurrence, 1);
+            assert_eq!(export[1].occurrence, 0);
+        }
+
+        /// A payment on each of the first `days` of January, of as many euros as the day.
+        fn daily<'a>(days: u32) -> Transactions<'a> {
+            Transactions::new(
+                (1..=days)
+                    .map(|day| {
+                        transaction(date(2024, 1, day), &checking(), &shop(), Decimal::from(day))
+                    })
+                    .collect(),
+            )
+        }
+
+        #[test]
+        fn filters_chain_and_keep_the_order() {
+            let transactions = daily(6);
+            let even = transactions.filter(|t| t.amount % dec!(2) == dec!(0));
+            assert_eq!(amounts(&even), [dec!(2), dec!(4), dec!(6)]);
+            let large = even.filter(|t| t.amount > dec!(3));
+            assert_eq!(amounts(&large), [dec!(4), dec!(6)]);
+            assert_eq!(large.len(), 2);
+            assert!(large.filter(|t| t.amount > dec!(6)).is_empty());
+        }
+
+        #[test]
+        fn views_iterate_from_both_ends() {
+            let transactions = daily(4);
+            let odd = transactions.filter(|t| t.amount % dec!(2) == dec!(1));
+            let mut iter = odd.iter();
+            assert_eq!(iter.len(), 2);
+            assert_eq!(iter.next_back().map(|t| t.amount), Some(dec!(3)));
+            assert_eq!(iter.next().map(|t| t.amount), Some(dec!(1)));
+            assert!(iter.next().is_none());
+            let all: Vec<Decimal> = transactions.iter().rev().map(|t| t.amount).collect();
+            assert_eq!(all, [dec!(4), dec!(3), dec!(2), dec!(1)]);
+        }
+
+        #[test]
+        fn filtered_views_borrow_the_transactions() {
+            let transactions = Transactions::new(vec![coffee()]);
+            let view = transactions.filter(|_| true);
+            assert!(std::ptr::eq(
+                view.iter().next().unwrap(),
+                &transactions.data()[0]
+            ));
+        }
+
+        #[test]
+        fn overlapping_exports_deduplicate() {
+            let mut first = vec![coffee(), coffee()];
+            let mut second = vec![coffee(), coffee(), coffee()];
+            number_occurrences(&mut first);
+            number_occurrences(&mut second);
+            first.append(&mut second);
+            let transactions = Transactions::new(first);
+            let occurrences: Vec<u64> = transactions.iter().map(|t| t.occurrence).collect();
+            assert_eq!(occurrences, [0, 1, 2]);
+        }
+    }
+}
+
+pub mod account {
+    use super::identify::*;
+    use crate::from_files::serializers::serde_iban;
+    use core::fmt;
+    use iban::Iban;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Hash, Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+    pub enum AccountType {
+        /// Your everyday account: pay bills, buy stuff, receive salary. Cash is flowing here.
+        /// No interest though most of the time.
+        Checking,
+        /// A place to save money. Less accessible, usually provides some interest.
+        Saving,
+        /// Also for saving money. Generally higher interest than savings account, but you
+        /// sign a contract with the bank to leave the money there for a fixed amount of time.
+        Deposit,
+        /// This is where you put your money if you want to participate in the stock market.
+        Brokerage,
+        Unknown,
+    }
+    /// A proper bank account that is guaranteed to have an Iban.
+    #[derive(Hash, Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+    pub struct Account {
+        #[serde(with = "serde_iban")]
+        pub iban: Iban,
+        pub name: String,
+        // pub account_type: Option<AccountType>, // TODO: Can we actually derive this information from the raw data though?
+    }
+    impl Account {
+        pub fn iban_id(iban: &Iban) -> ID<Self> {
+            let mut encoder = IdEncoder::new();
+            encoder.str(iban.as_str());
+            encoder.finish()
+        }
+    }
+    impl Identify for Account {
+        type IdType = Self;
+        /// Just the iban (electronic format) for uniformity.
+        fn encode(&self, encoder: &mut IdEncoder) {
+            encoder.str(self.iban.as_str());
+        }
+    }
+    impl fmt::Display for Account {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "[{}] {}", self.id(), self.name,)
+        }
+    }
+
+    /// Almost a bank account, except it's tied to a real account and as such doesn't have
+    /// an official iban.
+    #[derive(Hash, Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+    pub struct SubAccount {
+        /// Bank Sub Account Number. This is not a "real" thing (I think), but it serves its purpose.
+        pub bsan: String,
+        pub name: String,
+        pub parent_account: Account, // Might be nice to have? -> Also not generally known at read-time
+        pub account_type: Option<AccountType>,
+    }
+    impl Identify for SubAccount {
+        type IdType = Self;
+        /// Just the bsan for uniformity.
+        fn encode(&self, encoder: &mut IdEncoder) {
+            encoder.str(&self.bsan);
+        }
+    }
+    impl fmt::Display for SubAccount {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(
+                f,
+                "[{:?}/ {}] {}",
+                self.parent_account.id(),
+                self.id(),
+                self.name
+            )
+        }
+    }
+}
+
+pub mod state {
+    use super::{category::Category, identify::*, query::Query, rule::Rule, transaction::*};
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+    use serde::{Deserialize, Serialize};
+    use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+    /// The balance of an owned node at the start of `date`, before its transactions. For
+    /// nodes the bank doesn't report balances of, like savings sub-accounts, or to
+    /// anchor a balance before the first export.
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct OpeningBalance {
+        pub node: ID<Node>,
+        pub date: NaiveDate,
+        pub amount: Decimal,
+    }
+
+    // Example case: Tag my account as my account
+    #[derive(Clone, Serialize, Deserialize)]
+    pub struct Owner {
+        pub name: String,
+        pub owns: Vec<Node>,
+        /// Account names to use for nodes in bookkeeping exports, e.g.:
+        /// `"Assets:ING:Checking"` or `"Expenses:Groceries"`. Nodes that aren't
+        /// listed get a name derived from the node.
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        pub account_names: HashMap<ID<Node>, String>,
+        /// Saved queries, by name.
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        pub queries: BTreeMap<String, Query>,
+        /// Rules that tag transactions, applied in order. See `rules`.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub rules: Vec<Rule>,
+        /// The category tree, as the paths of its categories. Parents don't need to be
+        /// listed separately.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub categories: Vec<Category>,
+        /// Categories chosen for single transactions. They take precedence over rules.
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        pub categorized: HashMap<ID<Transaction>, Category>,
+        /// Known balances of owned nodes, see `analysis::balances::timeline`.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub opening_balances: Vec<OpeningBalance>,
+    }
+    impl Owner {
+        pub fn view(&self, id: &ID<Node>) -> Option<&Node> {
+            self.owns.iter().find(|node| &node.id() == id)
+        }
+        pub fn owns(&self, id: &ID<Node>) -> bool {
+            self.view(id).is_some()
+        }
+        pub fn name_of(&self, id: &ID<Node>) -> Option<String> {
+            self.view(id).map(|node| node.name())
+        }
+        /// Whether `transaction` only moves money between nodes the owner owns: it's
+        /// neither income nor an expense.
+        pub fn is_internal(&self, transaction: &Transaction) -> bool {
+            self.owns(&transaction.source.id()) && self.owns(&transaction.sink.id())
+        }
+        /// The node on the other side of the owner's account: the payee of a payment,
+        /// the payer of an incoming transaction. The sink when neither or both are owned.
+        pub fn counterparty<'t>(&self, transaction: &'t Transaction) -> &'t Node {
+            if self.owns(&transaction.sink.id()) && !self.owns(&transaction.source.id()) {
+                &transaction.source
+            } else {
+                &transaction.sink
+            }
+        }
+        /// Every category of the tree, parents included, in tree order.
+        pub fn category_tree(&self) -> BTreeSet<Category> {
+            self.categories.iter().flat_map(Category::lineage).collect()
+        }
+        /// Categories that rules or single transactions use, but that aren't in the tree.
+        pub fn undefined_categories(&self) -> BTreeSet<&Category> {
+            let tree = self.category_tree();
+            let rules = self.rules.iter().filter_map(|rule| rule.category.as_ref());
+            rules
+                .chain(self.categorized.values())
+                .filter(|category| !tree.contains(*category))
+                .collect()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::canonical::fixtures::*;
+        use rust_decimal_macros::dec;
+
+        #[test]
+        fn counterparty_is_the_other_side_of_the_owner() {
+            let me = owner(vec![checking(), joint()]);
+            let payment = transaction(date(2024, 1, 10), &checking(), &shop(), dec!(25));
+            assert_eq!(me.counterparty(&payment), &shop());
+            let refund = transaction(date(2024, 1, 10), &shop(), &checking(), dec!(5));
+            assert_eq!(me.counterparty(&refund), &shop());
+            let internal = transaction(date(2024, 1, 10), &checking(), &joint(), dec!(100));
+            assert_eq!(me.counterparty(&internal), &joint());
+            let unowned = transaction(date(2024, 1, 10), &shop(), &checking(), dec!(5));
+            assert_eq!(owner(vec![]).counterparty(&unowned), &checking());
+        }
+    }
+}
+
+/// Reusable selections of transactions. A `Query` is plain data: it is built up
+/// with chained calls, evaluated against any `View` with `Transactions::query`
+/// and stored in the profile under a name (see `Owner::queries`).
+pub mod query {
+    use super::{category::Category, identify::*, state::Owner, transaction::*};
+    use chrono::NaiveDate;
+    use regex::Regex;
+    use rust_decimal::Decimal;
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    /// Which of a transaction's nodes the owner owns.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Ownership {
+        /// At least one of them: the transaction concerns the owner.
+        Any,
+        /// Both: money moves between the owner's own nodes.
+        Internal,
+        /// Exactly one: income or an expense.
+        External,
+    }
+
+    /// `direction` with respect to `node`, see `Transaction::direction`.
+    #[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+    pub struct Relative {
+        pub direction: Direction,
+        pub node: ID<Node>,
+    }
+
+    /// A regular expression that (de)serializes as its pattern.
+    #[derive(Debug, Clone)]
+    pub struct Pattern(pub Regex);
+    impl PartialEq for Pattern {
+        fn eq(&self, other: &Self) -> bool {
+            self.0.as_str() == other.0.as_str()
+        }
+    }
+    impl Serialize for Pattern {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(self.0.as_str())
+        }
+    }
+    impl<'de> Deserialize<'de> for Pattern {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let pattern = String::deserialize(deserializer)?;
+            Regex::new(&pattern).map(Pattern).map_err(de::Error::custom)
+        }
+    }
+
+    /// Conditions a transaction has to meet, all of them. Conditions that aren't set
+    /// don't filter: `Query::default()` matches every transaction.
+    ///
+    /// ```ignore
+    /// let groceries = Query::default()
+    ///     .from(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap())
+    ///     .ownership(Ownership::External)
+    ///     .tag("#boodschappen")
+    ///     .max_amount(dec!(100));
+    /// let january = transactions.query(&groceries, &owner);
+    /// let large = january.filter(|t| t.amount > dec!(50));
+    /// ```
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct Query {
+        /// First date to include.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub from: Option<NaiveDate>,
+        /// Last date to include.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub to: Option<NaiveDate>,
+        /// Smallest amount to include. Amounts are always positive.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub min_amount: Option<Decimal>,
+        /// Largest amount to include.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub max_amount: Option<Decimal>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub direction: Option<Relative>,
+        /// A node that is the source or the sink.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub node: Option<ID<Node>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub ownership: Option<Ownership>,
+        /// Tags the transaction has, all of them, e.g.: `"#boodschappen"`.
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        pub tags: Vec<String>,
+        /// The bank's transaction code, ignoring case. See `Transaction::code`.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub code: Option<String>,
+        /// Matches (part of) the description.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub description: Option<Pattern>,
+        /// A category the transaction is in, directly or through a subcategory.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub category: Option<Category>,
+    }
+    impl Query {
+        pub fn from(mut self, date: NaiveDate) -> Self {
+            self.from = Some(date);
+            self
+        }
+        pub fn to(mut self, date: NaiveDate) -> Self {
+            self.to = Some(date);
+            self
+        }
+        pub fn min_amount(mut self, amount: Decimal) -> Self {
+            self.min_amount = Some(amount);
+            self
+        }
+        pub fn max_amount(mut self, amount: Decimal) -> Self {
+            self.max_amount = Some(amount);
+            self
+        }
+        /// Only transactions that go in `direction` with respect to `node`.
+        pub fn direction(mut self, direction: Direction, node: ID<Node>) -> Self {
+            self.direction = Some(Relative { direction, node });
+            self
+        }
+        pub fn node(mut self, node: ID<Node>) -> Self {
+            self.node = Some(node);
+            self
+        }
+        pub fn ownership(mut self, ownership: Ownership) -> Self {
+            self.ownership = Some(ownership);
+            self
+        }
+        /// Adds to the tags the transaction must have.
+        pub fn tag(mut self, tag: impl Into<String>) -> Self {
+            self.tags.push(tag.into());
+            self
+        }
+        pub fn code(mut self, code: impl Into<String>) -> Self {
+            self.code = Some(code.into());
+            self
+        }
+        pub fn description(mut self, pattern: Regex) -> Self {
+            self.description = Some(Pattern(pattern));
+            self
+        }
+        pub fn category(mut self, category: Category) -> Self {
+            self.category = Some(category);
+            self
+        }
+
+        /// Whether `transaction` meets every condition. `owner` decides `ownership`.
+        pub fn matches(&self, transaction: &Transaction, owner: &Owner) -> bool {
+            let (source, sink) = (transaction.source.id(), transaction.sink.id());
+            self.from.is_none_or(|from| transaction.date >= from)
+                && self.to.is_none_or(|to| transaction.date <= to)
+                && self.min_amount.is_none_or(|min| transaction.amount >= min)
+                && self.max_amount.is_none_or(|max| transaction.amount <= max)
+                && self
+                    .direction
+                    .is_none_or(|relative| match relative.direction {
+                        Direction::Incoming => sink == relative.node,
+                        Direction::Outgoing => source == relative.node,
+                    })
+                && self.node.is_none_or(|node| source == node || sink == node)
+                && self.ownership.is_none_or(|ownership| {
+                    match (ownership, owner.owns(&source), owner.owns(&sink)) {
+                        (Ownership::Any, source, sink) => source || sink,
+                        (Ownership::Internal, source, sink) => source && sink,
+                        (Ownership::External, source, sink) => source != sink,
+                    }
+                })
+                && (self.tags.is_empty() || {
+                    let tags = transaction.tags();
+                    self.tags.iter().all(|tag| tags.contains(tag))
+                })
+                && self.code.as_ref().is_none_or(|code| {
+                    transaction
+                        .code
+                        .as_ref()
+                        .is_some_and(|own| own.eq_ignore_ascii_case(code))
+                })
+                && self
+                    .description
+                    .as_ref()
+                    .is_none_or(|pattern| pattern.0.is_match(&transaction.description))
+                && self.category.as_ref().is_none_or(|category| {
+                    transaction
+                        .category
+                        .as_ref()
+                        .is_some_and(|own| category.contains(own))
+                })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::canonical::fixtures::*;
+        use rust_decimal_macros::dec;
+
+        fn groceries() -> Transaction {
+            Transaction {
+                inherent_tags: String::from("#boodschappen"),
+                description: String::from("Albert Heijn 1234 AMSTERDAM"),
+                code: Some(String::from("BA")),
+                category: "Food > Groceries".parse().ok(),
+                rule_tags: [(String::from("#weekly"), String::from("Weekly"))].into(),
+                ..transaction(date(2024, 1, 15), &checking(), &shop(), dec!(42.50))
+            }
+        }
+
+        fn matches(query: Query, transaction: &Transaction) -> bool {
+            query.matches(transaction, &owner(vec![checking(), joint()]))
+        }
+
+        #[test]
+        fn default_matches_everything() {
+            assert!(matches(Query::default(), &groceries()));
+        }
+
+        #[test]
+        fn dates_and_amounts_are_inclusive() {
+            let t = groceries();
+            assert!(matches(Query::default().from(t.date).to(t.date), &t));
+            assert!(!matches(Query::default().from(date(2024, 1, 16)), &t));
+            assert!(!matches(Query::default().to(date(2024, 1, 14)), &t));
+            let exact = Query::default()
+                .min_amount(dec!(42.50))
+                .max_amount(dec!(42.50));
+            assert!(matches(exact, &t));
+            assert!(!matches(Query::default().max_amount(dec!(42)), &t));
+        }
+
+        #[test]
+        fn direction_and_node() {
+            let t = groceries();
+            let outgoing = Query::default().direction(Direction::Outgoing, checking().id());
+            assert!(matches(outgoing, &t));
+            let incoming = Query::default().direction(Direction::Incoming, checking().id());
+            assert!(!matches(incoming, &t));
+            assert!(matches(Query::default().node(shop().id()), &t));
+            assert!(!matches(Query::default().node(joint().id()), &t));
+        }
+
+        #[test]
+        fn ownership() {
+            let external = groceries();
+            let internal = transaction(date(2024, 1, 15), &checking(), &joint(), dec!(10));
+            let foreign = transaction(date(2024, 1, 15), &shop(), &shop(), dec!(10));
+            let any = Query::default().ownership(Ownership::Any);
+            assert!(matches(any.clone(), &external) && matches(any.clone(), &internal));
+            assert!(!matches(any, &foreign));
+            let only_internal = Query::default().ownership(Ownership::Internal);
+            assert!(matches(only_internal.clone(), &internal));
+            assert!(!matches(only_internal, &external));
+            let only_external = Query::default().ownership(Ownership::External);
+            assert!(matches(only_external.clone(), &external));
+            assert!(!matches(only_external, &internal));
+        }
+
+        #[test]
+        fn tags_code_description_and_category() {
+            let t = groceries();
+            assert!(matches(
+                Query::default().tag("#boodschappen").tag("#weekly"),
+                &t
+            ));
+            assert!(!matches(
+                Query::default().tag("#boodschappen").tag("#rent"),
+                &t
+            ));
+            assert!(matches(Query::default().code("ba"), &t));
+            assert!(!matches(Query::default().code("GT"), &t));
+            let heijn = Regex::new("(?i)albert heijn").unwrap();
+            assert!(matches(Query::default().description(heijn), &t));
+            let jumbo = Regex::new("Jumbo").unwrap();
+            assert!(!matches(Query::default().description(jumbo), &t));
+            let food: Category = "Food".parse().unwrap();
+            assert!(matches(Query::default().category(food), &t));
+            let dining: Category = "Food > Dining".parse().unwrap();
+            assert!(!matches(Query::default().category(dining), &t));
+        }
+
+        #[test]
+        fn round_trips_as_json() {
+            let query = Query::default()
+                .from(date(2024, 1, 1))
+                .ownership(Ownership::External)
+                .tag("#boodschappen")
+                .description(Regex::new("^Albert").unwrap());
+            let json = serde_json::to_string(&query).unwrap();
+            assert_eq!(serde_json::from_str::<Query>(&json).unwrap(), query);
+        }
+    }
+}
+
+/// Categories for budgeting, in a tree: `Food > Groceries > Supermarket`. The owner
+/// defines the tree (`Owner::categories`), and every transaction has at most one
+/// category (`Transaction::category`).
+pub mod category {
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+    use std::{fmt, str::FromStr};
+    use thiserror::Error;
+
+    /// A category, by its path from the root of the tree. Categories sort depth first:
+    /// a parent comes right before its children.
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+    pub struct Category(Vec<String>);
+    impl Category {
+        /// The category at the end of `path`, the root first.
+        pub fn new<I: IntoIterator<Item = S>, S: Into<String>>(path: I) -> Option<Self> {
+            let path: Vec<String> = path.into_iter().map(Into::into).collect();
+            let valid = !path.is_empty()
+                && path
+                    .iter()
+                    .all(|name| !name.is_empty() && name.trim() == name && !name.contains('>'));
+            valid.then_some(Self(path))
+        }
+        /// The last name of the path.
+        pub fn name(&self) -> &str {
+            &self.0[self.0.len() - 1]
+        }
+        pub fn path(&self) -> &[String] {
+            &self.0
+        }
+        /// Number of parents.
+        pub fn depth(&self) -> usize {
+            self.0.len() - 1
+        }
+        pub fn parent(&self) -> Option<Category> {
+            (self.0.len() > 1).then(|| Self(self.0[..self.0.len() - 1].to_vec()))
+        }
+        /// The root of the tree first, ending with this category itself.
+        pub fn lineage(&self) -> impl Iterator<Item = Category> + '_ {
+            (1..=self.0.len()).map(|len| Self(self.0[..len].to_vec()))
+        }
+        /// Whether `other` is this category or one of its descendants.
+        pub fn contains(&self, other: &Category) -> bool {
+            other.0.starts_with(&self.0)
+        }
+    }
+
+    #[derive(Debug, Error, PartialEq, Eq)]
+    #[error("{0:?} is not a category, expecting names separated by `>`: \"Food > Groceries\"")]
+    pub struct InvalidCategory(pub String);
+
+    impl FromStr for Category {
+        type Err = InvalidCategory;
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            Self::new(s.split('>').map(str::trim)).ok_or_else(|| InvalidCategory(s.to_string()))
+        }
+    }
+    impl fmt::Display for Category {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.0.join(" > "))
+        }
+    }
+    /// Stored as displayed, e.g. in a profile.
+    impl Serialize for Category {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(&self.to_string())
+        }
+    }
+    impl<'de> Deserialize<'de> for Category {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let s = String::deserialize(deserializer)?;
+            s.parse().map_err(de::Error::custom)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn category(path: &str) -> Category {
+            path.parse().unwrap()
+        }
+
+        #[test]
+        fn parses_and_displays_paths() {
+            let groceries = category(" Food>Groceries ");
+            assert_eq!(groceries.path(), ["Food", "Groceries"]);
+            assert_eq!(groceries.to_string(), "Food > Groceries");
+            assert_eq!(groceries.name(), "Groceries");
+            assert_eq!(groceries.depth(), 1);
+            assert_eq!(groceries.parent(), Some(category("Food")));
+            for invalid in ["", "Food >", "> Food", "Food >> Groceries"] {
+                assert_eq!(
+                    invalid.parse::<Category>(),
+                    Err(InvalidCategory(String::from(invalid)))
+                );
+            }
+        }
+
+        #[test]
+        fn parents_sort_before_and_contain_their_children() {
+            let food = category("Food");
+            let groceries = category("Food > Groceries");
+            let lineage: Vec<Category> = groceries.lineage().collect();
+            assert_eq!(lineage, [food.clone(), groceries.clone()]);
+            assert!(food.contains(&groceries) && food.contains(&food));
+            assert!(!groceries.contains(&food));
+            assert!(!category("Foo").contains(&food));
+            let mut sorted = vec![category("Housing"), groceries.clone(), food.clone()];
+            sorted.sort();
+            assert_eq!(sorted, [food, groceries, category("Housing")]);
+        }
+    }
+}
+
+/// Rules that tag and categorize transactions, kept in the profile (see `Owner::rules`).
+/// A rule is plain data like a `Query`; `rules::apply` tags transactions with them.
+pub mod rule {
+    use super::{category::Category, identify::*, query::Pattern, state::Owner, transaction::*};
+    use rust_decimal::Decimal;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct Rule {
+        /// Names the rule in the trace of the tags it added.
+        pub name: String,
+        /// Tags to add, e.g.: `"#groceries"`. A missing `#` is added.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub tags: Vec<String>,
+        /// Category to put transactions in, unless an earlier rule did.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub category: Option<Category>,
+        /// A rule without conditions tags every transaction.
+        #[serde(default)]
+        pub when: Conditions,
+    }
+    impl Rule {
+        pub fn matches(&self, transaction: &Transaction, owner: &Owner) -> bool {
+            self.when.matches(transaction, owner)
+        }
+    }
+
+    /// What a transaction has to look like for a rule to tag it, all of it. Conditions that
+    /// aren't set don't count. The counterparty is the node on the other side of the
+    /// owner's account, see `Owner::counterparty`.
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct Conditions {
+        /// ID of the counterparty.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub counterparty: Option<ID<Node>>,
+        /// Matches (part of) the counterparty's name: the account name, or the ID of a
+        /// payment terminal or ATM.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub counterparty_name: Option<Pattern>,
+        /// Matches (part of) the description.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub description: Option<Pattern>,
+        /// The bank's transaction code, ignoring case. See `Transaction::code`.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub code: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub min_amount: Option<Decimal>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub max_amount: Option<Decimal>,
+        /// `Incoming` for money the owner receives, `Outgoing` for money the owner pays.
+        /// Transfers between the owner's own nodes are neither.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub direction: Option<Direction>,
+    }
+    impl Conditions {
+        pub fn matches(&self, transaction: &Transaction, owner: &Owner) -> bool {
+            let other = owner.counterparty(transaction);
+            self.counterparty.is_none_or(|id| other.id() == id)
+                && self
+                    .counterparty_name
+                    .as_ref()
+                    .is_none_or(|pattern| pattern.0.is_match(&name(other, owner)))
+                && self
+                    .description
+                    .as_ref()
+                    .is_none_or(|pattern| pattern.0.is_match(&transaction.description))
+                && self.code.as_ref().is_none_or(|code| {
+                    transaction
+                        .code
+                        .as_ref()
+                        .is_some_and(|own| own.eq_ignore_ascii_case(code))
+                })
+                && self.min_amount.is_none_or(|min| transaction.amount >= min)
+                && self.max_amount.is_none_or(|max| transaction.amount <= max)
+                && self.direction.is_none_or(|direction| {
+                    let source = owner.owns(&transaction.source.id());
+                    let sink = owner.owns(&transaction.sink.id());
+                    match direction {
+                        Direction::Incoming => sink && !source,
+                        Direction::Outgoing => source && !sink,
+                    }
+                })
+        }
+    }
+
+    /// The name rules match a node on. Terminals and ATMs are only told apart by their ID.
+    fn name(node: &Node, owner: &Owner) -> String {
+        match node {
+            Node::Terminal(id) | Node::Atm(id) => id.clone(),
+            _ => owner.name_of(&node.id()).unwrap_or_else(|| node.name()),
+        }
+    }
+}
+
+pub mod identify {
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+    use sha2::{Digest, Sha256};
+    use std::{
+        fmt::{Debug, Display},
+        hash::Hash,
+        marker::PhantomData,
+        num::ParseIntError,
+        str::FromStr,
+    };
+
+    #[derive(PartialEq, Eq, Hash)]
+    pub struct ID<Of: Identify>(u64, PhantomData<Of>);
+    impl<Of: Identify> ID<Of> {
+        pub fn new(id: u64) -> Self {
+            Self(id, PhantomData)
+        }
+    }
+    // Not derived: that would require `Of: Copy`, and an ID is just a number.
+    impl<Of: Identify> Clone for ID<Of> {
+        fn clone(&self) -> Self {
+            *self
+        }
+    }
+    impl<Of: Identify> Copy for ID<Of> {}
+    /// Display the `u64` ID value as a zero-padded, 16 digit hexadecimal string.
+    ///
+    /// E.g.: `"5E8C0A84534B0F04"`
+    impl<Of: Identify> Debug for ID<Of> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:016X}", self.0)
+        }
+    }
+    impl<Of: Identify> Display for ID<Of> {
+        /// Display the `u64` ID value as a zero-padded, 16 digit hexadecimal string.
+        ///
+        /// E.g.: `"5E8C0A84534B0F04"`
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self) // Transfer Debug
+        }
+    }
+    impl<Of: Identify> FromStr for ID<Of> {
+        type Err = ParseIntError;
+        /// Parse the hexadecimal representation, as displayed.
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            u64::from_str_radix(s, 16).map(Self::new)
+        }
+    }
+
+    /// IDs are stored as their hexadecimal representation, e.g. as keys in a profile.
+    impl<Of: Identify> Serialize for ID<Of> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(&self.to_string())
+        }
+    }
+    impl<'de, Of: Identify> Deserialize<'de> for ID<Of> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let s = String::deserialize(deserializer)?;
+            s.parse()
+                .map_err(|_| de::Error::custom(format!("{:?} is not an ID", s)))
+        }
+    }
+
+    /// Builds the canonical byte encoding of the identifying properties of a
+    /// value, and turns it into an `ID`.
+    ///
+    /// The encoding is fully specified, so IDs don't depend on the Rust
+    /// release or platform they are computed on:
+    /// - a string is its length in bytes as a little-endian `u64`, followed by
+    ///   its UTF-8 bytes.
+    /// - a `u64` is its 8 little-endian bytes.
+    /// - an optional field comes after the required ones. It is left out when
+    ///   absent, so adding one to a type keeps every existing ID. When present
+    ///   it is a one-byte tag, unique among the optional fields of the type,
+    ///   followed by its value. Present fields are written in order of their tag.
+    ///
+    /// The ID is the first 8 bytes of the SHA-256 digest of the encoding, read
+    /// as a big-endian `u64`. Its hexadecimal representation therefore equals
+    /// the first 16 hex digits of the digest.
+    pub struct IdEncoder(Sha256);
+    impl IdEncoder {
+        pub fn new() -> Self {
+            Self(Sha256::new())
+        }
+        pub fn str(&mut self, value: &str) -> &mut Self {
+            self.u64(value.len() as u64);
+            self.0.update(value.as_bytes());
+            self
+        }
+        pub fn u64(&mut self, value: u64) -> &mut Self {
+            self.0.update(value.to_le_bytes());
+            self
+        }
+        /// Encode another ID by its `u64` value.
+        pub fn id<Of: Identify>(&mut self, id: ID<Of>) -> &mut Self {
+            self.u64(id.0)
+        }
+        /// Encode an optional field with its `tag` and `encode`, if it is present.
+        pub fn optional<T>(
+            &mut self,
+            tag: u8,
+            value: Option<T>,
+            encode: impl FnOnce(&mut Self, T) -> &mut Self,
+        ) -> &mut Self {
+            match value {
+                Some(value) => {
+                    self.0.update([tag]);
+                    encode(self, value)
+                }
+                None => self,
+            }
+        }
+        pub fn finish<Of: Identify>(self) -> ID<Of> {
+            let digest = self.0.finalize();
+            let mut prefix = [0; 8];
+            prefix.copy_from_slice(&digest[..8]);
+            ID::new(u64::from_be_bytes(prefix))
+        }
+    }
+    impl Default for IdEncoder {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    pub trait Identify
+    where
+        Self: Hash + Debug,
+        Self::IdType: Identify,
+    {
+        type IdType;
+        /// Write the identifying properties of self to `encoder`. Two values
+        /// that encode the same bytes get the same ID.
+        ///
+        /// To implement this for your own type:
+        /// ```ignore
+        /// fn encode(&self, encoder: &mut IdEncoder) {
+        ///     encoder.str(&self.identifying_prop).u64(self.other_prop);
+        /// }
+        /// ```
+        ///
+        /// Changing an implementation changes every ID derived from it, so
+        /// update the golden IDs in the tests deliberately when you do.
+        fn encode(&self, encoder: &mut IdEncoder);
+
+        /// Generates a stable ID of self: the same value always gets the same
+        /// ID, across runs, platforms and Rust releases. See `IdEncoder` for
+        /// how it is computed.
+        fn id(&self) -> ID<Self::IdType> {
+            let mut encoder = IdEncoder::new();
+            self.encode(&mut encoder);
+            encoder.finish()
+        }
+
+        /// Discard `other`'s ID Type and rewrap it in `Self::IdType`.
+        ///
+        /// Nice to have transitive ID's when subtypes define their own
+        /// implementation of `.id()`.
+        ///
+        /// # Example
+        /// `Account` is a value in `Node::ProperAccount(Account)` that
+        /// defines its own implementation of `Identify.id()``, though
+        /// their ID types differ, so `Node` can easily pass through
+        /// the id value without recomputing it.
+        /// ```ignore
+        /// let account_id: ID<Account> = Account::default().id();
+        /// let node_id: ID<Node> = Node::transfer_from(account_id);
+        /// ```
+        fn transfer_from<OtherIdType: Identify>(other: ID<OtherIdType>) -> ID<Self::IdType> {
+            ID::new(other.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Golden IDs. These values are persisted in databases and profiles, so
+    //! they must never change by accident. They can be reproduced outside of
+    //! Rust, e.g. in Python:
+    //! ```python
+    //! def s(x): b = x.encode(); return struct.pack('<Q', len(b)) + b
+    //! hashlib.sha256(s('NL91ABNA0417164300')).hexdigest()[:16].upper()
+    //! ```
+    use super::{account::*, identify::*, transaction::*};
+    use chrono::NaiveDate;
+    use iban::Iban;
+    use rust_decimal_macros::dec;
+    use std::collections::BTreeMap;
+
+    fn checking() -> Account {
+        Account {
+            iban: Iban::parse("NL91ABNA0417164300").unwrap(),
+            name: String::from("Checking"),
+        }
+    }
+
+    fn savings() -> SubAccount {
+        SubAccount {
+            bsan: String::from("V12345678"),
+            name: String::from("Savings"),
+            parent_account: checking(),
+            account_type: Some(AccountType::Saving),
+        }
+    }
+
+    #[test]
+    fn account_id() {
+        assert_eq!(checking().id().to_string(), "0F0A7FDFF03A1771");
+        assert_eq!(
+            Account::iban_id(&checking().iban).to_string(),
+            "0F0A7FDFF03A1771"
+        );
+    }
+
+    #[test]
+    fn account_id_ignores_name() {
+        let renamed = Account {
+            name: String::from("Something else"),
+            ..checking()
+        };
+        assert_eq!(renamed.id(), checking().id());
+    }
+
+    #[test]
+    fn sub_account_id() {
+        assert_eq!(savings().id().to_string(), "AF21A2327FE8E3F5");
+    }
+
+    #[test]
+    fn terminal_id() {
+        assert_eq!(
+            Node::Terminal(String::from("AB1234")).id().to_string(),
+            "2236335A2B8B4FF7"
+        );
+        assert_eq!(
+            Node::Other(String::from("ING")).id().to_string(),
+            "2B631E96F88C723E"
+        );
+    }
+
+    #[test]
+    fn node_ids_transfer_from_accounts() {
+        assert_eq!(
+            Node::ProperAccount(checking()).id(),
+            Node::transfer_from(checking().id())
+        );
+        assert_eq!(
+            Node::SubAccount(savings()).id(),
+            Node::transfer_from(savings().id())
+        );
+    }
+
+    #[test]
+    fn transaction_id() {
+        let payment = Transaction {
+            date: NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+            source: Node::ProperAccount(checking()),
+            sink: Node::Terminal(String::from("AB1234")),
+            amount: dec!(12.50),
+            inherent_tags: String::from("#boodschappen"),
+            description: String::from("Term: AB1234"),
+            sequence: None,
+            reference: None,
+            end_to_end: None,
+            mandate: None,
+            balance: None,
+            code: None,
+            occurrence: 0,
+            rule_tags: BTreeMap::new(),
+            category: None,
+        };
+        assert_eq!(payment.id().to_string(), "01E5D3E8E82E3288");
+
+        let sequenced = Transaction {
+            sequence: Some(Sequence {
+                of: payment.source.id(),
+                number: 7,
+            }),
+            ..payment.clone()
+        };
+        assert_eq!(sequenced.id().to_string(), "13AC133D0C07EB13");
+
+        let referenced = Transaction {
+            reference: Some(String::from("20240102-0001")),
+            ..payment.clone()
+        };
+        assert_eq!(referenced.id().to_string(), "2112FE43FB393A32");
+
+        let initiated = Transaction {
+            end_to_end: Some(String::from("E2E-1")),
+            ..payment.clone()
+        };
+        assert_eq!(initiated.id().to_string(), "7D2C1111A626E6CD");
+
+        // Each optional field is tagged, so equal values in different fields differ.
+        let initiated_as_referenced = Transaction {
+            end_to_end: Some(String::from("20240102-0001")),
+            ..payment.clone()
+        };
+        assert_ne!(initiated_as_referenced.id(), referenced.id());
+
+        let collected = Transaction {
+            mandate: Some(Mandate {
+                id: String::from("M-1"),
+                creditor: None,
+            }),
+            ..payment.clone()
+        };
+        assert_eq!(collected.id(), payment.id());
+
+        let repeated = Transaction {
+            occurrence: 1,
+            ..payment.clone()
+        };
+        assert_eq!(repeated.id().to_string(), "FBDA46659AA0F9A9");
+
+        let saving = Transaction {
+            date: NaiveDate::from_ymd_opt(2024, 1, 4).unwrap(),
+            source: Node::ProperAccount(checking()),
+            sink: Node::SubAccount(savings()),
+            amount: dec!(50.00),
+            inherent_tags: String::new(),
+            description: String::from("Naar Oranje spaarrekening V12345678"),
+            sequence: None,
+            reference: None,
+            end_to_end: None,
+            mandate: None,
+            balance: None,
+            code: None,
+            occurrence: 0,
+            rule_tags: BTreeMap::new(),
+            category: None,
+        };
+        assert_eq!(saving.id().to_string(), "1E7E92D7608193E7");
+    }
+}
+
+/// Nodes, transactions and owners for the tests of every module.
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use super::{account::*, identify::*, state::Owner, transaction::*};
+    use chrono::NaiveDate;
+    use iban::Iban;
+    use rust_decimal::Decimal;
+    use std::{
+        collections::{BTreeMap, HashMap},
+        fs,
+        path::PathBuf,
+    };
+
+    pub fn account(iban: &str, name: &str) -> Node {
+        Node::ProperAccount(Account {
+            iban: Iban::parse(iban).unwrap(),
+            name: String::from(name),
+        })
+    }
+
+    pub fn checking() -> Node {
+        account("NL91ABNA0417164300", "Checking")
+    }
+
+    pub fn savings() -> Node {
+        let Node::ProperAccount(parent_account) = checking() else {
+            unreachable!()
+        };
+        Node::SubAccount(SubAccount {
+            bsan: String::from("V12345678"),
+            name: String::from("Savings"),
+            parent_account,
+            account_type: Some(AccountType::Saving),
+        })
+    }
+
+    pub fn joint() -> Node {
+        account("NL02ABNA0123456789", "Joint")
+    }
+
+    pub fn shop() -> Node {
+        account("NL44RABO0123456789", "Shop")
+    }
+
+    pub fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    /// Without a description, tags or anything else that's optional.
+    pub fn transaction(
+        date: NaiveDate,
+        source: &Node,
+        sink: &Node,
+        amount: Decimal,
+    ) -> Transaction {
+        Transaction {
+            date,
+            source: source.clone(),
+            sink: sink.clone(),
+            amount,
+            inherent_tags: String::new(),
+            description: String::new(),
+            sequence: None,
+            reference: None,
+            end_to_end: None,
+            mandate: None,
+            balance: None,
+            code: None,
+            occurrence: 0,
+            rule_tags: BTreeMap::new(),
+            category: None,
+        }
+    }
+
+    /// `transaction` with the balance of `of` after it, as a bank reports it.
+    pub fn with_balance(transaction: Transaction, of: &Node, amount: Decimal) -> Transaction {
+        Transaction {
+            balance: Some(Balance {
+                of: of.id(),
+                amount,
+            }),
+            ..transaction
+        }
+    }
+
+    /// A fresh directory `name` in the system's temporary directory, holding `files`
+    /// (name and contents).
+    pub fn directory(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("accounting-core-{}", name));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        for (file, contents) in files {
+            fs::write(directory.join(file), contents).unwrap();
+        }
+        directory
+    }
+
+    pub fn owner(owns: Vec<Node>) -> Owner {
+        Owner {
+            name: String::from("Me"),
+            owns,
+            account_names: HashMap::new(),
+            queries: BTreeMap::new(),
+            rules: Vec::new(),
+            categories: Vec::new(),
+            categorized: HashMap::new(),
+            opening_balances: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,1243 @@
+pub mod serializers {
+    mod date_deserializer {
+        /// Create a serde date deserializer module `local_date_deserializer`
+        /// that uses `from_format` to convert from source (e.g.: "%Y%m%d").
+        ///
+        /// Use via #[serde(with = "local_date_deserializer")]
+        #[macro_export]
+        macro_rules! date_deserializer_from_format {
+            ( $format:expr ) => {
+                mod local_date_deserializer {
+                    use chrono::NaiveDate;
+                    use serde::{self, Deserialize, Deserializer};
+
+                    pub fn deserialize<'de, D>(deserializer: D) -> Result<NaiveDate, D::Error>
+                    where
+                        D: Deserializer<'de>,
+                    {
+                        let s = String::deserialize(deserializer)?;
+                        let dt = NaiveDate::parse_from_str(&s, $format)
+                            .map_err(serde::de::Error::custom)?;
+
+                        Ok(dt)
+                    }
+                }
+            };
+        }
+    }
+    mod direction {
+        use crate::canonical::transaction::Direction;
+        use serde::{self, de::Error, Deserialize, Deserializer};
+        use std::str::FromStr;
+
+        impl FromStr for Direction {
+            type Err = String;
+            fn from_str(input: &str) -> Result<Self, Self::Err> {
+                match input {
+                    "Af" | "Debit" | "Outgoing" => Ok(Self::Outgoing),
+                    "Bij" | "Credit" | "Incoming" => Ok(Self::Incoming),
+                    _ => Err(String::from_str("unknown Direction field").unwrap()),
+                }
+            }
+        }
+
+        impl<'de> Deserialize<'de> for Direction {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                let s: String = String::deserialize(deserializer)?;
+                match Direction::from_str(&s) {
+                    Ok(t) => Ok(t),
+                    Err(err) => Err(Error::custom(err)),
+                }
+            }
+        }
+    }
+    pub mod serde_amount {
+        use rust_decimal::Decimal;
+        use serde::{self, Deserialize, Deserializer};
+        use std::str::FromStr;
+
+        /// Convert an amount written in the Dutch locale (e.g.: "1.234,56").
+        pub fn to_decimal(input: &str) -> Result<Decimal, String> {
+            let input_locale = locale::Numeric {
+                decimal_sep: String::from(","),
+                thousands_sep: String::from("."),
+            };
+            let normalized = input
+                .trim()
+                .replace(&input_locale.thousands_sep, "")
+                .replace(&input_locale.decimal_sep, ".");
+            Decimal::from_str(&normalized)
+                .map_err(|_| format!("can't convert {:?} to an amount", input))
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Decimal, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let s: String = String::deserialize(deserializer)?;
+            to_decimal(&s).map_err(serde::de::Error::custom)
+        }
+    }
+    pub mod serde_iban {
+        use iban::Iban;
+        use serde::{self, Deserialize, Deserializer, Serializer};
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Iban, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let s: String = String::deserialize(deserializer)?;
+            let dt: Iban = Iban::parse(&s).map_err(serde::de::Error::custom)?;
+            Ok(dt)
+        }
+        pub fn serialize<S>(iban: &Iban, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serializer.serialize_str(iban.as_str())
+        }
+    }
+
+    pub mod ing {
+        use super::*;
+        use crate::{
+            canonical::{account::*, identify::*, state::Owner, transaction::*},
+            from_files::{
+                error::{ConversionError, ImportError},
+                importer::{column_match, read_csv, BankImporter, CsvRecord},
+                report::ImportReport,
+            },
+        };
+        use chrono::NaiveDate;
+        use iban::Iban;
+        use regex::Regex;
+        use rust_decimal::Decimal;
+        use serde::{self, Deserialize};
+        use std::{collections::BTreeMap, path::Path, sync::LazyLock};
+
+        /// Current account exports from ING, with either English or Dutch headers.
+        pub struct Ing;
+        impl BankImporter for Ing {
+            fn name(&self) -> &'static str {
+                "ING"
+            }
+            fn sniff(&self, header: &str) -> f32 {
+                let english = [
+                    "Date",
+                    "Name / Description",
+                    "Account",
+                    "Counterparty",
+                    "Code",
+                    "Debit/credit",
+                    "Amount (EUR)",
+                    "Transaction type",
+                    "Notifications",
+                ];
+                let dutch = [
+                    "Datum",
+                    "Naam / Omschrijving",
+                    "Rekening",
+                    "Tegenrekening",
+                    "Code",
+                    "Af Bij",
+                    "Bedrag (EUR)",
+                    "Mutatiesoort",
+                    "Mededelingen",
+                ];
+                column_match(header, ';', &english).max(column_match(header, ';', &dutch))
+            }
+            fn import(
+                &self,
+                path: &Path,
+                profile: &Owner,
+                report: &mut ImportReport,
+            ) -> Result<Vec<Transaction>, ImportError> {
+                read_csv::<IngCurrentAccount>(path, b';', true, profile, report)
+            }
+        }
+
+        #[derive(Debug, PartialEq, Deserialize, Eq, Hash)]
+        pub enum Code {
+            AC,
+            BA,
+            DV,
+            FL,
+            GF,
+            GM,
+            GT,
+            IC,
+            ID,
+            OV,
+            PK,
+            PO,
+            ST,
+            VZ,
+        }
+
+        crate::date_deserializer_from_format!("%Y%m%d");
+
+        /// Slightly processed CSV from ING. Raw, use as base to implement
+        /// Transaction.
+        #[derive(Debug, Deserialize, PartialEq, Eq, Hash)]
+        pub struct IngCurrentAccount {
+            /// YYYYMMDD -> YYYY-MM-DD
+            #[serde(rename = "Date", alias = "Datum", with = "local_date_deserializer")]
+            pub date: NaiveDate,
+
+            /// Name, often not very descriptive.
+            #[serde(rename = "Name / Description", alias = "Naam / Omschrijving")]
+            pub name: String,
+
+            /// XX00XXXX0000000000 -> IBAN, make a type for that. Always account of the owner?
+            #[serde(rename = "Account", alias = "Rekening", with = "serde_iban")]
+            pub account: Iban,
+
+            /// IBAN, or [A-Z]?[0-9]{8} for ING sub-accounts (sparen, beleggen). Sparen actually empty, gotta extract from Notifications column...
+            /// Basically needs a post-processing step still. Do this when moving to universal transaction type.
+            #[serde(rename = "Counterparty", alias = "Tegenrekening")]
+            pub counter_party: Option<String>,
+
+            /// Constant type from see https://nl.wikipedia.org/wiki/Rekeningafschrift
+            #[serde(rename = "Code")]
+            pub code: Code,
+
+            /// Constant "Debit" | "Credit"
+            ///
+            /// Debit = incoming, Credit = outgoing.
+            #[serde(rename = "Debit/credit", alias = "Af Bij")]
+            pub direction: Direction,
+
+            /// 0000,00 - Always positive: sign depends on `self.direction`.
+            #[serde(rename = "Amount (EUR)", alias = "Bedrag (EUR)", with = "serde_amount")]
+            pub amount: Decimal,
+
+            /// Full name of `self.code`.
+            #[serde(rename = "Transaction type", alias = "Mutatiesoort")]
+            pub transaction_type: String,
+
+            /// Extra description as filled in by the initiator of the transaction.
+            #[serde(rename = "Notifications", alias = "Mededelingen")]
+            pub description: String,
+
+            /// 0000,00 - Balance of the account after this transaction
+            #[serde(
+                rename = "Resulting balance",
+                alias = "Saldo na mutatie",
+                with = "serde_amount"
+            )]
+            pub balance: Decimal,
+
+            /// Extra custom tags and/or text added by the account
+            /// owner. -> Split to a set of #tags and a rest String.
+            #[serde(rename = "Tag")]
+            pub tags: String,
+        }
+
+        impl CsvRecord for IngCurrentAccount {
+            fn into_transaction(self, profile: &Owner) -> Result<Transaction, ConversionError> {
+                owned_ing_transaction(self, profile)
+            }
+        }
+
+        pub fn owned_ing_transaction(
+            ing_transaction: IngCurrentAccount,
+            owner: &Owner,
+        ) -> Result<Transaction, ConversionError> {
+            let id = owner.name_of(&Node::transfer_from(Account::iban_id(
+                &ing_transaction.account,
+            )));
+            let owner_account = Account {
+                iban: ing_transaction.account,
+                name: id.unwrap_or("UNNAMED_ACCOUNT".to_string()),
+            };
+            let balance = Balance {
+                of: Node::transfer_from(owner_account.id()),
+                amount: ing_transaction.balance,
+            };
+            Ok(Transaction {
+                amount: ing_transaction.amount,
+                date: ing_transaction.date,
+                description: ing_transaction.description.clone(),
+                inherent_tags: ing_transaction.tags.clone(),
+                source: source(&ing_transaction, owner_account.clone())?,
+                sink: sink(&ing_transaction, owner_account)?,
+                sequence: None,
+                reference: None,
+                end_to_end: None,
+                mandate: None,
+                balance: Some(balance),
+                code: Some(format!("{:?}", ing_transaction.code)),
+                occurrence: 0,
+                rule_tags: BTreeMap::new(),
+                category: None,
+            })
+        }
+
+        fn sink(
+            ing_transaction: &IngCurrentAccount,
+            owner: Account,
+        ) -> Result<Node, ConversionError> {
+            match ing_transaction.direction {
+                Direction::Incoming => Ok(Node::ProperAccount(owner)),
+                Direction::Outgoing => determine_node_type(ing_transaction, owner),
+            }
+        }
+
+        fn source(
+            ing_transaction: &IngCurrentAccount,
+            owner: Account,
+        ) -> Result<Node, ConversionError> {
+            match ing_transaction.direction {
+                Direction::Outgoing => Ok(Node::ProperAccount(owner)),
+                Direction::Incoming => determine_node_type(ing_transaction, owner),
+            }
+        }
+
+        fn determine_node_type(
+            ing_transaction: &IngCurrentAccount,
+            owner: Account,
+        ) -> Result<Node, ConversionError> {
+            if ing_transaction.code == Code::BA || ing_transaction.code == Code::GM {
+                static TERMID: LazyLock<Regex> =
+                    LazyLock::new(|| Regex::new(r"Term: (?<terminalID>\w+)").unwrap());
+                let mut term_id_matcher = TERMID.captures_iter(&ing_transaction.description);
+                return Ok(Node::Terminal(match term_id_matcher.next() {
+                    Some(mtch) => mtch["terminalID"].into(),
+                    None => "UNKNOWN_TERM_ID".into(),
+                }));
+            }
+
+            if ing_transaction.code == Code::DV {
+                return Ok(Node::Other("ING".into()));
+            }
+
+            if ing_transaction.code == Code::ST {
+                return Ok(Node::Other("Deposit".into()));
+            }
+
+            if let Some(identifier) = &ing_transaction.counter_party {
+                static BROKERAGE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\d+").unwrap());
+
+                if let Ok(iban) = Iban::parse(identifier) {
+                    return Ok(Node::ProperAccount(Account {
+                        iban,
+                        name: String::from(&ing_transaction.name),
+                    }));
+                } else if BROKERAGE.is_match(identifier) {
+                    return Ok(Node::SubAccount(SubAccount {
+                        bsan: String::from(identifier),
+                        name: String::from(&ing_transaction.name),
+                        account_type: Some(AccountType::Brokerage),
+                        parent_account: owner,
+                    }));
+                }
+            }
+
+            static O_SPAARREKENINGID: LazyLock<Regex> = LazyLock::new(|| {
+                Regex::new(r"Oranje spaarrekening.*(?<sprekeningnr>[A-Z]\d+)").unwrap()
+            });
+            let mut sprknr_id_matcher =
+                O_SPAARREKENINGID.captures_iter(&ing_transaction.description);
+            if let Some(sprknr) = sprknr_id_matcher.next() {
+                return Ok(Node::SubAccount(SubAccount {
+                    bsan: String::from(&sprknr["sprekeningnr"]),
+                    name: String::from(&ing_transaction.name),
+                    parent_account: owner,
+                    account_type: Some(AccountType::Saving),
+                }));
+            }
+
+            Err(ConversionError::UnknownCounterparty)
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+            use crate::{canonical::fixtures::*, from_files::importer::MIN_CONFIDENCE};
+            use rust_decimal_macros::dec;
+
+            const SAMPLE: &str = r##""Date";"Name / Description";"Account";"Counterparty";"Code";"Debit/credit";"Amount (EUR)";"Transaction type";"Notifications";"Resulting balance";"Tag"
+"20240102";"Shop";"NL91ABNA0417164300";"NL44RABO0123456789";"OV";"Debit";"12,50";"Transfer";"Groceries";"87,50";"#food"
+"20240103";"Coffee";"NL91ABNA0417164300";"";"BA";"Debit";"3,20";"Payment terminal";"Pasvolgnr: 001 Term: CT123456";"84,30";""
+"20240104";"Oranje spaarrekening";"NL91ABNA0417164300";"";"OV";"Credit";"1.000,00";"Transfer";"Van Oranje spaarrekening V12345678";"1.084,30";""
+"20240105";"Shop";"NL91ABNA0417164300";"NL44RABO0123456789";"OV";"Debit";"twelve";"Transfer";"";"1.071,80";""
+"##;
+
+            #[test]
+            fn sniffs_english_and_dutch_headers() {
+                assert_eq!(Ing.sniff(SAMPLE), 1.0);
+                let dutch = "\"Datum\";\"Naam / Omschrijving\";\"Rekening\";\"Tegenrekening\";\"Code\";\"Af Bij\";\"Bedrag (EUR)\";\"Mutatiesoort\";\"Mededelingen\"";
+                assert_eq!(Ing.sniff(dutch), 1.0);
+                assert!(Ing.sniff("IBAN/BBAN,Munt,Volgnr,Datum,Bedrag") < MIN_CONFIDENCE);
+            }
+
+            #[test]
+            fn imports_current_account() {
+                let directory = directory("ing", &[("ing.csv", SAMPLE)]);
+                let mut report = ImportReport::default();
+                let transactions = Ing
+                    .import(
+                        &directory.join("ing.csv"),
+                        &owner(vec![checking()]),
+                        &mut report,
+                    )
+                    .unwrap();
+                assert_eq!(transactions.len(), 3);
+                assert_eq!(report.accepted().count(), 3);
+                assert_eq!(report.rejected().count(), 1);
+
+                let [payment, card, savings] = &transactions[..] else {
+                    unreachable!()
+                };
+                assert_eq!(payment.direction(&checking()), Some(Direction::Outgoing));
+                assert_eq!(payment.amount, dec!(12.50));
+                assert_eq!(payment.sink.id(), shop().id());
+                assert_eq!(payment.inherent_tags, "#food");
+
+                assert_eq!(card.sink, Node::Terminal(String::from("CT123456")));
+                assert_eq!(card.amount, dec!(3.20));
+
+                assert_eq!(savings.direction(&checking()), Some(Direction::Incoming));
+                assert_eq!(savings.amount, dec!(1000));
+                let Node::SubAccount(sub_account) = &savings.source else {
+                    panic!("expecting a sub-account, got {:?}", savings.source)
+                };
+                assert_eq!(sub_account.bsan, "V12345678");
+                assert_eq!(sub_account.account_type, Some(AccountType::Saving));
+            }
+        }
+    }
+
+    pub mod abn_amro;
+    pub mod camt053;
+    pub mod mt940;
+    pub mod ofx;
+    pub mod rabobank;
+    pub mod sepa;
+}
+
+pub mod error {
+    use rust_decimal::Decimal;
+    use std::{fmt, path::PathBuf};
+    use thiserror::Error;
+
+    /// Where in an input file something went wrong.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Location {
+        pub file: PathBuf,
+        /// 1-based line on which the record starts.
+        pub line: Option<u64>,
+        /// 1-based column (field) of the offending value, if it is known.
+        pub column: Option<u64>,
+        /// The record as it appears in the file.
+        pub record: Option<String>,
+    }
+    impl Location {
+        pub fn in_file(file: PathBuf) -> Self {
+            Location {
+                file,
+                line: None,
+                column: None,
+                record: None,
+            }
+        }
+    }
+    impl fmt::Display for Location {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.file.display())?;
+            if let Some(line) = self.line {
+                write!(f, ":{}", line)?;
+            }
+            if let Some(column) = self.column {
+                write!(f, ":{}", column)?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Balances of an account that don't add up.
+    #[derive(Debug, PartialEq, Eq)]
+    pub struct Balances {
+        pub account: String,
+        /// The balance before the transactions.
+        pub opening: Decimal,
+        /// The balance after the transactions, according to the bank.
+        pub closing: Decimal,
+        /// The balance after the transactions, according to `opening` and the transactions.
+        pub computed: Decimal,
+    }
+
+    /// A record that was read fine, but can't be turned into a `Transaction`.
+    #[derive(Debug, Error, PartialEq, Eq)]
+    pub enum ConversionError {
+        #[error("cannot determine the counterparty of the transaction")]
+        UnknownCounterparty,
+    }
+
+    #[derive(Debug, Error)]
+    pub enum ImportError {
+        #[error("cannot read {path:?}: {source}")]
+        Io {
+            path: PathBuf,
+            #[source]
+            source: std::io::Error,
+        },
+        #[error("the directory {0:?} contains no files in a supported format")]
+        NoImportableFiles(PathBuf),
+        #[error("expecting a path to a directory or a file, got {0:?}")]
+        UnsupportedPath(PathBuf),
+        #[error("{0:?} is not in any supported format")]
+        UnknownFormat(PathBuf),
+        #[error("expecting a path to a .json file, got {0:?}")]
+        NotJson(PathBuf),
+        #[error("invalid profile {path:?}: {source}")]
+        Profile {
+            path: PathBuf,
+            #[source]
+            source: serde_json::Error,
+        },
+        #[error("{location}: malformed record: {source}")]
+        Malformed {
+            location: Location,
+            #[source]
+            source: csv::Error,
+        },
+        #[error("{location}: malformed document: {source}")]
+        Xml {
+            location: Location,
+            #[source]
+            source: Box<roxmltree::Error>,
+        },
+        #[error("{location}: {message}")]
+        Invalid { location: Location, message: String },
+        #[error(
+            "{location}: {} closes at {}, but opening at {} with the transactions in between gives {}",
+            .balances.account, .balances.closing, .balances.opening, .balances.computed
+        )]
+        BalanceMismatch {
+            location: Location,
+            balances: Box<Balances>,
+        },
+        #[error("{location}: {source}")]
+        Conversion {
+            location: Location,
+            #[source]
+            source: ConversionError,
+        },
+    }
+}
+
+pub mod report {
+    use super::error::{ImportError, Location};
+    use crate::canonical::{identify::*, transaction::Transaction};
+    use std::fmt;
+
+    /// What happened to a single record during an import.
+    #[derive(Debug)]
+    pub enum Outcome {
+        /// Turned into a `Transaction`.
+        Accepted,
+        /// Deliberately ignored, e.g. an empty record. Holds the reason.
+        Skipped(String),
+        /// Couldn't be turned into a `Transaction`.
+        Rejected(ImportError),
+        /// Read fine, but doesn't add up with the rest of the file. E.g.: a
+        /// statement whose closing balance doesn't match its transactions.
+        Flagged(ImportError),
+    }
+
+    #[derive(Debug)]
+    pub struct Row {
+        pub location: Location,
+        pub outcome: Outcome,
+    }
+
+    /// An accepted record that describes the same transaction as an earlier one, e.g.:
+    /// because the exports it came from overlap. Only the transaction of `kept` remains.
+    #[derive(Debug)]
+    pub struct Merge {
+        pub transaction: ID<Transaction>,
+        pub kept: Location,
+        pub merged: Location,
+    }
+
+    /// Per-record account of an import, so no record goes missing without a trace.
+    #[derive(Debug, Default)]
+    pub struct ImportReport {
+        pub rows: Vec<Row>,
+        pub merges: Vec<Merge>,
+    }
+    impl ImportReport {
+        pub fn record(&mut self, location: Location, outcome: Outcome) {
+            self.rows.push(Row { location, outcome });
+        }
+        pub fn merge(&mut self, transaction: ID<Transaction>, kept: Location, merged: Location) {
+            self.merges.push(Merge {
+                transaction,
+                kept,
+                merged,
+            });
+        }
+        pub fn accepted(&self) -> impl Iterator<Item = &Row> {
+            self.rows
+                .iter()
+                .filter(|row| matches!(row.outcome, Outcome::Accepted))
+        }
+        pub fn skipped(&self) -> impl Iterator<Item = (&Location, &str)> {
+            self.rows.iter().filter_map(|row| match &row.outcome {
+                Outcome::Skipped(reason) => Some((&row.location, reason.as_str())),
+                _ => None,
+            })
+        }
+        pub fn rejected(&self) -> impl Iterator<Item = &ImportError> {
+            self.rows.iter().filter_map(|row| match &row.outcome {
+                Outcome::Rejected(err) => Some(err),
+                _ => None,
+            })
+        }
+        pub fn flagged(&self) -> impl Iterator<Item = &ImportError> {
+            self.rows.iter().filter_map(|row| match &row.outcome {
+                Outcome::Flagged(err) => Some(err),
+                _ => None,
+            })
+        }
+    }
+    /// One line summary, followed by a line per record that wasn't accepted.
+    impl fmt::Display for ImportReport {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            writeln!(
+                f,
+                "Accepted {}, skipped {}, rejected {} record(s), {} flag(s), merged {} duplicate(s)",
+                self.accepted().count(),
+                self.skipped().count(),
+                self.rejected().count(),
+                self.flagged().count(),
+                self.merges.len()
+            )?;
+            for (location, reason) in self.skipped() {
+                writeln!(f, "> Skipped {}: {}", location, reason)?;
+            }
+            for err in self.rejected() {
+                writeln!(f, "> Rejected {}", err)?;
+            }
+            for err in self.flagged() {
+                writeln!(f, "> Flagged {}", err)?;
+            }
+            for merge in &self.merges {
+                writeln!(
+                    f,
+                    "> Merged {} into {} ({})",
+                    merge.merged, merge.kept, merge.transaction
+                )?;
+            }
+            Ok(())
+        }
+    }
+}
+
+pub mod importer {
+    use super::{
+        error::{ConversionError, ImportError, Location},
+        report::{ImportReport, Outcome},
+        serializers::{
+            abn_amro::AbnAmro, camt053::Camt053, ing::Ing, mt940::Mt940, ofx::Ofx,
+            rabobank::Rabobank,
+        },
+    };
+    use crate::canonical::{identify::*, state::Owner, transaction::*};
+    use csv::StringRecord;
+    use rust_decimal::Decimal;
+    use serde::de::DeserializeOwned;
+    use std::{
+        fs::File,
+        io::{self, Read},
+        path::Path,
+    };
+
+    /// Importers need at least this much confidence before they get to read a file.
+    pub const MIN_CONFIDENCE: f32 = 0.5;
+
+    /// How many bytes from the start of a file are handed to `BankImporter::sniff`.
+    const HEADER_SIZE: u64 = 4096;
+
+    /// Knows how to read one bank's export format.
+    pub trait BankImporter {
+        /// Human readable name of the format, e.g.: "ING".
+        fn name(&self) -> &'static str;
+        /// Confidence between 0.0 and 1.0 that a file starting with `header`
+        /// is in the format of this importer.
+        fn sniff(&self, header: &str) -> f32;
+        /// Convert the records in the file at `path` into transactions. Records
+        /// that don't convert end up in `report`, they don't fail the import.
+        ///
+        /// Every returned transaction has its own `Outcome::Accepted` in `report`, recorded
+        /// in the same order: that's where the transaction came from.
+        fn import(
+            &self,
+            path: &Path,
+            profile: &Owner,
+            report: &mut ImportReport,
+        ) -> Result<Vec<Transaction>, ImportError>;
+    }
+
+    /// A raw csv record that can be converted into a `Transaction`.
+    pub trait CsvRecord: DeserializeOwned {
+        fn into_transaction(self, profile: &Owner) -> Result<Transaction, ConversionError>;
+    }
+
+    /// The set of importers to choose from when reading a file.
+    pub struct Registry {
+        importers: Vec<Box<dyn BankImporter>>,
+    }
+    impl Registry {
+        /// A registry without any importers.
+        pub fn empty() -> Self {
+            Self {
+                importers: Vec::new(),
+            }
+        }
+        pub fn register<I: BankImporter + 'static>(&mut self, importer: I) -> &mut Self {
+            self.importers.push(Box::new(importer));
+            self
+        }
+        /// The importer that is most confident it can read a file that starts
+        /// with `header`, if any is at least `MIN_CONFIDENCE` sure.
+        pub fn detect(&self, header: &str) -> Option<&dyn BankImporter> {
+            self.importers
+                .iter()
+                .map(|importer| (importer.sniff(header), importer))
+                .filter(|(confidence, _)| *confidence >= MIN_CONFIDENCE)
+                .max_by(|(a, _), (b, _)| a.total_cmp(b))
+                .map(|(_, importer)| importer.as_ref())
+        }
+        /// Like `detect`, for the file at `path`.
+        pub fn detect_file(&self, path: &Path) -> io::Result<Option<&dyn BankImporter>> {
+            Ok(self.detect(&read_header(path)?))
+        }
+    }
+    /// All importers that ship with this crate.
+    impl Default for Registry {
+        fn default() -> Self {
+            let mut registry = Self::empty();
+            registry
+                .register(Ing)
+                .register(Rabobank)
+                .register(AbnAmro)
+                .register(Camt053)
+                .register(Mt940)
+                .register(Ofx);
+            registry
+        }
+    }
+
+    /// Set the balance of `account` after each of `transactions`, starting at `opening`.
+    /// For statements that only report the balance before and after all of their
+    /// transactions, which are in the bank's order.
+    pub fn running_balances(transactions: &mut [Transaction], account: &Node, opening: Decimal) {
+        let mut amount = opening;
+        for transaction in transactions {
+            amount += match transaction.direction(account) {
+                Some(Direction::Incoming) => transaction.amount,
+                _ => -transaction.amount,
+            };
+            transaction.balance = Some(Balance {
+                of: account.id(),
+                amount,
+            });
+        }
+    }
+
+    /// The first few KiB of the file at `path`, lossily decoded as UTF-8.
+    pub fn read_header(path: &Path) -> io::Result<String> {
+        let mut bytes = Vec::new();
+        File::open(path)?
+            .take(HEADER_SIZE)
+            .read_to_end(&mut bytes)?;
+        let header = String::from_utf8_lossy(&bytes);
+        Ok(header.trim_start_matches('\u{feff}').to_string())
+    }
+
+    /// Fraction of `columns` that appear as (optionally quoted) fields in the
+    /// first line of `header`.
+    pub fn column_match(header: &str, delimiter: char, columns: &[&str]) -> f32 {
+        let first_line = header.lines().next().unwrap_or_default();
+        let fields: Vec<_> = first_line
+            .split(delimiter)
+            .map(|field| field.trim().trim_matches('"'))
+            .collect();
+        let found = columns
+            .iter()
+            .filter(|column| fields.contains(column))
+            .count();
+        found as f32 / columns.len() as f32
+    }
+
+    /// Read every record of the csv file at `path` as an `R` and convert it
+    /// into a `Transaction`. The outcome of every record ends up in `report`.
+    ///
+    /// Without `has_headers`, records are deserialized by field position.
+    pub fn read_csv<R: CsvRecord>(
+        path: &Path,
+        delimiter: u8,
+        has_headers: bool,
+        profile: &Owner,
+        report: &mut ImportReport,
+    ) -> Result<Vec<Transaction>, ImportError> {
+        let file = File::open(path).map_err(|source| ImportError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let mut transactions: Vec<Transaction> = Vec::new();
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(has_headers)
+            .flexible(true)
+            .from_reader(file);
+        let headers = if has_headers {
+            match reader.byte_headers() {
+                Ok(headers) => Some(StringRecord::from_byte_record_lossy(headers.clone())),
+                Err(source) => {
+                    let location = Location::in_file(path.to_path_buf());
+                    let err = ImportError::Malformed {
+                        location: location.clone(),
+                        source,
+                    };
+                    report.record(location, Outcome::Rejected(err));
+                    return Ok(transactions);
+                }
+            }
+        } else {
+            None
+        };
+        let separator = char::from(delimiter).to_string();
+        for record in reader.byte_records() {
+            let mut location = Location::in_file(path.to_path_buf());
+            let record = match record {
+                Ok(record) => {
+                    location.line = record.position().map(|pos| pos.line());
+                    // Not every bank exports UTF-8, don't reject a record over an odd character.
+                    StringRecord::from_byte_record_lossy(record)
+                }
+                Err(source) => {
+                    location.line = source.position().map(|pos| pos.line());
+                    let err = ImportError::Malformed {
+                        location: location.clone(),
+                        source,
+                    };
+                    report.record(location, Outcome::Rejected(err));
+                    continue;
+                }
+            };
+            location.record = Some(record.iter().collect::<Vec<_>>().join(&separator));
+
+            if record.iter().all(|field| field.trim().is_empty()) {
+                report.record(location, Outcome::Skipped(String::from("empty record")));
+                continue;
+            }
+            let outcome = match record.deserialize::<R>(headers.as_ref()) {
+                Err(source) => {
+                    if let csv::ErrorKind::Deserialize { err, .. } = source.kind() {
+                        location.column = err.field().map(|field| field + 1);
+                    }
+                    Outcome::Rejected(ImportError::Malformed {
+                        location: location.clone(),
+                        source,
+                    })
+                }
+                Ok(row) => match row.into_transaction(profile) {
+                    Ok(transaction) => {
+                        transactions.push(transaction);
+                        Outcome::Accepted
+                    }
+                    Err(source) => Outcome::Rejected(ImportError::Conversion {
+                        location: location.clone(),
+                        source,
+                    }),
+                },
+            };
+            report.record(location, outcome);
+        }
+        Ok(transactions)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::canonical::fixtures::*;
+        use rust_decimal_macros::dec;
+
+        #[test]
+        fn registry_picks_the_most_confident_importer() {
+            let registry = Registry::default();
+            let name = |header: &str| registry.detect(header).map(|importer| importer.name());
+            let ing = "Date;Name / Description;Account;Counterparty;Code;Debit/credit;Amount (EUR);Transaction type;Notifications";
+            assert_eq!(name(ing), Some("ING"));
+            let abn_amro = "417164300\tEUR\t20240102\t100,00\t87,50\t20240102\t-12,50\tShop";
+            assert_eq!(name(abn_amro), Some("ABN AMRO"));
+            let camt053 = "<?xml version=\"1.0\"?><Document xmlns=\"urn:iso:std:iso:20022:tech:xsd:camt.053.001.02\">";
+            assert_eq!(name(camt053), Some("CAMT.053"));
+            let mt940 = ":20:STMT\n:25:NL91ABNA0417164300\n:28C:1\n:60F:C240101EUR100,00\n:61:";
+            assert_eq!(name(mt940), Some("MT940"));
+            assert_eq!(name("OFXHEADER:100\nDATA:OFXSGML"), Some("OFX"));
+            assert_eq!(name("Date,Amount,Payee"), None);
+        }
+
+        #[test]
+        fn running_balances_follow_direction() {
+            let mut transactions = vec![
+                transaction(date(2024, 1, 1), &shop(), &checking(), dec!(50)),
+                transaction(date(2024, 1, 2), &checking(), &shop(), dec!(20)),
+                transaction(date(2024, 1, 2), &checking(), &joint(), dec!(5)),
+            ];
+            running_balances(&mut transactions, &checking(), dec!(100));
+            let balances: Vec<_> = transactions
+                .iter()
+                .map(|transaction| transaction.balance.clone().unwrap())
+                .collect();
+            assert!(balances.iter().all(|balance| balance.of == checking().id()));
+            let amounts: Vec<_> = balances.iter().map(|balance| balance.amount).collect();
+            assert_eq!(amounts, [dec!(150), dec!(130), dec!(125)]);
+        }
+    }
+}
+
+pub mod import {
+    use super::{
+        error::{ImportError, Location},
+        importer::Registry,
+        report::{ImportReport, Outcome},
+    };
+    use crate::{
+        canonical::{identify::*, state::Owner, transaction::*},
+        rules,
+    };
+    use std::{
+        collections::HashMap,
+        fs::{self, File},
+        path,
+    };
+
+    /// Deserialize bank exports into a Vector of Transactions, using the
+    /// importers of `Registry::default()`.
+    ///
+    /// `file_path` can point to:
+    /// - a directory that contains at least 1 file in a supported format. In this
+    ///   case, all such files in that directory will be deserialized, each with the
+    ///   importer that recognizes it. Other files are listed as skipped in the report.
+    /// - a single file in a supported format. In this case, just this file will be
+    ///   deserialized.
+    ///
+    /// The resulting transactions satisfy the properties of `Transactions`, and are tagged
+    /// by the rules of `profile` (see `rules`). Identical
+    /// rows within one export are different transactions (see `Transaction::occurrence`),
+    /// identical rows in different exports (the same file twice, or overlapping date
+    /// ranges) are the same transaction: those are merged and listed in the report.
+    ///
+    /// Records that can't be read or converted don't fail the import, and neither do files
+    /// that can't be read at all: they are listed in the returned `ImportReport` instead.
+    /// Only finding nothing to import is an error.
+    pub fn transactions_from_path<'a>(
+        file_path: &path::Path,
+        profile: &Owner,
+    ) -> Result<(Transactions<'a>, ImportReport), ImportError> {
+        import_from_path(file_path, profile, &Registry::default())
+    }
+
+    /// Like `transactions_from_path`, choosing from the importers in `registry`.
+    pub fn import_from_path<'a>(
+        file_path: &path::Path,
+        profile: &Owner,
+        registry: &Registry,
+    ) -> Result<(Transactions<'a>, ImportReport), ImportError> {
+        import_with_progress(file_path, profile, registry, |_| {})
+    }
+
+    /// How far along an import is: about to read the file at `path`, which is
+    /// file `index` (0-based) of `total`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct Progress<'p> {
+        pub path: &'p path::Path,
+        pub importer: &'static str,
+        pub index: usize,
+        pub total: usize,
+    }
+
+    /// Like `import_from_path`, calling `progress` before reading every file.
+    pub fn import_with_progress<'a, F>(
+        file_path: &path::Path,
+        profile: &Owner,
+        registry: &Registry,
+        mut progress: F,
+    ) -> Result<(Transactions<'a>, ImportReport), ImportError>
+    where
+        F: FnMut(Progress),
+    {
+        let io_error = |path: &path::Path| {
+            let path = path.to_path_buf();
+            move |source| ImportError::Io { path, source }
+        };
+        let mut report = ImportReport::default();
+        let mut files = Vec::new();
+        if file_path.is_dir() {
+            let mut paths = Vec::new();
+            for entry in fs::read_dir(file_path).map_err(io_error(file_path))? {
+                let path = entry.map_err(io_error(file_path))?.path();
+                if path.is_file() {
+                    paths.push(path);
+                }
+            }
+            paths.sort();
+            for path in paths {
+                match registry.detect_file(&path).map_err(io_error(&path)) {
+                    Ok(Some(importer)) => files.push((path, importer)),
+                    Ok(None) => report.record(
+                        Location::in_file(path),
+                        Outcome::Skipped(String::from("not in a supported format")),
+                    ),
+                    Err(err) => report.record(Location::in_file(path), Outcome::Rejected(err)),
+                }
+            }
+            if files.is_empty() {
+                return Err(ImportError::NoImportableFiles(file_path.to_path_buf()));
+            }
+        } else if file_path.is_file() {
+            match registry
+                .detect_file(file_path)
+                .map_err(io_error(file_path))?
+            {
+                Some(importer) => files.push((file_path.to_path_buf(), importer)),
+                None => return Err(ImportError::UnknownFormat(file_path.to_path_buf())),
+            }
+        } else {
+            return Err(ImportError::UnsupportedPath(file_path.to_path_buf()));
+        }
+
+        let mut transactions: Vec<Transaction> = Vec::new();
+        let mut origins: Vec<Location> = Vec::new();
+        let total = files.len();
+        for (index, (path, importer)) in files.into_iter().enumerate() {
+            progress(Progress {
+                path: &path,
+                importer: importer.name(),
+                index,
+                total,
+            });
+            let recorded = report.rows.len();
+            match importer.import(&path, profile, &mut report) {
+                Ok(mut imported) => {
+                    number_occurrences(&mut imported);
+                    transactions.append(&mut imported);
+                    origins.extend(
+                        report.rows[recorded..]
+                            .iter()
+                            .filter(|row| matches!(row.outcome, Outcome::Accepted))
+                            .map(|row| row.location.clone()),
+                    );
+                }
+                Err(err) => {
+                    // Nothing of the file is imported, so neither are its records.
+                    report.rows.truncate(recorded);
+                    report.record(Location::in_file(path), Outcome::Rejected(err));
+                }
+            }
+        }
+        merge_duplicates(&transactions, &origins, &mut report);
+
+        let mut transactions = Transactions::new(transactions);
+        rules::apply(&mut transactions, profile);
+        Ok((transactions, report))
+    }
+
+    /// Record every transaction with the ID of an earlier one in `report`. `origins` holds
+    /// the location of every transaction. `Transactions::new` does the actual merging.
+    fn merge_duplicates(
+        transactions: &[Transaction],
+        origins: &[Location],
+        report: &mut ImportReport,
+    ) {
+        let mut first: HashMap<ID<Transaction>, &Location> = HashMap::new();
+        for (transaction, origin) in transactions.iter().zip(origins) {
+            let id = transaction.id();
+            match first.get(&id) {
+                Some(kept) => report.merge(id, (*kept).clone(), origin.clone()),
+                None => {
+                    first.insert(id, origin);
+                }
+            }
+        }
+    }
+
+    pub fn profile_from_path(file_path: &path::PathBuf) -> Result<Owner, ImportError> {
+        use serde_json::from_reader;
+        if !(file_path.is_file() && file_path.extension().is_some_and(|ext| ext == "json")) {
+            return Err(ImportError::NotJson(file_path.clone()));
+        }
+        let file = File::open(file_path).map_err(|source| ImportError::Io {
+            path: file_path.clone(),
+            source,
+        })?;
+        from_reader(file).map_err(|source| ImportError::Profile {
+            path: file_path.clone(),
+            source,
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::{
+            canonical::fixtures::*,
+            from_files::{error::ConversionError, importer::BankImporter},
+        };
+        use rust_decimal_macros::dec;
+
+        const HEADER: &str = "Date;Name / Description;Account;Counterparty;Code;Debit/credit;Amount (EUR);Transaction type;Notifications;Resulting balance;Tag";
+
+        fn import(name: &str, rows: &[&str]) -> (Vec<Transaction>, ImportReport) {
+            let contents = [&[HEADER], rows].concat().join("\n");
+            let directory = directory(name, &[("ing.csv", &contents), ("notes.txt", "")]);
+            let profile = owner(vec![checking()]);
+            let (transactions, report) = transactions_from_path(&directory, &profile).unwrap();
+            (transactions.iter().cloned().collect(), report)
+        }
+
+        #[test]
+        fn reports_every_record() {
+            let (transactions, report) = import(
+                "report",
+                &[
+                    "20240102;Shop;NL91ABNA0417164300;NL44RABO0123456789;OV;Debit;12,50;Transfer;Groceries;87,50;",
+                    ";;;;;;;;;;",
+                    "20240103;Shop;NL91ABNA0417164300;NL44RABO0123456789;OV;Debit;twelve;Transfer;;75,00;",
+                    "20240104;Someone;NL91ABNA0417164300;;OV;Credit;5,00;Transfer;Gift;80,00;",
+                ],
+            );
+            assert_eq!(transactions.len(), 1);
+            assert_eq!(transactions[0].amount, dec!(12.50));
+            assert_eq!(report.accepted().count(), 1);
+            // The empty record, and notes.txt.
+            assert_eq!(report.skipped().count(), 2);
+
+            let rejected: Vec<_> = report.rejected().collect();
+            let [ImportError::Malformed { location, .. }, ImportError::Conversion { source, .. }] =
+                &rejected[..]
+            else {
+                panic!(
+                    "expecting a malformed and an unconvertible record, got {:?}",
+                    rejected
+                )
+            };
+            assert_eq!(location.line, Some(4));
+            assert!(location.record.as_deref().unwrap().contains("twelve"));
+            assert_eq!(source, &ConversionError::UnknownCounterparty);
+        }
+
+        #[test]
+        fn report_lists_what_was_not_accepted() {
+            let (_, report) = import("report-display", &[";;;;;;;;;;"]);
+            let text = report.to_string();
+            assert!(text.starts_with("Accepted 0, skipped 2, rejected 0 record(s)"));
+            assert!(text.contains("empty record") && text.contains("not in a supported format"));
+        }
+
+        /// Reads files that start with "GOOD" into a transaction, fails on files that
+        /// start with "BAD" after reading one record.
+        struct Fragile;
+        impl BankImporter for Fragile {
+            fn name(&self) -> &'static str {
+                "Fragile"
+            }
+            fn sniff(&self, header: &str) -> f32 {
+                match header.starts_with("GOOD") || header.starts_with("BAD") {
+                    true => 1.0,
+                    false => 0.0,
+                }
+            }
+            fn import(
+                &self,
+                path: &path::Path,
+                _: &Owner,
+                report: &mut ImportReport,
+            ) -> Result<Vec<Transaction>, ImportError> {
+                let location = Location::in_file(path.to_path_buf());
+                report.record(location.clone(), Outcome::Accepted);
+                match fs::read_to_string(path).unwrap().starts_with("GOOD") {
+                    true => Ok(vec![transaction(
+                        date(2024, 1, 1),
+                        &checking(),
+                        &shop(),
+                        dec!(1),
+                    )]),
+                    false => Err(ImportError::Conversion {
+                        location,
+                        source: ConversionError::UnknownCounterparty,
+                    }),
+                }
+            }
+        }
+
+        fn registry() -> Registry {
+            let mut registry = Registry::empty();
+            registry.register(Fragile);
+            registry
+        }
+
+        #[test]
+        fn failing_file_is_rejected_and_the_rest_imported() {
+            let directory = directory(
+                "failing-file",
+                &[("a.txt", "GOOD"), ("b.txt", "BAD"), ("c.txt", "other")],
+            );
+            let profile = owner(vec![checking()]);
+            let (transactions, report) =
+                import_from_path(&directory, &profile, &registry()).unwrap();
+            assert_eq!(transactions.iter().count(), 1);
+            assert_eq!(report.accepted().count(), 1);
+            assert_eq!(report.skipped().count(), 1);
+            let rejected: Vec<_> = report
+                .rows
+                .iter()
+                .filter(|row| matches!(row.outcome, Outcome::Rejected(_)))
+                .collect();
+            assert_eq!(rejected.len(), 1);
+            assert_eq!(rejected[0].location.file, directory.join("b.txt"));
+        }
+
+        #[test]
+        fn progress_precedes_every_importable_file() {
+            let directory = directory(
+                "progress",
+                &[("a.txt", "GOOD"), ("b.txt", "other"), ("c.txt", "BAD")],
+            );
+            let profile = owner(vec![checking()]);
+            let mut seen = Vec::new();
+            import_with_progress(&directory, &profile, &registry(), |progress| {
+                seen.push((
+                    progress.path.to_path_buf(),
+                    progress.importer,
+                    progress.index,
+                    progress.total,
+                ))
+            })
+            .unwrap();
+            assert_eq!(
+                seen,
+                [
+                    (directory.join("a.txt"), "Fragile", 0, 2),
+                    (directory.join("c.txt"), "Fragile", 1, 2),
+                ]
+            );
+        }
+
+        #[test]
+        fn nothing_importable_fails() {
+            let directory = directory("nothing-importable", &[("a.txt", "other")]);
+            let profile = owner(vec![checking()]);
+            let result = import_from_path(&directory, &profile, &registry());
+            assert!(matches!(result, Err(ImportError::NoImportableFiles(_))));
+        }
+    }
+}
@@ -0,0 +1,328 @@
+use super::{
+    sepa::{collapse_whitespace, SepaDescription},
+    serde_amount,
+};
+use crate::{
+    canonical::{account::*, identify::*, state::Owner, transaction::*},
+    from_files::{
+        error::{ConversionError, ImportError},
+        importer::{read_csv, BankImporter, CsvRecord},
+        report::ImportReport,
+    },
+};
+use chrono::NaiveDate;
+use iban::Iban;
+use regex::Regex;
+use rust_decimal::Decimal;
+use serde::{self, Deserialize, Deserializer};
+use std::{collections::BTreeMap, path::Path, sync::LazyLock};
+
+crate::date_deserializer_from_format!("%Y%m%d");
+
+/// ABN AMRO exports the bare account number ("123456789"), turn it into the IBAN.
+fn deserialize_account<'de, D>(deserializer: D) -> Result<Iban, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: String = String::deserialize(deserializer)?;
+    account_iban(&s).ok_or_else(|| {
+        serde::de::Error::custom(format!("{:?} is not an ABN AMRO account number", s))
+    })
+}
+
+/// The IBAN of an ABN AMRO account number, or of an IBAN (e.g.: newer exports).
+pub fn account_iban(account: &str) -> Option<Iban> {
+    let account = account.trim();
+    if let Ok(iban) = Iban::parse(account) {
+        return Some(iban);
+    }
+    if account.is_empty() || account.len() > 10 || !account.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let bban = format!("ABNA{:0>10}", account);
+    // ISO 13616: move the country code and "00" to the end, letters to numbers, mod 97.
+    let remainder = format!("{}NL00", bban)
+        .chars()
+        .map(|c| c.to_digit(36).unwrap())
+        .fold(0, |remainder, digit| {
+            let factor = if digit < 10 { 10 } else { 100 };
+            (remainder * factor + digit) % 97
+        });
+    Iban::parse(&format!("NL{:02}{}", 98 - remainder, bban)).ok()
+}
+
+/// Raw record of an ABN AMRO ".TAB" export. These files have no header, fields
+/// are in this order.
+#[derive(Debug, Deserialize, PartialEq, Eq, Hash)]
+pub struct AbnAmroCurrentAccount {
+    /// Account of the owner.
+    #[serde(deserialize_with = "deserialize_account")]
+    pub account: Iban,
+
+    /// "EUR"
+    pub currency: String,
+
+    /// YYYYMMDD
+    #[serde(with = "local_date_deserializer")]
+    pub date: NaiveDate,
+
+    /// 0,00 - Balance of the account before this transaction.
+    #[serde(with = "serde_amount")]
+    pub start_balance: Decimal,
+
+    /// 0,00 - Balance of the account after this transaction.
+    #[serde(with = "serde_amount")]
+    pub end_balance: Decimal,
+
+    /// YYYYMMDD
+    pub value_date: String,
+
+    /// "-0,00" | "0,00" - Sign is the direction with respect to `self.account`.
+    #[serde(with = "serde_amount")]
+    pub amount: Decimal,
+
+    /// Packed, space padded fields. See `SepaDescription` and `CardPayment`.
+    pub description: String,
+}
+
+/// ".TAB" exports from ABN AMRO.
+pub struct AbnAmro;
+impl BankImporter for AbnAmro {
+    fn name(&self) -> &'static str {
+        "ABN AMRO"
+    }
+    /// No header to go by, so check whether the first line looks like a record.
+    fn sniff(&self, header: &str) -> f32 {
+        let first_line = header.lines().next().unwrap_or_default();
+        let fields: Vec<_> = first_line.split('\t').collect();
+        if fields.len() != 8 {
+            return 0.0;
+        }
+        let is_date = |field: &str| NaiveDate::parse_from_str(field, "%Y%m%d").is_ok();
+        let is_amount = |field: &str| serde_amount::to_decimal(field).is_ok();
+        let checks = [
+            account_iban(fields[0]).is_some(),
+            fields[1].len() == 3 && fields[1].chars().all(|c| c.is_ascii_uppercase()),
+            is_date(fields[2]),
+            is_amount(fields[3]),
+            is_amount(fields[4]),
+            is_date(fields[5]),
+            is_amount(fields[6]),
+        ];
+        checks.iter().filter(|check| **check).count() as f32 / checks.len() as f32
+    }
+    fn import(
+        &self,
+        path: &Path,
+        profile: &Owner,
+        report: &mut ImportReport,
+    ) -> Result<Vec<Transaction>, ImportError> {
+        read_csv::<AbnAmroCurrentAccount>(path, b'\t', false, profile, report)
+    }
+}
+
+impl CsvRecord for AbnAmroCurrentAccount {
+    fn into_transaction(self, profile: &Owner) -> Result<Transaction, ConversionError> {
+        owned_abn_amro_transaction(self, profile)
+    }
+}
+
+/// A payment with a debit card ("BEA", betaalautomaat) or a withdrawal
+/// from an ATM ("GEA", geldautomaat).
+#[derive(Debug, PartialEq, Eq)]
+pub struct CardPayment {
+    pub kind: CardPaymentKind,
+    /// Terminal ID, the "NR:" field.
+    pub terminal: Option<String>,
+    /// Merchant or ATM name.
+    pub name: Option<String>,
+}
+#[derive(Debug, PartialEq, Eq)]
+pub enum CardPaymentKind {
+    Terminal,
+    Atm,
+}
+impl CardPayment {
+    /// Parse both the current (`BEA, Apple Pay  Merchant,PAS123  NR:ABC123, 02.01.24/10:15 CITY`)
+    /// and the older (`BEA   NR:ABC123   02.01.24/10.15 Merchant,PAS123`) layout.
+    pub fn parse(description: &str) -> Option<Self> {
+        let description = description.trim_start();
+        let kind = if description.starts_with("BEA") {
+            CardPaymentKind::Terminal
+        } else if description.starts_with("GEA") {
+            CardPaymentKind::Atm
+        } else {
+            return None;
+        };
+        static TERMINAL: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"NR:\s*(?<terminal>[^\s,]+)").unwrap());
+        static CURRENT: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"^[BG]EA,[^ ]*(?: \w+)*\s{2,}(?<name>.+?),PAS").unwrap());
+        static OLDER: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r"\d{2}\.\d{2}\.\d{2}/\d{2}[.:]\d{2}\s+(?<name>.+?),PAS").unwrap()
+        });
+        let name = CURRENT
+            .captures(description)
+            .or_else(|| OLDER.captures(description))
+            .map(|captures| collapse_whitespace(&captures["name"]));
+        Some(CardPayment {
+            kind,
+            terminal: TERMINAL
+                .captures(description)
+                .map(|captures| captures["terminal"].to_string()),
+            name,
+        })
+    }
+}
+
+pub fn owned_abn_amro_transaction(
+    abn_transaction: AbnAmroCurrentAccount,
+    owner: &Owner,
+) -> Result<Transaction, ConversionError> {
+    let id = owner.name_of(&Node::transfer_from(Account::iban_id(
+        &abn_transaction.account,
+    )));
+    let owner_account = Node::ProperAccount(Account {
+        iban: abn_transaction.account,
+        name: id.unwrap_or("UNNAMED_ACCOUNT".to_string()),
+    });
+    let balance = Balance {
+        of: owner_account.id(),
+        amount: abn_transaction.end_balance,
+    };
+    let counter_party = determine_node_type(&abn_transaction)?;
+    let (source, sink) = if abn_transaction.amount.is_sign_negative() {
+        (owner_account, counter_party)
+    } else {
+        (counter_party, owner_account)
+    };
+    Ok(Transaction {
+        date: abn_transaction.date,
+        source,
+        sink,
+        amount: abn_transaction.amount.abs(),
+        inherent_tags: String::new(),
+        description: collapse_whitespace(&abn_transaction.description),
+        sequence: None,
+        reference: None,
+        end_to_end: None,
+        mandate: None,
+        balance: Some(balance),
+        code: None,
+        occurrence: 0,
+        rule_tags: BTreeMap::new(),
+        category: None,
+    })
+}
+
+/// Like `ing::determine_node_type`, from the packed description.
+fn determine_node_type(abn_transaction: &AbnAmroCurrentAccount) -> Result<Node, ConversionError> {
+    if let Some(card_payment) = CardPayment::parse(&abn_transaction.description) {
+        let id = card_payment
+            .terminal
+            .or(card_payment.name)
+            .unwrap_or("UNKNOWN_TERM_ID".into());
+        return Ok(match card_payment.kind {
+            CardPaymentKind::Terminal => Node::Terminal(id),
+            CardPaymentKind::Atm => Node::Atm(id),
+        });
+    }
+
+    let sepa = SepaDescription::parse(&abn_transaction.description);
+    if let Some(iban) = sepa.iban.as_deref().and_then(|iban| Iban::parse(iban).ok()) {
+        return Ok(Node::ProperAccount(Account {
+            iban,
+            name: sepa.name.unwrap_or_default(),
+        }));
+    }
+
+    if abn_transaction
+        .description
+        .trim_start()
+        .starts_with("ABN AMRO")
+    {
+        return Ok(Node::Other("ABN AMRO".into()));
+    }
+
+    Err(ConversionError::UnknownCounterparty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canonical::fixtures::*;
+    use rust_decimal_macros::dec;
+
+    const SAMPLE: &str = "\
+417164300\tEUR\t20240102\t100,00\t87,50\t20240102\t-12,50\t/TRTP/SEPA OVERBOEKING/IBAN/NL44RABO0123456789/BIC/RABONL2U/NAME/Shop/REMI/Invoice 1/EREF/E2E-1\n\
+417164300\tEUR\t20240103\t87,50\t84,30\t20240103\t-3,20\tBEA, Apple Pay  Coffee Corner,PAS123  NR:CT123456, 03.01.24/10:15 AMSTERDAM\n\
+417164300\tEUR\t20240104\t84,30\t184,30\t20240104\t100,00\tSEPA Overboeking                 IBAN: NL02ABNA0123456789        BIC: ABNANL2A                    Naam: Joint                      Omschrijving: Pocket money\n\
+417164300\tEUR\t20240105\t184,30\t134,30\t20240105\t-50,00\tGEA   NR:ATM001   05.01.24/12.00 Cash Point,PAS123\n\
+417164300\tEUR\t2024-01-06\t134,30\t133,30\t20240106\t-1,00\tABN AMRO Bank N.V.               Fee\n\
+";
+
+    #[test]
+    fn account_number_to_iban() {
+        let iban = account_iban("417164300").unwrap();
+        assert_eq!(iban.as_str(), "NL91ABNA0417164300");
+        assert_eq!(account_iban("NL91ABNA0417164300"), Some(iban));
+        assert_eq!(account_iban("ABC"), None);
+    }
+
+    #[test]
+    fn sniffs_first_record() {
+        assert_eq!(AbnAmro.sniff(SAMPLE), 1.0);
+        assert_eq!(AbnAmro.sniff("\"IBAN/BBAN\",\"Munt\",\"Volgnr\""), 0.0);
+    }
+
+    #[test]
+    fn parses_card_payments() {
+        let current = CardPayment::parse(
+            "BEA, Apple Pay  Coffee Corner,PAS123  NR:CT123456, 03.01.24/10:15 AMSTERDAM",
+        );
+        assert_eq!(
+            current,
+            Some(CardPayment {
+                kind: CardPaymentKind::Terminal,
+                terminal: Some(String::from("CT123456")),
+                name: Some(String::from("Coffee Corner")),
+            })
+        );
+        let older =
+            CardPayment::parse("GEA   NR:ATM001   05.01.24/12.00 Cash Point,PAS123").unwrap();
+        assert_eq!(older.kind, CardPaymentKind::Atm);
+        assert_eq!(older.name.as_deref(), Some("Cash Point"));
+        assert_eq!(CardPayment::parse("SEPA Overboeking"), None);
+    }
+
+    #[test]
+    fn imports_tab_export() {
+        let directory = directory("abn-amro", &[("abn-amro.TAB", SAMPLE)]);
+        let mut report = ImportReport::default();
+        let transactions = AbnAmro
+            .import(
+                &directory.join("abn-amro.TAB"),
+                &owner(vec![checking()]),
+                &mut report,
+            )
+            .unwrap();
+        assert_eq!(transactions.len(), 4);
+        assert_eq!(report.rejected().count(), 1);
+
+        let [transfer, card, incoming, atm] = &transactions[..] else {
+            unreachable!()
+        };
+        assert_eq!(transfer.direction(&checking()), Some(Direction::Outgoing));
+        assert_eq!(transfer.amount, dec!(12.50));
+        assert_eq!(transfer.sink.id(), shop().id());
+        assert_eq!(transfer.sequence, None);
+
+        assert_eq!(card.sink, Node::Terminal(String::from("CT123456")));
+
+        assert_eq!(incoming.direction(&checking()), Some(Direction::Incoming));
+        assert_eq!(incoming.amount, dec!(100));
+        assert_eq!(incoming.source.id(), joint().id());
+
+        assert_eq!(atm.sink, Node::Atm(String::from("ATM001")));
+    }
+}
@@ -0,0 +1,509 @@
+//! ISO 20022 bank-to-customer statements (camt.053), as exported by every Dutch bank.
+use crate::{
+    canonical::{account::*, identify::*, state::Owner, transaction::*},
+    from_files::{
+        error::{Balances, ConversionError, ImportError, Location},
+        importer::{running_balances, BankImporter},
+        report::{ImportReport, Outcome},
+    },
+};
+use chrono::NaiveDate;
+use iban::Iban;
+use regex::Regex;
+use roxmltree::{Document, Node as XmlNode};
+use rust_decimal::Decimal;
+use std::{collections::BTreeMap, fs, path::Path, str::FromStr, sync::LazyLock};
+
+/// camt.053 XML statements. Every `<Stmt>` in the file is read, and its opening
+/// and closing balances are checked against the entries in between.
+pub struct Camt053;
+impl BankImporter for Camt053 {
+    fn name(&self) -> &'static str {
+        "CAMT.053"
+    }
+    fn sniff(&self, header: &str) -> f32 {
+        if header.contains("camt.053") {
+            1.0
+        } else if header.contains("<BkToCstmrStmt") {
+            0.9
+        } else {
+            0.0
+        }
+    }
+    fn import(
+        &self,
+        path: &Path,
+        profile: &Owner,
+        report: &mut ImportReport,
+    ) -> Result<Vec<Transaction>, ImportError> {
+        let text = fs::read_to_string(path).map_err(|source| ImportError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let document = match Document::parse(&text) {
+            Ok(document) => document,
+            Err(source) => {
+                let mut location = Location::in_file(path.to_path_buf());
+                location.line = Some(source.pos().row as u64);
+                location.column = Some(source.pos().col as u64);
+                let err = ImportError::Xml {
+                    location: location.clone(),
+                    source: Box::new(source),
+                };
+                report.record(location, Outcome::Rejected(err));
+                return Ok(Vec::new());
+            }
+        };
+        let reader = StatementReader {
+            document: &document,
+            path,
+            profile,
+        };
+        let mut transactions = Vec::new();
+        for statement in document.descendants().filter(|node| is(node, "Stmt")) {
+            transactions.append(&mut reader.read(statement, report));
+        }
+        Ok(transactions)
+    }
+}
+
+struct StatementReader<'a, 'input> {
+    document: &'a Document<'input>,
+    path: &'a Path,
+    profile: &'a Owner,
+}
+impl<'a, 'input> StatementReader<'a, 'input> {
+    fn location(&self, node: XmlNode) -> Location {
+        let position = self.document.text_pos_at(node.range().start);
+        let mut location = Location::in_file(self.path.to_path_buf());
+        location.line = Some(position.row as u64);
+        location.column = Some(position.col as u64);
+        location
+    }
+
+    fn read(&self, statement: XmlNode, report: &mut ImportReport) -> Vec<Transaction> {
+        let mut transactions = Vec::new();
+        let account = match text(statement, &["Acct", "Id", "IBAN"]).map(Iban::parse) {
+            Some(Ok(iban)) => iban,
+            _ => {
+                let location = self.location(statement);
+                let err = ImportError::Invalid {
+                    location: location.clone(),
+                    message: String::from("statement without a valid account IBAN"),
+                };
+                report.record(location, Outcome::Rejected(err));
+                return transactions;
+            }
+        };
+        let name = self
+            .profile
+            .name_of(&Node::transfer_from(Account::iban_id(&account)));
+        let owner_account = Node::ProperAccount(Account {
+            iban: account,
+            name: name.unwrap_or("UNNAMED_ACCOUNT".to_string()),
+        });
+
+        let mut booked = Decimal::ZERO;
+        for entry in children(statement, "Ntry") {
+            let location = self.location(entry);
+            let status = text(entry, &["Sts"]).or_else(|| text(entry, &["Sts", "Cd"]));
+            if status != Some("BOOK") {
+                report.record(
+                    location,
+                    Outcome::Skipped(String::from("entry isn't booked")),
+                );
+                continue;
+            }
+            match read_entry(entry, &owner_account) {
+                Ok(transaction) => {
+                    booked += if transaction.sink == owner_account {
+                        transaction.amount
+                    } else {
+                        -transaction.amount
+                    };
+                    transactions.push(transaction);
+                    report.record(location, Outcome::Accepted);
+                }
+                Err(err) => {
+                    let err = match err {
+                        EntryError::Invalid(message) => ImportError::Invalid {
+                            location: location.clone(),
+                            message,
+                        },
+                        EntryError::Conversion(source) => ImportError::Conversion {
+                            location: location.clone(),
+                            source,
+                        },
+                    };
+                    report.record(location, Outcome::Rejected(err));
+                }
+            }
+        }
+
+        let opening = balance(statement, &["OPBD", "PRCD"]);
+        let closing = balance(statement, &["CLBD"]);
+        match (opening, closing) {
+            (Some(opening), Some(closing)) if opening + booked != closing => {
+                let location = self.location(statement);
+                let err = ImportError::BalanceMismatch {
+                    location: location.clone(),
+                    balances: Box::new(Balances {
+                        account: account.to_string(),
+                        opening,
+                        closing,
+                        computed: opening + booked,
+                    }),
+                };
+                report.record(location, Outcome::Flagged(err));
+            }
+            // Only the balances around the statement are known, derive the ones in between.
+            (Some(opening), _) => running_balances(&mut transactions, &owner_account, opening),
+            _ => {}
+        }
+        transactions
+    }
+}
+
+enum EntryError {
+    Invalid(String),
+    Conversion(ConversionError),
+}
+
+fn read_entry(entry: XmlNode, owner_account: &Node) -> Result<Transaction, EntryError> {
+    let amount = text(entry, &["Amt"])
+        .and_then(|amount| Decimal::from_str(amount).ok())
+        .ok_or_else(|| EntryError::Invalid(String::from("entry without a valid <Amt>")))?;
+    let incoming = match text(entry, &["CdtDbtInd"]) {
+        Some("CRDT") => true,
+        Some("DBIT") => false,
+        _ => {
+            return Err(EntryError::Invalid(String::from(
+                "entry without a <CdtDbtInd>",
+            )))
+        }
+    };
+    let date = date_of(entry, "BookgDt")
+        .ok_or_else(|| EntryError::Invalid(String::from("entry without a valid <BookgDt>")))?;
+
+    // Batch entries can hold several transaction details, the first one describes the counterparty.
+    let details = child(entry, "NtryDtls").and_then(|details| child(details, "TxDtls"));
+    let (party, account) = if incoming {
+        ("Dbtr", "DbtrAcct")
+    } else {
+        ("Cdtr", "CdtrAcct")
+    };
+    let counterparty = Counterparty {
+        name: details.and_then(|details| {
+            text(details, &["RltdPties", party, "Nm"])
+                .or_else(|| text(details, &["RltdPties", party, "Pty", "Nm"]))
+        }),
+        iban: details.and_then(|details| text(details, &["RltdPties", account, "Id", "IBAN"])),
+    };
+    let remittance = details
+        .and_then(|details| text(details, &["RmtInf", "Ustrd"]))
+        .or_else(|| text(entry, &["AddtlNtryInf"]));
+
+    let reference = text(entry, &["AcctSvcrRef"])
+        .or_else(|| details.and_then(|details| text(details, &["Refs", "AcctSvcrRef"])))
+        .or_else(|| text(entry, &["NtryRef"]));
+    // "NOTPROVIDED" is what the initiator's bank fills in when there is none.
+    let end_to_end = details
+        .and_then(|details| text(details, &["Refs", "EndToEndId"]))
+        .filter(|id| *id != "NOTPROVIDED");
+    let mandate = details
+        .and_then(|details| text(details, &["Refs", "MndtId"]))
+        .map(|id| Mandate {
+            id: String::from(id),
+            creditor: details
+                .and_then(|details| {
+                    text(
+                        details,
+                        &["RltdPties", "CdtrSchmeId", "Id", "PrvtId", "Othr", "Id"],
+                    )
+                })
+                .map(String::from),
+        });
+
+    let counter_party = counterparty
+        .node(entry, text(entry, &["AddtlNtryInf"]))
+        .map_err(EntryError::Conversion)?;
+    let (source, sink) = if incoming {
+        (counter_party, owner_account.clone())
+    } else {
+        (owner_account.clone(), counter_party)
+    };
+    Ok(Transaction {
+        date,
+        source,
+        sink,
+        amount: amount.abs(),
+        inherent_tags: String::new(),
+        description: remittance.map(String::from).unwrap_or_default(),
+        sequence: None,
+        reference: reference.map(String::from),
+        end_to_end: end_to_end.map(String::from),
+        mandate,
+        balance: None,
+        code: bank_transaction_code(entry),
+        occurrence: 0,
+        rule_tags: BTreeMap::new(),
+        category: None,
+    })
+}
+
+/// Domain, family and sub-family of the bank transaction code, e.g.: "PMNT/RCDT/ESCT".
+fn bank_transaction_code(entry: XmlNode) -> Option<String> {
+    let code = [
+        text(entry, &["BkTxCd", "Domn", "Cd"]),
+        text(entry, &["BkTxCd", "Domn", "Fmly", "Cd"]),
+        text(entry, &["BkTxCd", "Domn", "Fmly", "SubFmlyCd"]),
+    ];
+    let code: Vec<&str> = code.into_iter().flatten().collect();
+    (!code.is_empty()).then(|| code.join("/"))
+}
+
+struct Counterparty<'a> {
+    name: Option<&'a str>,
+    iban: Option<&'a str>,
+}
+impl<'a> Counterparty<'a> {
+    /// Like `ing::determine_node_type`, from the related parties and the bank
+    /// transaction code of the entry.
+    fn node(&self, entry: XmlNode, additional_info: Option<&str>) -> Result<Node, ConversionError> {
+        let name = self.name.unwrap_or_default();
+        if let Some(iban) = self.iban.and_then(|iban| Iban::parse(iban).ok()) {
+            return Ok(Node::ProperAccount(Account {
+                iban,
+                name: String::from(name),
+            }));
+        }
+
+        static TERMINAL: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"Term: (?<terminalID>\w+)").unwrap());
+        let terminal_id = additional_info
+            .and_then(|info| TERMINAL.captures(info))
+            .map(|captures| captures["terminalID"].to_string());
+        let family = text(entry, &["BkTxCd", "Domn", "Fmly", "Cd"]);
+        let sub_family = text(entry, &["BkTxCd", "Domn", "Fmly", "SubFmlyCd"]);
+        let card_id = || terminal_id.clone().or(self.name.map(String::from));
+        match (family, sub_family) {
+            (Some("CCRD"), Some("CWDL")) => {
+                if let Some(id) = card_id() {
+                    return Ok(Node::Atm(id));
+                }
+            }
+            (Some("CCRD"), _) | (_, Some("POSD")) => {
+                if let Some(id) = card_id() {
+                    return Ok(Node::Terminal(id));
+                }
+            }
+            (Some("CHRG"), _) | (_, Some("CHRG")) => {
+                return Ok(Node::Other("Bank charges".into()));
+            }
+            _ => {}
+        }
+
+        match self.name {
+            Some(name) => Ok(Node::Other(name.into())),
+            None => Err(ConversionError::UnknownCounterparty),
+        }
+    }
+}
+
+/// The signed amount of the first balance of `statement` with one of the type `codes`.
+fn balance(statement: XmlNode, codes: &[&str]) -> Option<Decimal> {
+    children(statement, "Bal")
+        .find(|bal| {
+            text(*bal, &["Tp", "CdOrPrtry", "Cd"]).is_some_and(|code| codes.contains(&code))
+        })
+        .and_then(|bal| {
+            let amount = Decimal::from_str(text(bal, &["Amt"])?).ok()?;
+            match text(bal, &["CdtDbtInd"]) {
+                Some("DBIT") => Some(-amount),
+                _ => Some(amount),
+            }
+        })
+}
+
+/// `<Dt>` or the date part of `<DtTm>` inside `<element>`.
+fn date_of(entry: XmlNode, element: &str) -> Option<NaiveDate> {
+    let date = text(entry, &[element, "Dt"]).or_else(|| text(entry, &[element, "DtTm"]))?;
+    NaiveDate::parse_from_str(date.get(..10)?, "%Y-%m-%d").ok()
+}
+
+/// Element name without its namespace.
+fn is(node: &XmlNode, name: &str) -> bool {
+    node.is_element() && node.tag_name().name() == name
+}
+
+fn children<'a, 'input: 'a>(
+    node: XmlNode<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = XmlNode<'a, 'input>> + 'a {
+    node.children().filter(move |child| is(child, name))
+}
+
+fn child<'a, 'input>(node: XmlNode<'a, 'input>, name: &str) -> Option<XmlNode<'a, 'input>> {
+    node.children().find(|child| is(child, name))
+}
+
+/// Trimmed text of the element at `path` below `node`, if it isn't empty.
+fn text<'a, 'input>(node: XmlNode<'a, 'input>, path: &[&str]) -> Option<&'a str> {
+    path.iter()
+        .try_fold(node, |node, name| child(node, name))?
+        .text()
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canonical::fixtures::*;
+    use rust_decimal_macros::dec;
+
+    fn entry(amount: &str, indicator: &str, status: &str, inner: &str) -> String {
+        format!(
+            "<Ntry><Amt Ccy=\"EUR\">{amount}</Amt><CdtDbtInd>{indicator}</CdtDbtInd>\
+             <Sts>{status}</Sts><BookgDt><Dt>2024-01-02</Dt></BookgDt>\
+             <ValDt><Dt>2024-01-03</Dt></ValDt>{inner}</Ntry>"
+        )
+    }
+
+    fn statement(closing: &str, entries: &[String]) -> String {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+             <Document xmlns=\"urn:iso:std:iso:20022:tech:xsd:camt.053.001.02\"><BkToCstmrStmt><Stmt>\
+             <Acct><Id><IBAN>NL91ABNA0417164300</IBAN></Id></Acct>\
+             <Bal><Tp><CdOrPrtry><Cd>OPBD</Cd></CdOrPrtry></Tp><Amt Ccy=\"EUR\">100.00</Amt><CdtDbtInd>CRDT</CdtDbtInd></Bal>\
+             <Bal><Tp><CdOrPrtry><Cd>CLBD</Cd></CdOrPrtry></Tp><Amt Ccy=\"EUR\">{closing}</Amt><CdtDbtInd>CRDT</CdtDbtInd></Bal>\
+             {}</Stmt></BkToCstmrStmt></Document>",
+            entries.concat()
+        )
+    }
+
+    fn sample(closing: &str) -> String {
+        statement(
+            closing,
+            &[
+                entry(
+                    "12.50",
+                    "DBIT",
+                    "BOOK",
+                    "<AcctSvcrRef>20240102-0001</AcctSvcrRef>\
+                     <BkTxCd><Domn><Cd>PMNT</Cd><Fmly><Cd>ICDT</Cd><SubFmlyCd>ESCT</SubFmlyCd></Fmly></Domn></BkTxCd>\
+                     <NtryDtls><TxDtls><Refs><EndToEndId>E2E-1</EndToEndId></Refs>\
+                     <RltdPties><Cdtr><Nm>Shop</Nm></Cdtr><CdtrAcct><Id><IBAN>NL44RABO0123456789</IBAN></Id></CdtrAcct></RltdPties>\
+                     <RltdAgts><CdtrAgt><FinInstnId><BIC>RABONL2U</BIC></FinInstnId></CdtrAgt></RltdAgts>\
+                     <RmtInf><Ustrd>Invoice 1</Ustrd></RmtInf></TxDtls></NtryDtls>",
+                ),
+                entry(
+                    "3.20",
+                    "DBIT",
+                    "BOOK",
+                    "<NtryRef>0002</NtryRef>\
+                     <BkTxCd><Domn><Cd>PMNT</Cd><Fmly><Cd>CCRD</Cd><SubFmlyCd>POSD</SubFmlyCd></Fmly></Domn></BkTxCd>\
+                     <AddtlNtryInf>Coffee Term: CT123456</AddtlNtryInf>",
+                ),
+                entry(
+                    "30.00",
+                    "CRDT",
+                    "BOOK",
+                    "<NtryDtls><TxDtls><RltdPties><Dbtr><Nm>Joint</Nm></Dbtr>\
+                     <DbtrAcct><Id><IBAN>NL02ABNA0123456789</IBAN></Id></DbtrAcct></RltdPties></TxDtls></NtryDtls>",
+                ),
+                entry(
+                    "20.00",
+                    "DBIT",
+                    "BOOK",
+                    "<NtryDtls><TxDtls><Refs><EndToEndId>NOTPROVIDED</EndToEndId><MndtId>M-42</MndtId></Refs>\
+                     <RltdPties><Cdtr><Nm>Energy Co</Nm></Cdtr><CdtrAcct><Id><IBAN>NL44RABO0123456789</IBAN></Id></CdtrAcct>\
+                     <CdtrSchmeId><Id><PrvtId><Othr><Id>NL00ZZZ000000000000</Id></Othr></PrvtId></Id></CdtrSchmeId></RltdPties>\
+                     <RmtInf><Ustrd>Monthly bill</Ustrd></RmtInf></TxDtls></NtryDtls>",
+                ),
+                entry("1.00", "DBIT", "PDNG", ""),
+                entry("a lot", "DBIT", "BOOK", ""),
+            ],
+        )
+    }
+
+    fn import(name: &str, contents: &str) -> (Vec<Transaction>, ImportReport) {
+        let directory = directory(name, &[("statement.xml", contents)]);
+        let mut report = ImportReport::default();
+        let transactions = Camt053
+            .import(
+                &directory.join("statement.xml"),
+                &owner(vec![checking()]),
+                &mut report,
+            )
+            .unwrap();
+        (transactions, report)
+    }
+
+    #[test]
+    fn sniffs_namespace() {
+        assert_eq!(Camt053.sniff(&sample("94.30")), 1.0);
+        assert_eq!(Camt053.sniff("<OFX>"), 0.0);
+    }
+
+    #[test]
+    fn imports_statement() {
+        let (transactions, report) = import("camt053", &sample("94.30"));
+        assert_eq!(transactions.len(), 4);
+        assert_eq!(report.skipped().count(), 1);
+        assert_eq!(report.rejected().count(), 1);
+        assert_eq!(report.flagged().count(), 0);
+
+        let [transfer, card, incoming, debit] = &transactions[..] else {
+            unreachable!()
+        };
+        assert_eq!(transfer.direction(&checking()), Some(Direction::Outgoing));
+        assert_eq!(transfer.amount, dec!(12.50));
+        assert_eq!(transfer.sink.id(), shop().id());
+        // Only the remittance: everything else has a place of its own.
+        assert_eq!(transfer.description, "Invoice 1");
+        assert_eq!(transfer.reference.as_deref(), Some("20240102-0001"));
+        assert_eq!(transfer.end_to_end.as_deref(), Some("E2E-1"));
+        assert_eq!(transfer.mandate, None);
+        assert_eq!(transfer.sequence, None);
+
+        assert_eq!(card.sink, Node::Terminal(String::from("CT123456")));
+        assert_eq!(card.description, "Coffee Term: CT123456");
+        assert_eq!(card.reference.as_deref(), Some("0002"));
+
+        assert_eq!(incoming.direction(&checking()), Some(Direction::Incoming));
+        assert_eq!(incoming.source.id(), joint().id());
+        assert_eq!(incoming.description, "");
+        assert_eq!(incoming.reference, None);
+
+        assert_eq!(debit.description, "Monthly bill");
+        assert_eq!(debit.end_to_end, None);
+        assert_eq!(
+            debit.mandate,
+            Some(Mandate {
+                id: String::from("M-42"),
+                creditor: Some(String::from("NL00ZZZ000000000000")),
+            })
+        );
+    }
+
+    #[test]
+    fn flags_closing_balance_mismatch() {
+        let (transactions, report) = import("camt053-mismatch", &sample("200.00"));
+        assert_eq!(transactions.len(), 4);
+        assert!(matches!(
+            report.flagged().next(),
+            Some(ImportError::BalanceMismatch { balances, .. }) if balances.computed == dec!(94.30)
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_document() {
+        let (transactions, report) = import("camt053-malformed", "<Document><Stmt>");
+        assert!(transactions.is_empty());
+        assert!(matches!(
+            report.rejected().next(),
+            Some(ImportError::Xml { .. })
+        ));
+    }
+}
@@ -0,0 +1,503 @@
+//! SWIFT MT940 customer statements, the export format of older ING, Rabobank and
+//! ABN AMRO archives.
+use super::{
+    abn_amro::{self, CardPayment, CardPaymentKind},
+    sepa::{collapse_whitespace, SepaDescription},
+};
+use crate::{
+    canonical::{account::*, identify::*, state::Owner, transaction::*},
+    from_files::{
+        error::{Balances, ConversionError, ImportError, Location},
+        importer::{running_balances, BankImporter},
+        report::{ImportReport, Outcome},
+    },
+};
+use chrono::{Datelike, NaiveDate};
+use iban::Iban;
+use regex::Regex;
+use rust_decimal::Decimal;
+use std::{collections::BTreeMap, fs, path::Path, str::FromStr, sync::LazyLock};
+
+/// MT940 statement files. A file can hold several statements, each one is
+/// checked against its opening (`:60F:`) and closing (`:62F:`) balance.
+pub struct Mt940;
+impl BankImporter for Mt940 {
+    fn name(&self) -> &'static str {
+        "MT940"
+    }
+    fn sniff(&self, header: &str) -> f32 {
+        let tags = [":20:", ":25:", ":28C:", ":60F:", ":61:"];
+        let found = tags
+            .iter()
+            .filter(|tag| header.lines().any(|line| line.starts_with(*tag)))
+            .count();
+        found as f32 / tags.len() as f32
+    }
+    fn import(
+        &self,
+        path: &Path,
+        profile: &Owner,
+        report: &mut ImportReport,
+    ) -> Result<Vec<Transaction>, ImportError> {
+        let bytes = fs::read(path).map_err(|source| ImportError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        // Archives are often Latin-1, don't reject a statement over an odd character.
+        let text = String::from_utf8_lossy(&bytes);
+        let mut transactions = Vec::new();
+        for statement in statements(&fields(&text)) {
+            transactions.append(&mut statement.read(path, profile, report));
+        }
+        Ok(transactions)
+    }
+}
+
+/// A `:tag:value` field, with its continuation lines.
+#[derive(Debug)]
+struct Field {
+    tag: String,
+    /// The lines of the value, without line endings.
+    lines: Vec<String>,
+    /// 1-based line on which the field starts.
+    line: u64,
+}
+
+/// The fields of every statement in `text`. A line of just `-` (or `-}`, in a SWIFT
+/// message) ends a statement; continuation lines may start with a `-` themselves.
+fn fields(text: &str) -> Vec<Field> {
+    static TAG: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"^:(?<tag>\d{2}[A-Z]?):(?<value>.*)$").unwrap());
+    let mut fields: Vec<Field> = Vec::new();
+    let mut in_field = false;
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if let Some(captures) = TAG.captures(line) {
+            fields.push(Field {
+                tag: captures["tag"].to_string(),
+                lines: vec![captures["value"].to_string()],
+                line: number as u64 + 1,
+            });
+            in_field = true;
+        } else if matches!(line.trim_end(), "-" | "-}") || line.starts_with('{') {
+            // End of a statement, or a SWIFT block header.
+            in_field = false;
+        } else if in_field {
+            if let Some(field) = fields.last_mut() {
+                field.lines.push(line.to_string());
+            }
+        }
+    }
+    fields
+}
+
+#[derive(Default)]
+struct Statement<'a> {
+    account: Option<&'a Field>,
+    opening: Option<&'a Field>,
+    closing: Option<&'a Field>,
+    /// `:61:` fields, with the `:86:` field that follows them.
+    entries: Vec<(&'a Field, Option<&'a Field>)>,
+    /// Line of the `:20:` field.
+    line: u64,
+}
+
+fn statements(fields: &[Field]) -> Vec<Statement<'_>> {
+    let mut statements: Vec<Statement> = Vec::new();
+    for field in fields {
+        if field.tag == "20" || statements.is_empty() {
+            statements.push(Statement {
+                line: field.line,
+                ..Default::default()
+            });
+        }
+        let statement = statements.last_mut().unwrap();
+        match field.tag.as_str() {
+            "25" => statement.account = Some(field),
+            "60F" | "60M" => statement.opening = Some(field),
+            "62F" | "62M" => statement.closing = Some(field),
+            "61" => statement.entries.push((field, None)),
+            "86" => {
+                if let Some((_, narrative @ None)) = statement.entries.last_mut() {
+                    *narrative = Some(field);
+                }
+            }
+            _ => {}
+        }
+    }
+    statements
+}
+
+impl<'a> Statement<'a> {
+    fn location(path: &Path, line: u64) -> Location {
+        let mut location = Location::in_file(path.to_path_buf());
+        location.line = Some(line);
+        location
+    }
+
+    fn read(&self, path: &Path, profile: &Owner, report: &mut ImportReport) -> Vec<Transaction> {
+        let mut transactions = Vec::new();
+        let Some(account) = self.account.and_then(|field| account_iban(&field.lines[0])) else {
+            let location = Self::location(path, self.line);
+            let err = ImportError::Invalid {
+                location: location.clone(),
+                message: String::from("statement without a valid :25: account"),
+            };
+            report.record(location, Outcome::Rejected(err));
+            return transactions;
+        };
+        let name = profile.name_of(&Node::transfer_from(Account::iban_id(&account)));
+        let owner_account = Node::ProperAccount(Account {
+            iban: account,
+            name: name.unwrap_or("UNNAMED_ACCOUNT".to_string()),
+        });
+
+        let mut booked = Decimal::ZERO;
+        for (entry, narrative) in &self.entries {
+            let mut location = Self::location(path, entry.line);
+            location.record = Some(
+                [Some(*entry), *narrative]
+                    .iter()
+                    .flatten()
+                    .map(|field| format!(":{}:{}", field.tag, field.lines.join("\n")))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            );
+            match read_entry(entry, *narrative, &owner_account) {
+                Ok(transaction) => {
+                    booked += if transaction.sink == owner_account {
+                        transaction.amount
+                    } else {
+                        -transaction.amount
+                    };
+                    transactions.push(transaction);
+                    report.record(location, Outcome::Accepted);
+                }
+                Err(err) => {
+                    let err = match err {
+                        EntryError::Invalid(message) => ImportError::Invalid {
+                            location: location.clone(),
+                            message,
+                        },
+                        EntryError::Conversion(source) => ImportError::Conversion {
+                            location: location.clone(),
+                            source,
+                        },
+                    };
+                    report.record(location, Outcome::Rejected(err));
+                }
+            }
+        }
+
+        let opening = self.opening.and_then(|field| balance(&field.lines[0]));
+        let closing = self.closing.and_then(|field| balance(&field.lines[0]));
+        match (opening, closing) {
+            (Some(opening), Some(closing)) if opening + booked != closing => {
+                let location = Self::location(path, self.line);
+                let err = ImportError::BalanceMismatch {
+                    location: location.clone(),
+                    balances: Box::new(Balances {
+                        account: account.to_string(),
+                        opening,
+                        closing,
+                        computed: opening + booked,
+                    }),
+                };
+                report.record(location, Outcome::Flagged(err));
+            }
+            // Only the balances around the statement are known, derive the ones in between.
+            (Some(opening), _) => running_balances(&mut transactions, &owner_account, opening),
+            _ => {}
+        }
+        transactions
+    }
+}
+
+/// `:25:` holds the IBAN, optionally followed by the currency ("NL91ABNA0417164300EUR"),
+/// or a bare ABN AMRO account number.
+fn account_iban(value: &str) -> Option<Iban> {
+    let value = value.trim();
+    static CURRENCY: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\s*[A-Z]{3}$").unwrap());
+    let without_currency = CURRENCY.replace(value, "");
+    [value, without_currency.as_ref()]
+        .iter()
+        .find_map(|candidate| abn_amro::account_iban(&candidate.replace(' ', "")))
+}
+
+/// `:60F:`/`:62F:`: C|D, YYMMDD, currency, amount ("C240101EUR1000,00").
+fn balance(value: &str) -> Option<Decimal> {
+    static BALANCE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"^(?<mark>[CD])\d{6}[A-Z]{3}(?<amount>[\d,]+)").unwrap());
+    let captures = BALANCE.captures(value.trim())?;
+    let amount = Decimal::from_str(&captures["amount"].replace(',', ".")).ok()?;
+    Some(if &captures["mark"] == "D" {
+        -amount
+    } else {
+        amount
+    })
+}
+
+enum EntryError {
+    Invalid(String),
+    Conversion(ConversionError),
+}
+
+fn read_entry(
+    entry: &Field,
+    narrative: Option<&Field>,
+    owner_account: &Node,
+) -> Result<Transaction, EntryError> {
+    static STATEMENT_LINE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(
+            r"^(?<value_date>\d{6})(?<entry_date>\d{4})?(?<mark>RC|RD|C|D)[A-Z]?(?<amount>\d+,\d*)(?<type>[NFS][A-Z0-9]{3}).*?(?://(?<reference>\S+))?$",
+        )
+        .unwrap()
+    });
+    let captures = STATEMENT_LINE
+        .captures(entry.lines[0].trim())
+        .ok_or_else(|| EntryError::Invalid(String::from("malformed :61: statement line")))?;
+    let value_date = NaiveDate::parse_from_str(&captures["value_date"], "%y%m%d")
+        .map_err(|_| EntryError::Invalid(String::from("invalid :61: value date")))?;
+    let date = match captures.name("entry_date") {
+        Some(entry_date) => booking_date(value_date, entry_date.as_str())
+            .ok_or_else(|| EntryError::Invalid(String::from("invalid :61: entry date")))?,
+        None => value_date,
+    };
+    let amount = Decimal::from_str(&captures["amount"].replace(',', "."))
+        .map_err(|_| EntryError::Invalid(String::from("invalid :61: amount")))?;
+    // RC (reversal of credit) is a debit, RD (reversal of debit) a credit.
+    let incoming = matches!(&captures["mark"], "C" | "RD");
+
+    // Structured narratives are wrapped at a fixed width, mid-word. Free text is wrapped between words.
+    let narrative = narrative.map_or(String::new(), |field| {
+        if field.lines[0].starts_with('/') {
+            field.lines.concat()
+        } else {
+            field.lines.join(" ")
+        }
+    });
+    let counter_party = determine_node_type(entry, &captures["type"], &narrative)
+        .map_err(EntryError::Conversion)?;
+    let (source, sink) = if incoming {
+        (counter_party, owner_account.clone())
+    } else {
+        (owner_account.clone(), counter_party)
+    };
+    let sepa = SepaDescription::parse(&narrative);
+    Ok(Transaction {
+        date,
+        source,
+        sink,
+        amount,
+        inherent_tags: String::new(),
+        // The whole narrative, its structured fields included.
+        description: collapse_whitespace(&narrative),
+        sequence: None,
+        // The bank's reference follows the customer's after "//".
+        reference: captures
+            .name("reference")
+            .map(|reference| reference.as_str().to_string()),
+        end_to_end: sepa.end_to_end.filter(|id| id != "NOTPROVIDED"),
+        mandate: sepa.mandate.map(|id| Mandate {
+            id,
+            creditor: sepa.creditor,
+        }),
+        balance: None,
+        code: Some(captures["type"].to_string()),
+        occurrence: 0,
+        rule_tags: BTreeMap::new(),
+        category: None,
+    })
+}
+
+/// Combine the MMDD entry date with the year of the value date. Entries booked
+/// around new year can have a value date in the other year.
+fn booking_date(value_date: NaiveDate, entry_date: &str) -> Option<NaiveDate> {
+    let month: u32 = entry_date.get(..2)?.parse().ok()?;
+    let day: u32 = entry_date.get(2..)?.parse().ok()?;
+    let year = match (value_date.month(), month) {
+        (12, 1) => value_date.year() + 1,
+        (1, 12) => value_date.year() - 1,
+        _ => value_date.year(),
+    };
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+/// Like `ing::determine_node_type`, from the `:86:` narrative. Rabobank puts the
+/// counterparty account on the second line of the `:61:` field instead.
+fn determine_node_type(
+    entry: &Field,
+    transaction_type: &str,
+    narrative: &str,
+) -> Result<Node, ConversionError> {
+    if let Some(card_payment) = CardPayment::parse(narrative) {
+        let id = card_payment
+            .terminal
+            .or(card_payment.name)
+            .unwrap_or("UNKNOWN_TERM_ID".into());
+        return Ok(match card_payment.kind {
+            CardPaymentKind::Terminal => Node::Terminal(id),
+            CardPaymentKind::Atm => Node::Atm(id),
+        });
+    }
+
+    let sepa = SepaDescription::parse(narrative);
+    let supplementary = entry.lines.get(1).map(|line| line.trim());
+    if let Some(iban) = sepa
+        .iban
+        .as_deref()
+        .or(supplementary)
+        .and_then(|iban| Iban::parse(iban).ok())
+    {
+        return Ok(Node::ProperAccount(Account {
+            iban,
+            name: sepa.name.unwrap_or_default(),
+        }));
+    }
+
+    static TERMINAL: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"Term: (?<terminalID>\w+)").unwrap());
+    if let Some(captures) = TERMINAL.captures(narrative) {
+        return Ok(Node::Terminal(captures["terminalID"].into()));
+    }
+
+    if matches!(transaction_type, "NCHG" | "NKST" | "NINT") {
+        return Ok(Node::Other("Bank charges".into()));
+    }
+
+    Err(ConversionError::UnknownCounterparty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canonical::fixtures::*;
+    use rust_decimal_macros::dec;
+
+    fn sample(closing: &str) -> String {
+        format!(
+            "\
+:20:STMT1
+:25:NL91ABNA0417164300EUR
+:28C:1
+:60F:C240101EUR100,00
+:61:2401020102D12,50NTRFNONREF//B4A02XYZ
+:86:/TRTP/SEPA OVERBOEKING/IBAN/NL44RABO0123456789/BIC/RABONL2U/NAME/Sh
+op/REMI/Invoice 1/EREF/E2E-1
+:61:2401030103D3,20NMSCNONREF
+:86:BEA, Apple Pay  Coffee Corner,PAS123  NR:CT123456, 03.01.24/10:15
+AMSTERDAM
+:61:2401040104C50,00NTRFNONREF
+NL02ABNA0123456789
+:86:Pocket money
+- gift from the joint account
+:61:2401050105D20,00NDDTNONREF
+:86:/TRTP/SEPA Incasso algemeen doorlopend/CSID/NL00ZZZ000000000000/NAME/En
+ergy Co/MARF/M-42/REMI/Monthly bill/IBAN/NL44RABO0123456789/EREF/NOTPROVID
+ED
+:61:24013X0104C1,00NTRFNONREF
+:86:Not a date
+:62F:C240105EUR{closing}
+-
+"
+        )
+    }
+
+    fn import(name: &str, contents: &str) -> (Vec<Transaction>, ImportReport) {
+        let directory = directory(name, &[("statement.sta", contents)]);
+        let mut report = ImportReport::default();
+        let transactions = Mt940
+            .import(
+                &directory.join("statement.sta"),
+                &owner(vec![checking()]),
+                &mut report,
+            )
+            .unwrap();
+        (transactions, report)
+    }
+
+    #[test]
+    fn sniffs_tags() {
+        assert_eq!(Mt940.sniff(&sample("114,30")), 1.0);
+        assert_eq!(Mt940.sniff("OFXHEADER:100"), 0.0);
+    }
+
+    #[test]
+    fn imports_statement() {
+        let (transactions, report) = import("mt940", &sample("114,30"));
+        assert_eq!(transactions.len(), 4);
+        assert_eq!(report.rejected().count(), 1);
+        assert_eq!(report.flagged().count(), 0);
+
+        let [transfer, card, incoming, debit] = &transactions[..] else {
+            unreachable!()
+        };
+        assert_eq!(transfer.direction(&checking()), Some(Direction::Outgoing));
+        assert_eq!(transfer.amount, dec!(12.50));
+        assert_eq!(transfer.date, date(2024, 1, 2));
+        assert_eq!(transfer.sink.id(), shop().id());
+        assert_eq!(
+            transfer.description,
+            "/TRTP/SEPA OVERBOEKING/IBAN/NL44RABO0123456789/BIC/RABONL2U/NAME/Shop/REMI/Invoice 1/EREF/E2E-1"
+        );
+        assert_eq!(transfer.reference.as_deref(), Some("B4A02XYZ"));
+        assert_eq!(transfer.end_to_end.as_deref(), Some("E2E-1"));
+        assert_eq!(transfer.sequence, None);
+
+        assert_eq!(card.sink, Node::Terminal(String::from("CT123456")));
+        assert_eq!(card.reference, None);
+
+        // Rabobank: the counterparty account on the second line of :61:.
+        assert_eq!(incoming.direction(&checking()), Some(Direction::Incoming));
+        assert_eq!(incoming.source.id(), joint().id());
+        assert_eq!(
+            incoming.description,
+            "Pocket money - gift from the joint account"
+        );
+
+        assert_eq!(debit.end_to_end, None);
+        assert_eq!(
+            debit.mandate,
+            Some(Mandate {
+                id: String::from("M-42"),
+                creditor: Some(String::from("NL00ZZZ000000000000")),
+            })
+        );
+    }
+
+    #[test]
+    fn flags_closing_balance_mismatch() {
+        let (_, report) = import("mt940-mismatch", &sample("200,00"));
+        assert!(matches!(
+            report.flagged().next(),
+            Some(ImportError::BalanceMismatch { balances, .. }) if balances.computed == dec!(114.30)
+        ));
+    }
+
+    #[test]
+    fn only_a_dash_line_ends_a_statement() {
+        let fields = fields(":20:STMT1\n:86:Refund\n-1 item returned\n-}\n:20:STMT2\n-\nTrailer\n");
+        let values: Vec<_> = fields
+            .iter()
+            .map(|field| (field.tag.as_str(), field.lines.join("|")))
+            .collect();
+        assert_eq!(
+            values,
+            [
+                ("20", String::from("STMT1")),
+                ("86", String::from("Refund|-1 item returned")),
+                ("20", String::from("STMT2")),
+            ]
+        );
+    }
+
+    #[test]
+    fn booking_date_across_new_year() {
+        let value_date = date(2023, 12, 31);
+        assert_eq!(booking_date(value_date, "0102"), Some(date(2024, 1, 2)));
+        assert_eq!(
+            booking_date(date(2024, 1, 1), "1231"),
+            Some(date(2023, 12, 31))
+        );
+    }
+}
@@ -0,0 +1,434 @@
+//! Open Financial Exchange statements: OFX 1.x (SGML) and 2.x (XML), and Quicken's
+//! QFX, which is OFX with a few extra elements.
+use crate::{
+    canonical::{account::*, identify::*, state::Owner, transaction::*},
+    from_files::{
+        error::{ConversionError, ImportError, Location},
+        importer::BankImporter,
+        report::{ImportReport, Outcome},
+    },
+};
+use chrono::NaiveDate;
+use iban::Iban;
+use rust_decimal::Decimal;
+use std::{collections::BTreeMap, fs, path::Path, str::FromStr};
+
+/// Bank (`<STMTRS>`) and credit card (`<CCSTMTRS>`) statements in OFX files.
+/// `<FITID>` becomes the `Transaction::reference`.
+pub struct Ofx;
+impl BankImporter for Ofx {
+    fn name(&self) -> &'static str {
+        "OFX"
+    }
+    fn sniff(&self, header: &str) -> f32 {
+        if header.contains("OFXHEADER") {
+            1.0
+        } else if header.contains("<OFX>") {
+            0.9
+        } else {
+            0.0
+        }
+    }
+    fn import(
+        &self,
+        path: &Path,
+        profile: &Owner,
+        report: &mut ImportReport,
+    ) -> Result<Vec<Transaction>, ImportError> {
+        let bytes = fs::read(path).map_err(|source| ImportError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        // OFX 1.x is usually in a Windows code page, don't reject a file over an odd character.
+        let document = Element::parse(&String::from_utf8_lossy(&bytes));
+        let mut statements = Vec::new();
+        document.descendants(&["STMTRS", "CCSTMTRS"], &mut statements);
+
+        let mut transactions = Vec::new();
+        for statement in statements {
+            transactions.append(&mut read_statement(statement, path, profile, report));
+        }
+        Ok(transactions)
+    }
+}
+
+fn location(path: &Path, element: &Element) -> Location {
+    let mut location = Location::in_file(path.to_path_buf());
+    location.line = Some(element.line);
+    location
+}
+
+fn read_statement(
+    statement: &Element,
+    path: &Path,
+    profile: &Owner,
+    report: &mut ImportReport,
+) -> Vec<Transaction> {
+    let mut transactions = Vec::new();
+    let account = statement
+        .text(&["BANKACCTFROM", "ACCTID"])
+        .or_else(|| statement.text(&["CCACCTFROM", "ACCTID"]));
+    let Some(owner_account) = account.map(|account| owned_node(account, profile)) else {
+        let location = location(path, statement);
+        let err = ImportError::Invalid {
+            location: location.clone(),
+            message: String::from("statement without an <ACCTID>"),
+        };
+        report.record(location, Outcome::Rejected(err));
+        return transactions;
+    };
+
+    let entries = statement
+        .child("BANKTRANLIST")
+        .map_or(&[][..], |list| &list.children);
+    for entry in entries.iter().filter(|entry| entry.name == "STMTTRN") {
+        let location = location(path, entry);
+        match read_entry(entry, &owner_account) {
+            Ok(transaction) => {
+                transactions.push(transaction);
+                report.record(location, Outcome::Accepted);
+            }
+            Err(EntryError::Invalid(message)) => {
+                let err = ImportError::Invalid {
+                    location: location.clone(),
+                    message,
+                };
+                report.record(location, Outcome::Rejected(err));
+            }
+            Err(EntryError::Conversion(source)) => {
+                let err = ImportError::Conversion {
+                    location: location.clone(),
+                    source,
+                };
+                report.record(location, Outcome::Rejected(err));
+            }
+        }
+    }
+    transactions
+}
+
+/// The node of the statement's account. Credit cards and foreign accounts
+/// often don't have an IBAN: those are `Node::Other(account)`, unless the profile
+/// owns a node with the same ID (e.g.: a `SubAccount` with `account` as its bsan).
+fn owned_node(account: &str, profile: &Owner) -> Node {
+    if let Ok(iban) = Iban::parse(account) {
+        let name = profile.name_of(&Node::transfer_from(Account::iban_id(&iban)));
+        return Node::ProperAccount(Account {
+            iban,
+            name: name.unwrap_or("UNNAMED_ACCOUNT".to_string()),
+        });
+    }
+    let node = Node::Other(account.to_string());
+    profile.view(&node.id()).cloned().unwrap_or(node)
+}
+
+enum EntryError {
+    Invalid(String),
+    Conversion(ConversionError),
+}
+
+fn read_entry(entry: &Element, owner_account: &Node) -> Result<Transaction, EntryError> {
+    let amount = entry
+        .text(&["TRNAMT"])
+        .and_then(|amount| Decimal::from_str(&amount.replace(',', ".")).ok())
+        .ok_or_else(|| EntryError::Invalid(String::from("entry without a valid <TRNAMT>")))?;
+    // YYYYMMDD, optionally followed by a time and a time zone.
+    let date = entry
+        .text(&["DTPOSTED"])
+        .and_then(|date| NaiveDate::parse_from_str(date.get(..8)?, "%Y%m%d").ok())
+        .ok_or_else(|| EntryError::Invalid(String::from("entry without a valid <DTPOSTED>")))?;
+    let reference = entry
+        .text(&["FITID"])
+        .ok_or_else(|| EntryError::Invalid(String::from("entry without a <FITID>")))?;
+
+    let counter_party = determine_node_type(entry).map_err(EntryError::Conversion)?;
+    let (source, sink) = if amount.is_sign_negative() {
+        (owner_account.clone(), counter_party)
+    } else {
+        (counter_party, owner_account.clone())
+    };
+    let description = entry
+        .text(&["MEMO"])
+        .or_else(|| name(entry))
+        .unwrap_or_default();
+    Ok(Transaction {
+        date,
+        source,
+        sink,
+        amount: amount.abs(),
+        inherent_tags: String::new(),
+        description: description.to_string(),
+        sequence: None,
+        reference: Some(reference.to_string()),
+        end_to_end: None,
+        mandate: None,
+        balance: None,
+        code: entry.text(&["TRNTYPE"]).map(String::from),
+        occurrence: 0,
+        rule_tags: BTreeMap::new(),
+        category: None,
+    })
+}
+
+fn name(entry: &Element) -> Option<&str> {
+    entry
+        .text(&["NAME"])
+        .or_else(|| entry.text(&["PAYEE", "NAME"]))
+}
+
+/// Like `ing::determine_node_type`, from the transaction type and the
+/// counterparty's account of the entry.
+fn determine_node_type(entry: &Element) -> Result<Node, ConversionError> {
+    let name = name(entry);
+    let account = entry
+        .text(&["BANKACCTTO", "ACCTID"])
+        .or_else(|| entry.text(&["CCACCTTO", "ACCTID"]));
+    if let Some(iban) = account.and_then(|account| Iban::parse(account).ok()) {
+        return Ok(Node::ProperAccount(Account {
+            iban,
+            name: name.unwrap_or_default().to_string(),
+        }));
+    }
+
+    match (entry.text(&["TRNTYPE"]), name) {
+        (Some("ATM"), Some(name)) => Ok(Node::Atm(name.into())),
+        (Some("POS"), Some(name)) => Ok(Node::Terminal(name.into())),
+        (Some("FEE" | "SRVCHG"), _) => Ok(Node::Other("Bank charges".into())),
+        (Some("INT" | "DIV"), None) => Ok(Node::Other("Interest".into())),
+        (_, Some(name)) => Ok(Node::Other(name.into())),
+        (_, None) => Err(ConversionError::UnknownCounterparty),
+    }
+}
+
+/// An OFX element. Leaf elements have `text`, aggregates have `children`.
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    text: Option<String>,
+    children: Vec<Element>,
+    /// 1-based line on which the element starts.
+    line: u64,
+}
+impl Element {
+    /// Read both SGML, in which leaf elements aren't closed (`<TRNAMT>-12.50`),
+    /// and XML. The headers in front of `<OFX>` are ignored, and so is anything
+    /// that doesn't nest properly.
+    fn parse(text: &str) -> Self {
+        let mut stack = vec![Element::default()];
+        let mut line = 1;
+        let mut rest = text;
+        while let Some(start) = rest.find('<') {
+            let content = &rest[..start];
+            line += content.matches('\n').count() as u64;
+            let content = content.trim();
+            if !content.is_empty() && stack.len() > 1 {
+                stack.last_mut().unwrap().text = Some(unescape(content));
+            }
+            let Some(end) = rest[start..].find('>') else {
+                break;
+            };
+            let tag = &rest[start + 1..start + end];
+            rest = &rest[start + end + 1..];
+
+            if tag.starts_with('?') || tag.starts_with('!') {
+                line += tag.matches('\n').count() as u64;
+                continue;
+            }
+            let is_leaf = |element: &Element| element.text.is_some() && element.children.is_empty();
+            if let Some(name) = tag.strip_prefix('/') {
+                // Names are upper-cased when opened, so closing tags must be too.
+                let name = name.trim().to_uppercase();
+                if stack.len() > 1
+                    && stack
+                        .last()
+                        .is_some_and(|top| top.name != name && is_leaf(top))
+                {
+                    close(&mut stack);
+                }
+                if let Some(open) = stack
+                    .iter()
+                    .skip(1)
+                    .rposition(|element| element.name == name)
+                {
+                    while stack.len() > open + 1 {
+                        close(&mut stack);
+                    }
+                }
+            } else {
+                if stack.len() > 1 && stack.last().is_some_and(is_leaf) {
+                    close(&mut stack);
+                }
+                let self_closing = tag.ends_with('/');
+                let name = tag
+                    .trim_end_matches('/')
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or_default();
+                stack.push(Element {
+                    name: name.to_uppercase(),
+                    line,
+                    ..Default::default()
+                });
+                if self_closing {
+                    close(&mut stack);
+                }
+            }
+            line += tag.matches('\n').count() as u64;
+        }
+        while stack.len() > 1 {
+            close(&mut stack);
+        }
+        stack.pop().unwrap()
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    /// Text of the element at `path` below self, if it isn't empty.
+    fn text(&self, path: &[&str]) -> Option<&str> {
+        path.iter()
+            .try_fold(self, |element, name| element.child(name))?
+            .text
+            .as_deref()
+            .filter(|text| !text.is_empty())
+    }
+
+    /// Every element below self with one of `names`, in document order.
+    fn descendants<'a>(&'a self, names: &[&str], found: &mut Vec<&'a Element>) {
+        for child in &self.children {
+            if names.contains(&child.name.as_str()) {
+                found.push(child);
+            } else {
+                child.descendants(names, found);
+            }
+        }
+    }
+}
+
+/// Move the innermost open element into its parent.
+fn close(stack: &mut Vec<Element>) {
+    let element = stack.pop().unwrap();
+    stack.last_mut().unwrap().children.push(element);
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canonical::fixtures::*;
+    use rust_decimal_macros::dec;
+
+    const SGML: &str = "\
+OFXHEADER:100
+DATA:OFXSGML
+VERSION:102
+
+<OFX>
+<BANKMSGSRSV1><STMTTRNRS><STMTRS>
+<CURDEF>EUR
+<BANKACCTFROM><BANKID>ABNA<ACCTID>NL91ABNA0417164300<ACCTTYPE>CHECKING</BANKACCTFROM>
+<BANKTRANLIST>
+<DTSTART>20240101<DTEND>20240131
+<STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20240102120000[+1:CET]<TRNAMT>-12,50<FITID>F1<NAME>Shop
+<BANKACCTTO><ACCTID>NL44RABO0123456789</BANKACCTTO><MEMO>Invoice 1 &amp; 2</STMTTRN>
+<STMTTRN><TRNTYPE>POS<DTPOSTED>20240103<TRNAMT>-3.20<FITID>F2<NAME>Coffee Corner</STMTTRN>
+<STMTTRN><TRNTYPE>CREDIT<DTPOSTED>20240104<TRNAMT>50.00<FITID>F3<NAME>Joint
+<BANKACCTTO><ACCTID>NL02ABNA0123456789</BANKACCTTO></STMTTRN>
+<STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20240105<TRNAMT>-1.00<NAME>No FITID</STMTTRN>
+</BANKTRANLIST>
+<LEDGERBAL><BALAMT>134.30<DTASOF>20240131</LEDGERBAL>
+</STMTRS></STMTTRNRS></BANKMSGSRSV1>
+</OFX>
+";
+
+    const XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<?OFX OFXHEADER="200" VERSION="220"?>
+<OFX><CREDITCARDMSGSRSV1><CCSTMTTRNRS><CCSTMTRS>
+<CCACCTFROM><ACCTID>4111-XXXX</ACCTID></CCACCTFROM>
+<BANKTRANLIST>
+<STMTTRN><TRNTYPE>FEE</TRNTYPE><DTPOSTED>20240110</DTPOSTED><TRNAMT>-2.50</TRNAMT><FITID>C1</FITID></STMTTRN>
+</BANKTRANLIST>
+</CCSTMTRS></CCSTMTTRNRS></CREDITCARDMSGSRSV1></OFX>
+"#;
+
+    fn import(name: &str, contents: &str) -> (Vec<Transaction>, ImportReport) {
+        let directory = directory(name, &[("statement.ofx", contents)]);
+        let mut report = ImportReport::default();
+        let transactions = Ofx
+            .import(
+                &directory.join("statement.ofx"),
+                &owner(vec![checking()]),
+                &mut report,
+            )
+            .unwrap();
+        (transactions, report)
+    }
+
+    #[test]
+    fn sniffs_header() {
+        assert_eq!(Ofx.sniff(SGML), 1.0);
+        assert_eq!(Ofx.sniff("<OFX><SIGNONMSGSRSV1>"), 0.9);
+        assert_eq!(Ofx.sniff(":20:STMT1"), 0.0);
+    }
+
+    #[test]
+    fn imports_sgml_statement() {
+        let (transactions, report) = import("ofx-sgml", SGML);
+        assert_eq!(transactions.len(), 3);
+        assert_eq!(report.rejected().count(), 1);
+
+        let [transfer, card, incoming] = &transactions[..] else {
+            unreachable!()
+        };
+        assert_eq!(transfer.direction(&checking()), Some(Direction::Outgoing));
+        assert_eq!(transfer.amount, dec!(12.50));
+        assert_eq!(transfer.date, date(2024, 1, 2));
+        assert_eq!(transfer.sink.id(), shop().id());
+        assert_eq!(transfer.description, "Invoice 1 & 2");
+        assert_eq!(transfer.reference.as_deref(), Some("F1"));
+        assert_eq!(transfer.sequence, None);
+
+        assert_eq!(card.sink, Node::Terminal(String::from("Coffee Corner")));
+
+        assert_eq!(incoming.direction(&checking()), Some(Direction::Incoming));
+        assert_eq!(incoming.amount, dec!(50));
+        assert_eq!(incoming.source.id(), joint().id());
+    }
+
+    #[test]
+    fn imports_xml_credit_card_statement() {
+        let (transactions, report) = import("ofx-xml", XML);
+        assert_eq!(report.rejected().count(), 0);
+        let [fee] = &transactions[..] else {
+            panic!("expecting one transaction, got {:?}", transactions)
+        };
+        assert_eq!(fee.source, Node::Other(String::from("4111-XXXX")));
+        assert_eq!(fee.sink, Node::Other(String::from("Bank charges")));
+        assert_eq!(fee.amount, dec!(2.50));
+        assert_eq!(fee.reference.as_deref(), Some("C1"));
+    }
+
+    #[test]
+    fn closes_lowercase_tags() {
+        let ofx = Element::parse(
+            "<ofx><banktranlist><stmttrn><trnamt>1</trnamt></stmttrn>\
+             <stmttrn><trnamt>2</trnamt></Stmttrn></banktranlist></ofx>",
+        );
+        let list = ofx.child("OFX").unwrap().child("BANKTRANLIST").unwrap();
+        let amounts: Vec<_> = list
+            .children
+            .iter()
+            .map(|transaction| transaction.text(&["TRNAMT"]))
+            .collect();
+        assert_eq!(amounts, [Some("1"), Some("2")]);
+    }
+}
@@ -0,0 +1,286 @@
+use super::{serde_amount, serde_iban};
+use crate::{
+    canonical::{account::*, identify::*, state::Owner, transaction::*},
+    from_files::{
+        error::{ConversionError, ImportError},
+        importer::{column_match, read_csv, BankImporter, CsvRecord},
+        report::ImportReport,
+    },
+};
+use chrono::NaiveDate;
+use iban::Iban;
+use rust_decimal::Decimal;
+use serde::{self, Deserialize, Deserializer};
+use std::{collections::BTreeMap, path::Path};
+
+/// Transaction codes, see https://www.rabobank.nl/particulieren/betalen/service/transactiecodes
+#[derive(Debug, PartialEq, Deserialize, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Code {
+    /// Acceptgiro
+    Ac,
+    /// Betaalautomaat
+    Ba,
+    /// Betalen contactloos
+    Bc,
+    /// Bankgiro opdracht
+    Bg,
+    /// Crediteurenbetaling
+    Cb,
+    /// Chipknip
+    Ck,
+    /// Diverse boekingen
+    Db,
+    /// Bankieren via internet
+    Eb,
+    /// Euro-incasso
+    Ei,
+    /// Geldautomaat Euro
+    Ga,
+    /// Geldautomaat vreemde valuta
+    Gb,
+    /// iDEAL
+    Id,
+    /// Kashandeling
+    Kh,
+    /// Machtiging
+    Ma,
+    /// Salarisbetaling
+    Sb,
+    /// Eigen rekening
+    Tb,
+    /// Any code not listed above.
+    #[serde(other)]
+    Other,
+}
+
+crate::date_deserializer_from_format!("%Y-%m-%d");
+
+/// Volgnr is zero-padded to 18 digits: "000000000000007213".
+fn deserialize_sequence<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: String = String::deserialize(deserializer)?;
+    s.trim().parse().map_err(serde::de::Error::custom)
+}
+
+/// Raw record of a Rabobank current account export ("CSV (comma separated)").
+/// Only the columns that are needed are listed, the rest is ignored.
+#[derive(Debug, Deserialize, PartialEq, Eq, Hash)]
+pub struct RabobankCurrentAccount {
+    /// Account of the owner.
+    #[serde(rename = "IBAN/BBAN", with = "serde_iban")]
+    pub account: Iban,
+
+    /// Sequence number of the transaction within the account, increasing.
+    #[serde(rename = "Volgnr", deserialize_with = "deserialize_sequence")]
+    pub sequence: u64,
+
+    /// YYYY-MM-DD
+    #[serde(rename = "Datum", with = "local_date_deserializer")]
+    pub date: NaiveDate,
+
+    /// "+0,00" | "-0,00" - Sign is the direction with respect to `self.account`.
+    #[serde(rename = "Bedrag", with = "serde_amount")]
+    pub amount: Decimal,
+
+    /// "+0,00" - Balance of the account after this transaction.
+    #[serde(rename = "Saldo na trn", with = "serde_amount")]
+    pub balance: Decimal,
+
+    /// IBAN (or an old BBAN). Empty for card payments and cash withdrawals.
+    #[serde(rename = "Tegenrekening IBAN/BBAN")]
+    pub counter_party: Option<String>,
+
+    #[serde(rename = "Naam tegenpartij")]
+    pub name: String,
+
+    #[serde(rename = "Code")]
+    pub code: Code,
+
+    #[serde(rename = "Omschrijving-1")]
+    pub description_1: String,
+    #[serde(rename = "Omschrijving-2")]
+    pub description_2: String,
+    #[serde(rename = "Omschrijving-3")]
+    pub description_3: String,
+}
+
+/// Current account exports from Rabobank.
+pub struct Rabobank;
+impl BankImporter for Rabobank {
+    fn name(&self) -> &'static str {
+        "Rabobank"
+    }
+    fn sniff(&self, header: &str) -> f32 {
+        let columns = [
+            "IBAN/BBAN",
+            "Munt",
+            "Volgnr",
+            "Datum",
+            "Bedrag",
+            "Saldo na trn",
+            "Tegenrekening IBAN/BBAN",
+            "Naam tegenpartij",
+            "Code",
+            "Omschrijving-1",
+        ];
+        column_match(header, ',', &columns)
+    }
+    fn import(
+        &self,
+        path: &Path,
+        profile: &Owner,
+        report: &mut ImportReport,
+    ) -> Result<Vec<Transaction>, ImportError> {
+        read_csv::<RabobankCurrentAccount>(path, b',', true, profile, report)
+    }
+}
+
+impl CsvRecord for RabobankCurrentAccount {
+    fn into_transaction(self, profile: &Owner) -> Result<Transaction, ConversionError> {
+        owned_rabobank_transaction(self, profile)
+    }
+}
+
+pub fn owned_rabobank_transaction(
+    rabo_transaction: RabobankCurrentAccount,
+    owner: &Owner,
+) -> Result<Transaction, ConversionError> {
+    let id = owner.name_of(&Node::transfer_from(Account::iban_id(
+        &rabo_transaction.account,
+    )));
+    let owner_account = Node::ProperAccount(Account {
+        iban: rabo_transaction.account,
+        name: id.unwrap_or("UNNAMED_ACCOUNT".to_string()),
+    });
+    let sequence = Sequence {
+        of: owner_account.id(),
+        number: rabo_transaction.sequence,
+    };
+    let balance = Balance {
+        of: owner_account.id(),
+        amount: rabo_transaction.balance,
+    };
+    let counter_party = determine_node_type(&rabo_transaction)?;
+    let (source, sink) = if rabo_transaction.amount.is_sign_negative() {
+        (owner_account, counter_party)
+    } else {
+        (counter_party, owner_account)
+    };
+    let description = [
+        &rabo_transaction.description_1,
+        &rabo_transaction.description_2,
+        &rabo_transaction.description_3,
+    ]
+    .iter()
+    .map(|part| part.trim())
+    .filter(|part| !part.is_empty())
+    .collect::<Vec<_>>()
+    .join(" ");
+    Ok(Transaction {
+        date: rabo_transaction.date,
+        source,
+        sink,
+        amount: rabo_transaction.amount.abs(),
+        inherent_tags: String::new(),
+        description,
+        sequence: Some(sequence),
+        reference: None,
+        end_to_end: None,
+        mandate: None,
+        balance: Some(balance),
+        code: match rabo_transaction.code {
+            Code::Other => None,
+            code => Some(format!("{:?}", code).to_uppercase()),
+        },
+        occurrence: 0,
+        rule_tags: BTreeMap::new(),
+        category: None,
+    })
+}
+
+fn determine_node_type(rabo_transaction: &RabobankCurrentAccount) -> Result<Node, ConversionError> {
+    let name = rabo_transaction.name.trim();
+    match rabo_transaction.code {
+        // No terminal ID in the export: the merchant name is the best we have.
+        Code::Ba | Code::Bc if !name.is_empty() => return Ok(Node::Terminal(name.into())),
+        Code::Ga | Code::Gb => return Ok(Node::Atm(name.into())),
+        _ => {}
+    }
+
+    if let Some(identifier) = &rabo_transaction.counter_party {
+        if let Ok(iban) = Iban::parse(identifier.trim()) {
+            return Ok(Node::ProperAccount(Account {
+                iban,
+                name: String::from(name),
+            }));
+        }
+    }
+
+    match rabo_transaction.code {
+        Code::Db | Code::Kh => Ok(Node::Other("Rabobank".into())),
+        _ => Err(ConversionError::UnknownCounterparty),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canonical::fixtures::*;
+    use rust_decimal_macros::dec;
+
+    const SAMPLE: &str = r#""IBAN/BBAN","Munt","Volgnr","Datum","Bedrag","Saldo na trn","Tegenrekening IBAN/BBAN","Naam tegenpartij","Code","Omschrijving-1","Omschrijving-2","Omschrijving-3"
+"NL44RABO0123456789","EUR","000000000000000101","2024-01-02","-12,50","+87,50","NL91ABNA0417164300","Checking","cb","Invoice"," 2024-001",""
+"NL44RABO0123456789","EUR","000000000000000102","2024-01-02","+3,00","+90,50","","Rabobank","db","Interest","",""
+"NL44RABO0123456789","EUR","000000000000000103","2024-01-03","-4,10","+86,40","","Bakery","bc","","",""
+"NL44RABO0123456789","EUR","one hundred four","2024-01-03","-1,00","+85,40","","","cb","","",""
+"NL44RABO0123456789","EUR","000000000000000105","2024-01-04","-1,00","+84,40","","","ei","","",""
+"#;
+
+    #[test]
+    fn sniffs_header() {
+        assert_eq!(Rabobank.sniff(SAMPLE), 1.0);
+        assert_eq!(Rabobank.sniff("Date;Name / Description;Account"), 0.0);
+    }
+
+    #[test]
+    fn imports_current_account() {
+        let own = account("NL44RABO0123456789", "Own");
+        let directory = directory("rabobank", &[("rabobank.csv", SAMPLE)]);
+        let mut report = ImportReport::default();
+        let transactions = Rabobank
+            .import(
+                &directory.join("rabobank.csv"),
+                &owner(vec![own.clone()]),
+                &mut report,
+            )
+            .unwrap();
+        assert_eq!(transactions.len(), 3);
+        // A sequence number that isn't one, and a direct debit without a counterparty.
+        assert_eq!(report.rejected().count(), 2);
+
+        let [payment, interest, card] = &transactions[..] else {
+            unreachable!()
+        };
+        assert_eq!(payment.direction(&own), Some(Direction::Outgoing));
+        assert_eq!(payment.amount, dec!(12.50));
+        assert_eq!(payment.sink.id(), checking().id());
+        assert_eq!(payment.description, "Invoice 2024-001");
+        assert_eq!(
+            payment.sequence,
+            Some(Sequence {
+                of: own.id(),
+                number: 101,
+            })
+        );
+
+        assert_eq!(interest.direction(&own), Some(Direction::Incoming));
+        assert_eq!(interest.amount, dec!(3));
+        assert_eq!(interest.source, Node::Other(String::from("Rabobank")));
+        assert_eq!(interest.sequence.as_ref().unwrap().number, 102);
+
+        assert_eq!(card.sink, Node::Terminal(String::from("Bakery")));
+    }
+}
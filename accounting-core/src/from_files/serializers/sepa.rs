@@ -0,0 +1,271 @@
+//! Fields that banks pack into the free text description of SEPA transactions.
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// The structured parts of a packed SEPA description. Every field is optional:
+/// banks only include what applies to the transaction.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct SepaDescription {
+    /// Transaction type, e.g.: "SEPA OVERBOEKING", "SEPA Incasso algemeen doorlopend",
+    /// "Doorlopende incasso".
+    pub transaction_type: Option<String>,
+    /// IBAN of the counterparty.
+    pub iban: Option<String>,
+    /// BIC of the counterparty's bank.
+    pub bic: Option<String>,
+    /// Name of the counterparty.
+    pub name: Option<String>,
+    /// Remittance information: the description as filled in by the initiator.
+    pub remittance: Option<String>,
+    /// End-to-end reference, assigned by the initiator.
+    pub end_to_end: Option<String>,
+    /// Mandate ID of a direct debit.
+    pub mandate: Option<String>,
+    /// Creditor ID of a direct debit.
+    pub creditor: Option<String>,
+}
+impl SepaDescription {
+    /// Parse either the slash separated format (`/TRTP/SEPA OVERBOEKING/IBAN/NL.../NAME/...`)
+    /// or the labeled format (`SEPA Overboeking IBAN: NL... Naam: ...`). Text that
+    /// contains neither gives an empty `SepaDescription`.
+    pub fn parse(description: &str) -> Self {
+        let description = description.trim();
+        if description.starts_with('/') {
+            Self::from_slashes(description)
+        } else {
+            Self::from_labels(description)
+        }
+    }
+
+    /// `/KEY/value/KEY/value...`: values may contain slashes themselves, so
+    /// only known keys separate fields. A value may be empty, like REMI's in
+    /// `/REMI/USTD//text`, where the next key follows directly.
+    fn from_slashes(description: &str) -> Self {
+        // The slash after a key isn't part of the match, it may start the next key.
+        static KEYS: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(
+                r"/(TRTP|IBAN|BIC|NAME|REMI|EREF|MARF|CSID|CNTP|ORDP|BENM|ID|ADDR|SVCL|PURP|RTRN|ISDT|USTD|STRD)",
+            )
+            .unwrap()
+        });
+        let mut result = Self::default();
+        let matches: Vec<_> = KEYS
+            .captures_iter(description)
+            .filter(|captures| description[captures.get(0).unwrap().end()..].starts_with('/'))
+            .collect();
+        for (i, captures) in matches.iter().enumerate() {
+            let whole = captures.get(0).unwrap();
+            let end = matches
+                .get(i + 1)
+                .map_or(description.len(), |next| next.get(0).unwrap().start());
+            let value = description[(whole.end() + 1).min(end)..end]
+                .trim_matches('/')
+                .trim();
+            result.set(&captures[1], value);
+        }
+        result
+    }
+
+    /// `Label: value Label: value...` with a fixed set of Dutch labels, and the English
+    /// ones of ING's exports. ING puts the type of a direct debit after its IDs, e.g.:
+    /// `Machtiging ID: 0007 Incassant ID: NL30ZZZ3330 Doorlopende incasso`.
+    fn from_labels(description: &str) -> Self {
+        static LABELS: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(
+                r"(IBAN|BIC|Naam|Name|Omschrijving|Description|Kenmerk|Reference|Machtiging(?: ID)?|Mandate ID|Incassant(?: ID)?|Creditor ID):\s*",
+            )
+            .unwrap()
+        });
+        let mut result = Self::default();
+        let matches: Vec<_> = LABELS.captures_iter(description).collect();
+        if let Some(first) = matches.first() {
+            let transaction_type = description[..first.get(0).unwrap().start()].trim();
+            if !transaction_type.is_empty() {
+                result.transaction_type = Some(collapse_whitespace(transaction_type));
+            }
+        }
+        for (i, captures) in matches.iter().enumerate() {
+            let end = matches
+                .get(i + 1)
+                .map_or(description.len(), |next| next.get(0).unwrap().start());
+            let mut value = description[captures.get(0).unwrap().end()..end].trim();
+            let key = match &captures[1] {
+                "Naam" | "Name" => "NAME",
+                "Omschrijving" | "Description" => "REMI",
+                "Kenmerk" | "Reference" => "EREF",
+                "Machtiging" | "Machtiging ID" | "Mandate ID" => "MARF",
+                "Incassant" | "Incassant ID" | "Creditor ID" => "CSID",
+                key => key,
+            };
+            if key == "MARF" || key == "CSID" {
+                // IDs don't contain spaces, what follows is the type of direct debit.
+                if let Some((id, rest)) = value.split_once(char::is_whitespace) {
+                    if result.transaction_type.is_none() && !rest.trim().is_empty() {
+                        result.transaction_type = Some(collapse_whitespace(rest));
+                    }
+                    value = id;
+                }
+            }
+            result.set(key, value);
+        }
+        result
+    }
+
+    fn set(&mut self, key: &str, value: &str) {
+        if value.is_empty() {
+            return;
+        }
+        if key == "CNTP" {
+            // Counterparty: account/BIC/name/city
+            let mut parts = value.split('/').map(str::trim);
+            for field in ["IBAN", "BIC", "NAME"] {
+                if let Some(part) = parts.next() {
+                    self.set(field, part);
+                }
+            }
+            return;
+        }
+        let value = Some(collapse_whitespace(value));
+        match key {
+            "TRTP" => self.transaction_type = value,
+            "IBAN" => self.iban = value,
+            "BIC" => self.bic = value,
+            "NAME" => self.name = value,
+            "REMI" => self.remittance = value,
+            // Unstructured or structured remittance info, nested in REMI.
+            "USTD" | "STRD" if self.remittance.is_none() => self.remittance = value,
+            "EREF" => self.end_to_end = value,
+            "MARF" => self.mandate = value,
+            "CSID" => self.creditor = value,
+            _ => {}
+        }
+    }
+}
+
+/// Exports pad fields with runs of spaces, reduce those to a single space.
+pub fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(fields: &[(&str, &str)]) -> SepaDescription {
+        let mut description = SepaDescription::default();
+        for (key, value) in fields {
+            description.set(key, value);
+        }
+        description
+    }
+
+    #[test]
+    fn parses_slashes() {
+        let cases = [
+            (
+                "/TRTP/SEPA OVERBOEKING/IBAN/NL44RABO0123456789/BIC/RABONL2U/NAME/Shop/REMI/Invoice 1/EREF/E2E-1",
+                parsed(&[
+                    ("TRTP", "SEPA OVERBOEKING"),
+                    ("IBAN", "NL44RABO0123456789"),
+                    ("BIC", "RABONL2U"),
+                    ("NAME", "Shop"),
+                    ("REMI", "Invoice 1"),
+                    ("EREF", "E2E-1"),
+                ]),
+            ),
+            (
+                "/TRTP/SEPA Incasso algemeen doorlopend/CSID/NL00ZZZ000000000000/NAME/Energy Co/MARF/M-42/REMI/Monthly   bill/IBAN/NL44RABO0123456789",
+                parsed(&[
+                    ("TRTP", "SEPA Incasso algemeen doorlopend"),
+                    ("CSID", "NL00ZZZ000000000000"),
+                    ("NAME", "Energy Co"),
+                    ("MARF", "M-42"),
+                    ("REMI", "Monthly bill"),
+                    ("IBAN", "NL44RABO0123456789"),
+                ]),
+            ),
+            // Remittance nested in REMI, with an empty REMI value before it.
+            (
+                "/REMI/USTD//Invoice 2/EREF/E2E-2",
+                parsed(&[("REMI", "Invoice 2"), ("EREF", "E2E-2")]),
+            ),
+            (
+                "/REMI/STRD/CUR/RF18539007547034/EREF/NOTPROVIDED",
+                parsed(&[("REMI", "CUR/RF18539007547034"), ("EREF", "NOTPROVIDED")]),
+            ),
+            // Slashes within a value, and text that only starts like a key.
+            (
+                "/NAME/A/B Trading/REMI/IDEAL 1/2 payment",
+                parsed(&[("NAME", "A/B Trading"), ("REMI", "IDEAL 1/2 payment")]),
+            ),
+            (
+                "/CNTP/NL44RABO0123456789/RABONL2U/Shop/AMSTERDAM/REMI/Invoice 3",
+                parsed(&[
+                    ("IBAN", "NL44RABO0123456789"),
+                    ("BIC", "RABONL2U"),
+                    ("NAME", "Shop"),
+                    ("REMI", "Invoice 3"),
+                ]),
+            ),
+            ("/XYZ/unknown", SepaDescription::default()),
+        ];
+        for (description, expected) in cases {
+            assert_eq!(
+                SepaDescription::parse(description),
+                expected,
+                "{}",
+                description
+            );
+        }
+    }
+
+    #[test]
+    fn parses_labels() {
+        let cases = [
+            (
+                "SEPA Overboeking                 IBAN: NL02ABNA0123456789        BIC: ABNANL2A                    Naam: Joint                      Omschrijving: Pocket money",
+                parsed(&[
+                    ("TRTP", "SEPA Overboeking"),
+                    ("IBAN", "NL02ABNA0123456789"),
+                    ("BIC", "ABNANL2A"),
+                    ("NAME", "Joint"),
+                    ("REMI", "Pocket money"),
+                ]),
+            ),
+            (
+                "SEPA Incasso algemeen doorlopend Incassant: NL00ZZZ000000000000  Naam: Energy Co  Machtiging: M-42  Omschrijving: Monthly bill  IBAN: NL44RABO0123456789  Kenmerk: E2E-3",
+                parsed(&[
+                    ("TRTP", "SEPA Incasso algemeen doorlopend"),
+                    ("CSID", "NL00ZZZ000000000000"),
+                    ("NAME", "Energy Co"),
+                    ("MARF", "M-42"),
+                    ("REMI", "Monthly bill"),
+                    ("IBAN", "NL44RABO0123456789"),
+                    ("EREF", "E2E-3"),
+                ]),
+            ),
+            // ING's English labels, with the type of direct debit after the IDs.
+            (
+                "Name: Energy Co Description: Monthly bill IBAN: NL44RABO0123456789 Reference: E2E-4 Mandate ID: M-42 Creditor ID: NL00ZZZ000000000000 Doorlopende incasso",
+                parsed(&[
+                    ("NAME", "Energy Co"),
+                    ("REMI", "Monthly bill"),
+                    ("IBAN", "NL44RABO0123456789"),
+                    ("EREF", "E2E-4"),
+                    ("MARF", "M-42"),
+                    ("CSID", "NL00ZZZ000000000000"),
+                    ("TRTP", "Doorlopende incasso"),
+                ]),
+            ),
+            ("ABN AMRO Bank N.V.               Fee", SepaDescription::default()),
+        ];
+        for (description, expected) in cases {
+            assert_eq!(
+                SepaDescription::parse(description),
+                expected,
+                "{}",
+                description
+            );
+        }
+    }
+}
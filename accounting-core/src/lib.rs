@@ -0,0 +1,9 @@
+//! Reading bank exports into canonical transactions, analysing them, and storing
+//! and writing them back out. Shared by the command line app and the Tauri app.
+
+pub mod analysis;
+pub mod canonical;
+pub mod from_files;
+pub mod rules;
+pub mod store;
+pub mod to_files;
@@ -0,0 +1,199 @@
+//! Tagging transactions with rules. An `Owner` keeps a list of them (see `canonical::rule`):
+//! every rule that matches a transaction adds its tags to it, and the transaction remembers
+//! which rule added which tag (see `Transaction::rule_tags`). The first matching rule with a
+//! category sets the transaction's category. Rules are applied when transactions are
+//! imported, and again with `apply` whenever the rules or the owner change.
+use crate::canonical::{category::Category, identify::*, rule::Rule, state::Owner, transaction::*};
+use std::collections::BTreeMap;
+
+/// The tags `rules` add to `transaction`, each with the name of the first rule (in
+/// order) that adds it.
+pub fn evaluate(
+    transaction: &Transaction,
+    rules: &[Rule],
+    owner: &Owner,
+) -> BTreeMap<String, String> {
+    let mut tags = BTreeMap::new();
+    for rule in rules.iter().filter(|rule| rule.matches(transaction, owner)) {
+        for tag in &rule.tags {
+            tags.entry(format!("#{}", tag.trim_start_matches('#')))
+                .or_insert_with(|| rule.name.clone());
+        }
+    }
+    tags
+}
+
+/// The category of `transaction`: the one the owner chose for it, or the one of the first
+/// rule that matches and has a category.
+pub fn categorize(transaction: &Transaction, owner: &Owner) -> Option<Category> {
+    if let Some(category) = owner.categorized.get(&transaction.id()) {
+        return Some(category.clone());
+    }
+    owner
+        .rules
+        .iter()
+        .filter(|rule| rule.category.is_some())
+        .find(|rule| rule.matches(transaction, owner))
+        .and_then(|rule| rule.category.clone())
+}
+
+/// Replace the rule tags and the category of every transaction with the ones `owner`'s
+/// rules and categories give it now. Returns the number of transactions with at least one
+/// rule tag or a category.
+pub fn apply(transactions: &mut Transactions, owner: &Owner) -> usize {
+    let mut tagged = 0;
+    for transaction in transactions.data_mut() {
+        transaction.rule_tags = evaluate(transaction, &owner.rules, owner);
+        transaction.category = categorize(transaction, owner);
+        if !transaction.rule_tags.is_empty() || transaction.category.is_some() {
+            tagged += 1;
+        }
+    }
+    tagged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canonical::{fixtures::*, query::Pattern, rule::Conditions};
+    use regex::Regex;
+    use rust_decimal_macros::dec;
+
+    fn rule(name: &str, tags: &[&str], category: Option<&str>, when: Conditions) -> Rule {
+        Rule {
+            name: String::from(name),
+            tags: tags.iter().map(|tag| String::from(*tag)).collect(),
+            category: category.map(|category| category.parse().unwrap()),
+            when,
+        }
+    }
+
+    fn pattern(pattern: &str) -> Option<Pattern> {
+        Some(Pattern(Regex::new(pattern).unwrap()))
+    }
+
+    fn coffee() -> Transaction {
+        Transaction {
+            description: String::from("Coffee Corner AMSTERDAM"),
+            ..transaction(
+                date(2024, 1, 2),
+                &checking(),
+                &Node::Terminal(String::from("CT123456")),
+                dec!(3.20),
+            )
+        }
+    }
+
+    #[test]
+    fn first_rule_names_each_tag() {
+        let rules = [
+            rule(
+                "Terminal",
+                &["coffee"],
+                None,
+                Conditions {
+                    counterparty_name: pattern("^CT"),
+                    ..Default::default()
+                },
+            ),
+            rule(
+                "Description",
+                &["#coffee", "#amsterdam"],
+                None,
+                Conditions {
+                    description: pattern("AMSTERDAM"),
+                    ..Default::default()
+                },
+            ),
+            rule(
+                "Large",
+                &["#large"],
+                None,
+                Conditions {
+                    min_amount: Some(dec!(100)),
+                    ..Default::default()
+                },
+            ),
+        ];
+        let tags = evaluate(&coffee(), &rules, &owner(vec![checking()]));
+        let expected: BTreeMap<String, String> =
+            [("#amsterdam", "Description"), ("#coffee", "Terminal")]
+                .into_iter()
+                .map(|(tag, rule)| (String::from(tag), String::from(rule)))
+                .collect();
+        assert_eq!(tags, expected);
+    }
+
+    #[test]
+    fn transfers_between_own_nodes_have_no_direction() {
+        let outgoing = rule(
+            "Out",
+            &["#out"],
+            None,
+            Conditions {
+                direction: Some(Direction::Outgoing),
+                ..Default::default()
+            },
+        );
+        let owner = owner(vec![checking(), joint()]);
+        let transfer = transaction(date(2024, 1, 2), &checking(), &joint(), dec!(10));
+        assert!(evaluate(&transfer, std::slice::from_ref(&outgoing), &owner).is_empty());
+        assert!(outgoing.matches(&coffee(), &owner));
+    }
+
+    #[test]
+    fn first_rule_with_a_category_categorizes() {
+        let mut owner = owner(vec![checking()]);
+        owner.rules = vec![
+            rule("Tag only", &["#coffee"], None, Conditions::default()),
+            rule(
+                "Groceries",
+                &[],
+                Some("Food > Groceries"),
+                Conditions {
+                    code: Some(String::from("BA")),
+                    ..Default::default()
+                },
+            ),
+            rule("Coffee", &[], Some("Food > Coffee"), Conditions::default()),
+            rule("Other", &[], Some("Other"), Conditions::default()),
+        ];
+        let coffee = coffee();
+        assert_eq!(categorize(&coffee, &owner), "Food > Coffee".parse().ok());
+
+        // The owner's own choice comes before any rule.
+        owner
+            .categorized
+            .insert(coffee.id(), "Treats".parse().unwrap());
+        assert_eq!(categorize(&coffee, &owner), "Treats".parse().ok());
+    }
+
+    #[test]
+    fn apply_replaces_tags_and_categories() {
+        let mut owner = owner(vec![checking()]);
+        owner.rules = vec![rule(
+            "Coffee",
+            &["#coffee"],
+            Some("Food"),
+            Conditions {
+                description: pattern("Coffee"),
+                ..Default::default()
+            },
+        )];
+        let stale = Transaction {
+            rule_tags: [(String::from("#old"), String::from("Removed"))].into(),
+            category: "Old".parse().ok(),
+            ..transaction(date(2024, 1, 3), &checking(), &shop(), dec!(1))
+        };
+        let mut transactions = Transactions::new(vec![coffee(), stale]);
+        assert_eq!(apply(&mut transactions, &owner), 1);
+        let [coffee, other] = transactions.data() else {
+            unreachable!()
+        };
+        assert!(coffee.rule_tags.contains_key("#coffee"));
+        assert!(coffee.tags().contains("#coffee"));
+        assert_eq!(coffee.category, "Food".parse().ok());
+        assert!(other.rule_tags.is_empty());
+        assert_eq!(other.category, None);
+    }
+}
@@ -0,0 +1,457 @@
+//! Persistence of canonical transactions, nodes and owners in a SQLite database.
+//!
+//! The schema is versioned through SQLite's `user_version` pragma: every entry
+//! in `MIGRATIONS` moves the database one version forward, so opening an older
+//! database file upgrades it in place.
+use crate::canonical::{
+    identify::*,
+    state::Owner,
+    transaction::{Balance, Mandate, Node, Sequence, Transaction, Transactions},
+};
+use chrono::NaiveDate;
+use rusqlite::{
+    params, Connection, Error, OptionalExtension, Result, Transaction as SqlTransaction,
+};
+use rust_decimal::Decimal;
+use std::{collections::BTreeMap, path::Path, str::FromStr};
+
+type Migration = fn(&SqlTransaction) -> Result<()>;
+
+/// Ordered list of schema migrations. Index `i` upgrades the schema from
+/// version `i` to version `i + 1`. Only ever append to this list.
+const MIGRATIONS: &[Migration] = &[initial_schema];
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+fn initial_schema(tx: &SqlTransaction) -> Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE nodes (
+            key  INTEGER PRIMARY KEY,
+            id   TEXT NOT NULL,
+            node TEXT NOT NULL UNIQUE
+        );
+        CREATE INDEX nodes_by_id ON nodes (id);
+
+        CREATE TABLE transactions (
+            key           INTEGER PRIMARY KEY,
+            id            TEXT NOT NULL UNIQUE,
+            date          TEXT NOT NULL,
+            source        INTEGER NOT NULL REFERENCES nodes (key),
+            sink          INTEGER NOT NULL REFERENCES nodes (key),
+            amount        TEXT NOT NULL,
+            inherent_tags TEXT NOT NULL,
+            description   TEXT NOT NULL,
+            sequence_of   TEXT,
+            sequence      INTEGER,
+            reference     TEXT,
+            end_to_end    TEXT,
+            mandate       TEXT,
+            creditor      TEXT,
+            balance_of    TEXT,
+            balance       TEXT,
+            occurrence    INTEGER NOT NULL DEFAULT 0,
+            code          TEXT
+        );
+        CREATE INDEX transactions_by_date ON transactions (date);
+
+        CREATE TABLE owners (
+            name             TEXT PRIMARY KEY,
+            account_names    TEXT NOT NULL DEFAULT '{}',
+            queries          TEXT NOT NULL DEFAULT '{}',
+            rules            TEXT NOT NULL DEFAULT '[]',
+            categories       TEXT NOT NULL DEFAULT '[]',
+            categorized      TEXT NOT NULL DEFAULT '{}',
+            opening_balances TEXT NOT NULL DEFAULT '[]'
+        );
+
+        CREATE TABLE owned_nodes (
+            owner    TEXT NOT NULL REFERENCES owners (name) ON DELETE CASCADE,
+            node     INTEGER NOT NULL REFERENCES nodes (key),
+            position INTEGER NOT NULL,
+            PRIMARY KEY (owner, node)
+        );",
+    )
+}
+
+/// Columns (and joins) that `transaction_from_row` reads, starting at its `offset`.
+const TRANSACTION_SELECT: &str =
+    "t.date, source.node, sink.node, t.amount, t.inherent_tags, t.description, t.sequence_of,
+     t.sequence, t.reference, t.end_to_end, t.mandate, t.creditor, t.balance_of, t.balance,
+     t.occurrence, t.code
+     FROM transactions t
+     JOIN nodes source ON source.key = t.source
+     JOIN nodes sink ON sink.key = t.sink";
+
+fn transaction_from_row(row: &rusqlite::Row, offset: usize) -> Result<Transaction> {
+    Ok(Transaction {
+        date: parse_column(row.get(offset)?, offset, |date| {
+            NaiveDate::parse_from_str(date, DATE_FORMAT)
+        })?,
+        source: json_column(row.get(offset + 1)?, offset + 1)?,
+        sink: json_column(row.get(offset + 2)?, offset + 2)?,
+        amount: parse_column(row.get(offset + 3)?, offset + 3, Decimal::from_str)?,
+        inherent_tags: row.get(offset + 4)?,
+        description: row.get(offset + 5)?,
+        sequence: match (
+            row.get::<_, Option<String>>(offset + 6)?,
+            row.get::<_, Option<i64>>(offset + 7)?,
+        ) {
+            (Some(of), Some(number)) => Some(Sequence {
+                of: parse_column(of, offset + 6, ID::from_str)?,
+                number: number as u64,
+            }),
+            _ => None,
+        },
+        reference: row.get(offset + 8)?,
+        end_to_end: row.get(offset + 9)?,
+        mandate: row
+            .get::<_, Option<String>>(offset + 10)?
+            .map(|id| -> Result<Mandate> {
+                Ok(Mandate {
+                    id,
+                    creditor: row.get(offset + 11)?,
+                })
+            })
+            .transpose()?,
+        balance: match (
+            row.get::<_, Option<String>>(offset + 12)?,
+            row.get::<_, Option<String>>(offset + 13)?,
+        ) {
+            (Some(of), Some(amount)) => Some(Balance {
+                of: parse_column(of, offset + 12, ID::from_str)?,
+                amount: parse_column(amount, offset + 13, Decimal::from_str)?,
+            }),
+            _ => None,
+        },
+        occurrence: row.get::<_, i64>(offset + 14)? as u64,
+        code: row.get(offset + 15)?,
+        // Derived from the owner's rules and categories, so not stored.
+        rule_tags: BTreeMap::new(),
+        category: None,
+    })
+}
+
+/// A SQLite database holding previously imported transactions and profiles.
+pub struct Store {
+    connection: Connection,
+}
+impl Store {
+    /// Open (or create) the database at `path` and bring its schema up to date.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::migrated(Connection::open(path)?)
+    }
+
+    fn migrated(mut connection: Connection) -> Result<Self> {
+        connection.pragma_update(None, "foreign_keys", true)?;
+        let version = schema_version(&connection)?;
+        if version > MIGRATIONS.len() {
+            return Err(newer_schema(version));
+        }
+        for (from, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let tx = connection.transaction()?;
+            migration(&tx)?;
+            tx.pragma_update(None, "user_version", from + 1)?;
+            tx.commit()?;
+        }
+        Ok(Self { connection })
+    }
+
+    /// The schema version of the underlying database.
+    pub fn schema_version(&self) -> Result<usize> {
+        schema_version(&self.connection)
+    }
+
+    /// Write `transactions` (and the nodes they refer to) to the database.
+    /// Transactions that are already stored are left untouched.
+    ///
+    /// Returns the number of newly stored transactions.
+    pub fn save_transactions<'t, I>(&mut self, transactions: I) -> Result<usize>
+    where
+        I: IntoIterator<Item = &'t Transaction>,
+    {
+        let tx = self.connection.transaction()?;
+        let mut inserted = 0;
+        for transaction in transactions {
+            let source = insert_node(&tx, &transaction.source)?;
+            let sink = insert_node(&tx, &transaction.sink)?;
+            inserted += tx.execute(
+                "INSERT OR IGNORE INTO transactions
+                    (id, date, source, sink, amount, inherent_tags, description, sequence_of,
+                     sequence, reference, end_to_end, mandate, creditor, balance_of, balance,
+                     occurrence, code)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16,
+                         ?17)",
+                params![
+                    transaction.id().to_string(),
+                    transaction.date.format(DATE_FORMAT).to_string(),
+                    source,
+                    sink,
+                    transaction.amount.to_string(),
+                    transaction.inherent_tags,
+                    transaction.description,
+                    transaction
+                        .sequence
+                        .as_ref()
+                        .map(|sequence| sequence.of.to_string()),
+                    transaction
+                        .sequence
+                        .as_ref()
+                        .map(|sequence| sequence.number as i64),
+                    transaction.reference,
+                    transaction.end_to_end,
+                    transaction.mandate.as_ref().map(|mandate| &mandate.id),
+                    transaction
+                        .mandate
+                        .as_ref()
+                        .and_then(|mandate| mandate.creditor.as_ref()),
+                    transaction
+                        .balance
+                        .as_ref()
+                        .map(|balance| balance.of.to_string()),
+                    transaction
+                        .balance
+                        .as_ref()
+                        .map(|balance| balance.amount.to_string()),
+                    transaction.occurrence as i64,
+                    transaction.code,
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    /// Read back every stored transaction, in the order they were stored per date.
+    pub fn load_transactions(&self) -> Result<Vec<Transaction>> {
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {} ORDER BY t.date, t.key",
+            TRANSACTION_SELECT
+        ))?;
+        let rows = statement.query_map([], |row| transaction_from_row(row, 0))?;
+        rows.collect()
+    }
+
+    /// Store `owner` together with the nodes it owns, replacing any previously
+    /// stored owner with the same name.
+    pub fn save_owner(&mut self, owner: &Owner) -> Result<()> {
+        let tx = self.connection.transaction()?;
+        tx.execute("DELETE FROM owners WHERE name = ?1", [&owner.name])?;
+        tx.execute(
+            "INSERT INTO owners
+                 (name, account_names, queries, rules, categories, categorized, opening_balances)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                owner.name,
+                to_json(&owner.account_names)?,
+                to_json(&owner.queries)?,
+                to_json(&owner.rules)?,
+                to_json(&owner.categories)?,
+                to_json(&owner.categorized)?,
+                to_json(&owner.opening_balances)?
+            ],
+        )?;
+        for (position, node) in owner.owns.iter().enumerate() {
+            let node = insert_node(&tx, node)?;
+            tx.execute(
+                "INSERT OR IGNORE INTO owned_nodes (owner, node, position) VALUES (?1, ?2, ?3)",
+                params![owner.name, node, position],
+            )?;
+        }
+        tx.commit()
+    }
+
+    /// Read back the owner stored under `name`, if any.
+    pub fn load_owner(&self, name: &str) -> Result<Option<Owner>> {
+        let owner = self
+            .connection
+            .query_row(
+                "SELECT account_names, queries, rules, categories, categorized, opening_balances
+                 FROM owners WHERE name = ?1",
+                [name],
+                |row| {
+                    Ok(Owner {
+                        name: name.to_string(),
+                        owns: Vec::new(),
+                        account_names: json_column(row.get(0)?, 0)?,
+                        queries: json_column(row.get(1)?, 1)?,
+                        rules: json_column(row.get(2)?, 2)?,
+                        categories: json_column(row.get(3)?, 3)?,
+                        categorized: json_column(row.get(4)?, 4)?,
+                        opening_balances: json_column(row.get(5)?, 5)?,
+                    })
+                },
+            )
+            .optional()?;
+        let Some(owner) = owner else {
+            return Ok(None);
+        };
+        let mut statement = self.connection.prepare(
+            "SELECT nodes.node FROM owned_nodes
+             JOIN nodes ON nodes.key = owned_nodes.node
+             WHERE owned_nodes.owner = ?1
+             ORDER BY owned_nodes.position",
+        )?;
+        let owns = statement
+            .query_map([name], |row| json_column(row.get(0)?, 0))?
+            .collect::<Result<_>>()?;
+        Ok(Some(Owner { owns, ..owner }))
+    }
+}
+
+/// Build `Transactions` from everything in `store` instead of from .csv files.
+pub fn transactions_from_store<'a>(store: &Store) -> Result<Transactions<'a>> {
+    Ok(Transactions::new(store.load_transactions()?))
+}
+
+/// A database written by a newer version of this program can't be opened: its
+/// schema can't be migrated backwards.
+fn newer_schema(version: usize) -> Error {
+    Error::SqliteFailure(
+        rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CANTOPEN),
+        Some(format!(
+            "database schema version {} is newer than the supported version {}",
+            version,
+            MIGRATIONS.len()
+        )),
+    )
+}
+
+fn schema_version(connection: &Connection) -> Result<usize> {
+    connection.pragma_query_value(None, "user_version", |row| row.get(0))
+}
+
+/// Insert `node` if it isn't stored yet and return its key.
+fn insert_node(tx: &SqlTransaction, node: &Node) -> Result<i64> {
+    let json = to_json(node)?;
+    tx.execute(
+        "INSERT OR IGNORE INTO nodes (id, node) VALUES (?1, ?2)",
+        params![node.id().to_string(), json],
+    )?;
+    tx.query_row("SELECT key FROM nodes WHERE node = ?1", [json], |row| {
+        row.get(0)
+    })
+}
+
+fn to_json<T: serde::Serialize + ?Sized>(value: &T) -> Result<String> {
+    serde_json::to_string(value).map_err(|err| Error::ToSqlConversionFailure(Box::new(err)))
+}
+
+fn json_column<T: serde::de::DeserializeOwned>(value: String, column: usize) -> Result<T> {
+    parse_column(value, column, |value| serde_json::from_str(value))
+}
+
+fn parse_column<T, E, F>(value: String, column: usize, parse: F) -> Result<T>
+where
+    E: std::error::Error + Send + Sync + 'static,
+    F: FnOnce(&str) -> std::result::Result<T, E>,
+{
+    parse(&value).map_err(|err| {
+        Error::FromSqlConversionFailure(column, rusqlite::types::Type::Text, Box::new(err))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canonical::{
+        fixtures::*,
+        query::Query,
+        rule::{Conditions, Rule},
+        state::OpeningBalance,
+    };
+    use rust_decimal_macros::dec;
+
+    fn store() -> Store {
+        Store::migrated(Connection::open_in_memory().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn new_database_is_at_latest_version() {
+        assert_eq!(store().schema_version().unwrap(), MIGRATIONS.len());
+    }
+
+    #[test]
+    fn transactions_round_trip() {
+        let mut store = store();
+        let payment = Transaction {
+            inherent_tags: String::from("#food"),
+            description: String::from("Groceries"),
+            reference: Some(String::from("20240103-0001")),
+            end_to_end: Some(String::from("E2E-1")),
+            mandate: Some(Mandate {
+                id: String::from("M-1"),
+                creditor: Some(String::from("NL00ZZZ000000000000")),
+            }),
+            code: Some(String::from("BA")),
+            ..transaction(date(2024, 1, 3), &checking(), &shop(), dec!(12.50))
+        };
+        let refund = Transaction {
+            sequence: Some(Sequence {
+                of: checking().id(),
+                number: 101,
+            }),
+            occurrence: 1,
+            ..with_balance(
+                transaction(date(2024, 1, 2), &shop(), &checking(), dec!(2)),
+                &checking(),
+                dec!(102.25),
+            )
+        };
+        assert_eq!(store.save_transactions([&payment, &refund]).unwrap(), 2);
+        assert_eq!(store.save_transactions([&payment]).unwrap(), 0);
+        assert_eq!(store.load_transactions().unwrap(), [refund, payment]);
+    }
+
+    #[test]
+    fn owner_round_trips() {
+        let mut store = store();
+        store.save_owner(&owner(vec![shop(), checking()])).unwrap();
+        let mut me = owner(vec![checking()]);
+        me.account_names
+            .insert(checking().id(), String::from("Assets:Bank:Checking"));
+        me.queries.insert(
+            String::from("groceries"),
+            Query::default().tag("#boodschappen"),
+        );
+        me.rules.push(Rule {
+            name: String::from("Shop"),
+            tags: vec![String::from("#shop")],
+            category: "Shopping".parse().ok(),
+            when: Conditions {
+                counterparty: Some(shop().id()),
+                ..Default::default()
+            },
+        });
+        me.categories.push("Shopping > Food".parse().unwrap());
+        let payment = transaction(date(2024, 1, 3), &checking(), &shop(), dec!(1));
+        me.categorized.insert(payment.id(), "Food".parse().unwrap());
+        me.opening_balances.push(OpeningBalance {
+            node: checking().id(),
+            date: date(2024, 1, 1),
+            amount: dec!(1000),
+        });
+        store.save_owner(&me).unwrap();
+        let loaded = store.load_owner("Me").unwrap().unwrap();
+        assert_eq!(loaded.owns, [checking()]);
+        assert_eq!(loaded.account_names, me.account_names);
+        assert_eq!(loaded.queries, me.queries);
+        assert_eq!(loaded.rules, me.rules);
+        assert_eq!(loaded.categories, me.categories);
+        assert_eq!(loaded.categorized, me.categorized);
+        assert_eq!(loaded.opening_balances, me.opening_balances);
+        assert!(store.load_owner("Someone else").unwrap().is_none());
+    }
+
+    #[test]
+    fn refuses_newer_schema() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .pragma_update(None, "user_version", MIGRATIONS.len() + 1)
+            .unwrap();
+        let Err(Error::SqliteFailure(error, Some(message))) = Store::migrated(connection) else {
+            panic!("expecting a newer schema to be refused")
+        };
+        assert_eq!(error.code, rusqlite::ErrorCode::CannotOpen);
+        assert!(message.contains("newer than the supported version"));
+    }
+}
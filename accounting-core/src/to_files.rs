@@ -0,0 +1,136 @@
+//! Writing transactions back out, in formats that other tools read.
+
+use crate::canonical::transaction::*;
+
+/// The formats only come from Dutch banks, so everything is in euros.
+pub const CURRENCY: &str = "EUR";
+
+/// The tags of `transaction` without their `#`, reduced to letters, digits
+/// and `-_/.`, sorted.
+pub fn tag_names(transaction: &Transaction) -> Vec<String> {
+    let mut tags: Vec<String> = transaction
+        .tags()
+        .iter()
+        .map(|tag| {
+            tag.trim_start_matches('#')
+                .chars()
+                .filter(|c| c.is_ascii_alphanumeric() || "-_/.".contains(*c))
+                .collect()
+        })
+        .filter(|tag: &String| !tag.is_empty())
+        .collect();
+    tags.sort();
+    tags
+}
+
+pub mod accounts {
+    use crate::canonical::{identify::*, state::Owner, transaction::*};
+    use rust_decimal::Decimal;
+    use std::collections::HashMap;
+
+    /// Names of nodes as accounts in double-entry bookkeeping (`Assets:INGB:Checking`),
+    /// for the journal exporters.
+    ///
+    /// Names come from `Owner::account_names` first. Otherwise, owned nodes are assets,
+    /// other nodes are income when they pay the owner more than they receive from the
+    /// owner, and expenses when they don't.
+    pub struct AccountNames<'a> {
+        owner: &'a Owner,
+        income: HashMap<ID<Node>, Decimal>,
+    }
+    impl<'a> AccountNames<'a> {
+        pub fn new<'t, I>(owner: &'a Owner, transactions: I) -> Self
+        where
+            I: IntoIterator<Item = &'t Transaction>,
+        {
+            let mut income: HashMap<ID<Node>, Decimal> = HashMap::new();
+            for transaction in transactions {
+                if owner.owns(&transaction.sink.id()) {
+                    *income.entry(transaction.source.id()).or_default() += transaction.amount;
+                }
+                if owner.owns(&transaction.source.id()) {
+                    *income.entry(transaction.sink.id()).or_default() -= transaction.amount;
+                }
+            }
+            Self { owner, income }
+        }
+
+        pub fn name(&self, node: &Node) -> String {
+            if let Some(name) = self.owner.account_names.get(&node.id()) {
+                return name.clone();
+            }
+            // The profile's node, its name is the one the owner chose.
+            if let Some(node) = self.owner.view(&node.id()) {
+                return match node {
+                    Node::ProperAccount(acc) => {
+                        let bank = acc.iban.bban().bank_identifier().map(String::from);
+                        let name = Some(acc.name.as_str()).filter(|name| !name.is_empty());
+                        account(
+                            "Assets",
+                            &[bank.as_deref(), Some(name.unwrap_or(acc.iban.as_str()))],
+                        )
+                    }
+                    Node::SubAccount(acc) => {
+                        let parent = &acc.parent_account.iban;
+                        let bank = parent.bban().bank_identifier().map(String::from);
+                        let name = Some(acc.name.as_str()).filter(|name| !name.is_empty());
+                        account(
+                            "Assets",
+                            &[bank.as_deref(), Some(name.unwrap_or(&acc.bsan))],
+                        )
+                    }
+                    Node::Terminal(id) | Node::Atm(id) | Node::Other(id) => {
+                        account("Assets", &[Some(id)])
+                    }
+                };
+            }
+
+            let root = match self.income.get(&node.id()) {
+                Some(net) if net.is_sign_positive() && !net.is_zero() => "Income",
+                _ => "Expenses",
+            };
+            match node {
+                // Withdrawn cash is still ours.
+                Node::Atm(_) => account("Assets", &[Some("Cash")]),
+                Node::Terminal(id) => account(root, &[Some("Terminals"), Some(id)]),
+                Node::Other(id) => account(root, &[Some(id)]),
+                Node::ProperAccount(acc) => {
+                    let name = Some(acc.name.as_str()).filter(|name| !name.is_empty());
+                    account(root, &[Some(name.unwrap_or(acc.iban.as_str()))])
+                }
+                Node::SubAccount(acc) => {
+                    let name = Some(acc.name.as_str()).filter(|name| !name.is_empty());
+                    account(root, &[Some(name.unwrap_or(&acc.bsan))])
+                }
+            }
+        }
+    }
+
+    fn account(root: &str, components: &[Option<&str>]) -> String {
+        let mut name = String::from(root);
+        for component in components.iter().flatten() {
+            name.push(':');
+            name.push_str(&component_name(component));
+        }
+        name
+    }
+
+    /// Account name components start with a capital or a digit, followed by
+    /// letters, digits and dashes: "Coffee & Co." becomes "Coffee-Co".
+    pub fn component_name(text: &str) -> String {
+        let words: Vec<_> = text
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect();
+        let mut chars = words.join("-").chars().collect::<Vec<_>>();
+        match chars.first_mut() {
+            Some(first) => first.make_ascii_uppercase(),
+            None => return String::from("Unknown"),
+        }
+        chars.into_iter().collect()
+    }
+}
+
+pub mod beancount;
+pub mod ledger;
+pub mod ofx;
@@ -0,0 +1,218 @@
+//! Beancount journals (https://beancount.github.io/docs/beancount_language_syntax.html).
+use super::{accounts::AccountNames, tag_names, CURRENCY};
+use crate::{
+    analysis::balances::closing_balances,
+    canonical::{
+        identify::*,
+        state::Owner,
+        transaction::{Node, Transaction, Transactions, View},
+    },
+};
+use chrono::{Days, NaiveDate};
+use itertools::Itertools;
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::{self, Write},
+};
+
+/// The account that opening balances come from.
+pub const OPENING_BALANCES: &str = "Equity:Opening-Balances";
+
+/// Write `transactions` as a Beancount journal to `out`:
+/// - an `open` directive for the account of every node, dated at its first transaction.
+///   Owned nodes without transactions are opened at the first transaction of the journal.
+/// - a transaction with two postings for every `Transaction`, with its ID as `id` metadata
+///   and its tags as `#tags`.
+/// - a `balance` assertion for every day an owned account has a known balance (e.g.:
+///   ING's "Resulting balance"), dated the next day: Beancount checks balances at the
+///   start of the day.
+/// - a `pad` from `OPENING_BALANCES` on the open date of every account with a balance
+///   assertion, for the money that was on it before its first transaction. Only the first
+///   assertion is padded: later ones still fail when transactions are missing.
+///
+/// Account names come from `AccountNames`.
+pub fn write<'a, Perspective: View<'a>, W: Write>(
+    transactions: &'a Transactions<'a, Perspective>,
+    owner: &Owner,
+    mut out: W,
+) -> io::Result<()> {
+    let names = AccountNames::new(owner, transactions);
+
+    writeln!(out, "option \"title\" {}", quoted(&owner.name))?;
+    writeln!(out, "option \"operating_currency\" \"{}\"", CURRENCY)?;
+    writeln!(out)?;
+
+    let mut opened: HashMap<String, NaiveDate> = HashMap::new();
+    for transaction in transactions {
+        for node in [&transaction.source, &transaction.sink] {
+            let date = opened.entry(names.name(node)).or_insert(transaction.date);
+            *date = (*date).min(transaction.date);
+        }
+    }
+    if let Some(first) = transactions.iter().map(|t| t.date).min() {
+        for node in &owner.owns {
+            opened.entry(names.name(node)).or_insert(first);
+        }
+    }
+
+    let nodes: HashMap<ID<Node>, &Node> = owner.owns.iter().map(|node| (node.id(), node)).collect();
+    let balances: Vec<_> = closing_balances(transactions)
+        .into_iter()
+        .filter_map(|((date, of), amount)| Some((date, names.name(nodes.get(&of)?), amount)))
+        .sorted()
+        .collect();
+    let padded: BTreeSet<&String> = balances.iter().map(|(_, account, _)| account).collect();
+    if let Some(first) = opened.values().min().filter(|_| !padded.is_empty()) {
+        writeln!(out, "{} open {}", first, OPENING_BALANCES)?;
+    }
+    let opened: Vec<_> = opened
+        .iter()
+        .sorted_by(|a, b| (a.1, a.0).cmp(&(b.1, b.0)))
+        .collect();
+    for (account, date) in &opened {
+        writeln!(out, "{} open {} {}", date, account, CURRENCY)?;
+    }
+    for (account, date) in opened
+        .iter()
+        .filter(|(account, _)| padded.contains(account))
+    {
+        writeln!(out, "{} pad {} {}", date, account, OPENING_BALANCES)?;
+    }
+
+    for transaction in transactions {
+        writeln!(out)?;
+        write_transaction(&mut out, transaction, owner, &names)?;
+    }
+
+    if !balances.is_empty() {
+        writeln!(out)?;
+    }
+    for (date, account, amount) in balances {
+        let next_day = date + Days::new(1);
+        writeln!(
+            out,
+            "{} balance {} {} {}",
+            next_day, account, amount, CURRENCY
+        )?;
+    }
+    Ok(())
+}
+
+fn write_transaction<W: Write>(
+    out: &mut W,
+    transaction: &Transaction,
+    owner: &Owner,
+    names: &AccountNames,
+) -> io::Result<()> {
+    let payee = owner.counterparty(transaction);
+    let tags: String = tag_names(transaction)
+        .iter()
+        .map(|tag| format!(" #{}", tag))
+        .collect();
+    writeln!(
+        out,
+        "{} * {} {}{}",
+        transaction.date,
+        quoted(&payee.name()),
+        quoted(&transaction.description),
+        tags
+    )?;
+    writeln!(out, "  id: \"{}\"", transaction.id())?;
+    writeln!(
+        out,
+        "  {}  {} {}",
+        names.name(&transaction.sink),
+        transaction.amount,
+        CURRENCY
+    )?;
+    writeln!(
+        out,
+        "  {}  {} {}",
+        names.name(&transaction.source),
+        -transaction.amount,
+        CURRENCY
+    )
+}
+
+fn quoted(text: &str) -> String {
+    format!(
+        "\"{}\"",
+        text.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', " ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canonical::fixtures::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn pads_opening_balance_before_first_assertion() {
+        let transactions = Transactions::new(vec![
+            with_balance(
+                transaction(date(2024, 1, 10), &checking(), &shop(), dec!(25)),
+                &checking(),
+                dec!(75),
+            ),
+            with_balance(
+                transaction(date(2024, 1, 20), &shop(), &checking(), dec!(5)),
+                &checking(),
+                dec!(80),
+            ),
+        ]);
+        let mut out = Vec::new();
+        write(&transactions, &owner(vec![checking()]), &mut out).unwrap();
+        let journal = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = journal.lines().collect();
+        let position = |line: &str| lines.iter().position(|l| *l == line).unwrap();
+
+        let open = position("2024-01-10 open Equity:Opening-Balances");
+        let account = position("2024-01-10 open Assets:ABNA:Checking EUR");
+        let pad = position("2024-01-10 pad Assets:ABNA:Checking Equity:Opening-Balances");
+        let first = position("2024-01-11 balance Assets:ABNA:Checking 75 EUR");
+        assert!(open < pad && account < pad && pad < first);
+        assert!(lines.contains(&"2024-01-21 balance Assets:ABNA:Checking 80 EUR"));
+        assert_eq!(journal.matches(" pad ").count(), 1);
+    }
+
+    #[test]
+    fn closing_balance_ends_the_days_chain() {
+        let transactions = [
+            with_balance(
+                transaction(date(2024, 1, 2), &shop(), &checking(), dec!(5)),
+                &checking(),
+                dec!(95),
+            ),
+            with_balance(
+                transaction(date(2024, 1, 2), &checking(), &shop(), dec!(10)),
+                &checking(),
+                dec!(90),
+            ),
+            with_balance(
+                transaction(date(2024, 1, 3), &checking(), &shop(), dec!(20)),
+                &checking(),
+                dec!(75),
+            ),
+        ];
+        let closing = closing_balances(&transactions);
+        assert_eq!(closing[&(date(2024, 1, 2), checking().id())], dec!(95));
+        assert_eq!(closing[&(date(2024, 1, 3), checking().id())], dec!(75));
+    }
+
+    #[test]
+    fn no_pad_without_balances() {
+        let transactions = Transactions::new(vec![transaction(
+            date(2024, 1, 10),
+            &checking(),
+            &shop(),
+            dec!(25),
+        )]);
+        let mut out = Vec::new();
+        write(&transactions, &owner(vec![checking()]), &mut out).unwrap();
+        let journal = String::from_utf8(out).unwrap();
+        assert!(!journal.contains(OPENING_BALANCES));
+    }
+}
@@ -0,0 +1,163 @@
+//! ledger-cli journals (https://ledger-cli.org/doc/ledger3.html), which hledger
+//! reads as well.
+use super::{accounts::AccountNames, tag_names, CURRENCY};
+use crate::canonical::{
+    identify::*,
+    state::Owner,
+    transaction::{Direction, Node, Transaction, Transactions, View},
+};
+use rust_decimal::Decimal;
+use std::{
+    collections::BTreeSet,
+    io::{self, Write},
+};
+
+const DATE_FORMAT: &str = "%Y/%m/%d";
+
+/// Write `transactions` as a ledger journal to `out`: an `account` directive for
+/// every account, then every transaction with the counterparty's `Node::name` as
+/// payee, its ID and description as comments, its tags as `tag:` comments, and a
+/// posting per node.
+///
+/// Account names come from `AccountNames`.
+pub fn write<'a, Perspective: View<'a>, W: Write>(
+    transactions: &'a Transactions<'a, Perspective>,
+    owner: &Owner,
+    mut out: W,
+) -> io::Result<()> {
+    let names = AccountNames::new(owner, transactions);
+
+    let accounts: BTreeSet<String> = transactions
+        .iter()
+        .flat_map(|transaction| [&transaction.source, &transaction.sink])
+        .chain(&owner.owns)
+        .map(|node| names.name(node))
+        .collect();
+    for account in &accounts {
+        writeln!(out, "account {}", account)?;
+    }
+
+    for transaction in transactions {
+        writeln!(out)?;
+        write_transaction(&mut out, transaction, owner, &names)?;
+    }
+    Ok(())
+}
+
+fn write_transaction<W: Write>(
+    out: &mut W,
+    transaction: &Transaction,
+    owner: &Owner,
+    names: &AccountNames,
+) -> io::Result<()> {
+    writeln!(
+        out,
+        "{} * {}",
+        transaction.date.format(DATE_FORMAT),
+        single_line(&owner.counterparty(transaction).name())
+    )?;
+    writeln!(out, "    ; id: {}", transaction.id())?;
+    if !transaction.description.is_empty() {
+        writeln!(out, "    ; {}", single_line(&transaction.description))?;
+    }
+    // A `tag:` comment per tag, which both hledger and ledger read as a tag.
+    for tag in tag_names(transaction) {
+        writeln!(out, "    ; {}:", tag)?;
+    }
+    for (node, amount) in postings(transaction, owner) {
+        writeln!(out, "    {}  {} {}", names.name(node), amount, CURRENCY)?;
+    }
+    Ok(())
+}
+
+/// The signed amount for both nodes of `transaction`. Owned nodes get the sign of
+/// the transaction's direction from their perspective, the other node the opposite.
+/// Owned nodes come first.
+fn postings<'t>(transaction: &'t Transaction, owner: &Owner) -> Vec<(&'t Node, Decimal)> {
+    let signed = |node: &Node| match transaction.direction(node) {
+        Some(Direction::Incoming) => transaction.amount,
+        _ => -transaction.amount,
+    };
+    let mut postings: Vec<_> = [&transaction.source, &transaction.sink]
+        .into_iter()
+        .map(|node| (node, signed(node)))
+        .collect();
+    postings.sort_by_key(|(node, _)| !owner.owns(&node.id()));
+    postings
+}
+
+/// Payees and comments end at the end of the line.
+fn single_line(text: &str) -> String {
+    text.replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canonical::fixtures::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn writes_tags_as_tag_comments() {
+        let transactions = Transactions::new(vec![Transaction {
+            inherent_tags: String::from("#food #weekly"),
+            description: String::from("Groceries"),
+            ..transaction(date(2024, 1, 10), &checking(), &shop(), dec!(25))
+        }]);
+        let mut out = Vec::new();
+        write(&transactions, &owner(vec![checking()]), &mut out).unwrap();
+        let journal = String::from_utf8(out).unwrap();
+        let comments: Vec<&str> = journal
+            .lines()
+            .filter(|line| line.starts_with("    ;"))
+            .skip(1)
+            .collect();
+        assert_eq!(
+            comments,
+            ["    ; Groceries", "    ; food:", "    ; weekly:"]
+        );
+    }
+
+    #[test]
+    fn postings_sign_amounts_from_the_owners_side() {
+        let me = owner(vec![checking(), joint()]);
+        let signed = |transaction: &Transaction| -> Vec<(Node, Decimal)> {
+            postings(transaction, &me)
+                .into_iter()
+                .map(|(node, amount)| (node.clone(), amount))
+                .collect()
+        };
+
+        let outgoing = transaction(date(2024, 1, 10), &checking(), &shop(), dec!(25));
+        assert_eq!(
+            signed(&outgoing),
+            [(checking(), dec!(-25)), (shop(), dec!(25))]
+        );
+
+        let incoming = transaction(date(2024, 1, 10), &shop(), &checking(), dec!(5));
+        assert_eq!(
+            signed(&incoming),
+            [(checking(), dec!(5)), (shop(), dec!(-5))]
+        );
+
+        let internal = transaction(date(2024, 1, 10), &checking(), &joint(), dec!(100));
+        assert_eq!(
+            signed(&internal),
+            [(checking(), dec!(-100)), (joint(), dec!(100))]
+        );
+
+        let unowned = transaction(
+            date(2024, 1, 10),
+            &shop(),
+            &account("NL20INGB0001234567", "Other"),
+            dec!(7),
+        );
+        assert_eq!(
+            signed(&unowned),
+            [
+                (shop(), dec!(-7)),
+                (account("NL20INGB0001234567", "Other"), dec!(7))
+            ]
+        );
+    }
+}
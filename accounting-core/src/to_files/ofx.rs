@@ -0,0 +1,307 @@
+//! OFX 2.2 (XML) statements, one per account of the owner. The inverse of
+//! `from_files::serializers::ofx`.
+use super::CURRENCY;
+use crate::canonical::{
+    account::AccountType,
+    identify::*,
+    state::Owner,
+    transaction::{Direction, Node, Transaction, Transactions, View},
+};
+use chrono::NaiveDate;
+use std::io::{self, Write};
+
+const DATE_FORMAT: &str = "%Y%m%d";
+
+/// Write the transactions of every node `owner` owns to `out`: a bank statement
+/// for accounts and sub-accounts, a credit card statement for `Node::Other` (which
+/// is what credit cards without an IBAN are imported as).
+///
+/// `<FITID>` is the bank's reference when the transaction has one, its ID otherwise.
+/// Balances aren't known, so statements have no `<LEDGERBAL>`.
+pub fn write<'a, Perspective: View<'a>, W: Write>(
+    transactions: &'a Transactions<'a, Perspective>,
+    owner: &Owner,
+    out: W,
+) -> io::Result<()> {
+    let first = transactions.iter().map(|t| t.date).min();
+    let last = transactions.iter().map(|t| t.date).max();
+    let mut ofx = OfxWriter { out, depth: 0 };
+
+    writeln!(
+        ofx.out,
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>"
+    )?;
+    writeln!(
+        ofx.out,
+        "<?OFX OFXHEADER=\"200\" VERSION=\"220\" SECURITY=\"NONE\" OLDFILEUID=\"NONE\" NEWFILEUID=\"NONE\"?>"
+    )?;
+    ofx.open("OFX")?;
+    ofx.open("SIGNONMSGSRSV1")?;
+    ofx.open("SONRS")?;
+    ofx.status()?;
+    // The date of the data rather than the current time, so exports are reproducible.
+    ofx.leaf("DTSERVER", last.unwrap_or_default().format(DATE_FORMAT))?;
+    ofx.leaf("LANGUAGE", "ENG")?;
+    ofx.close("SONRS")?;
+    ofx.close("SIGNONMSGSRSV1")?;
+
+    let statements: Vec<_> = owner
+        .owns
+        .iter()
+        .filter_map(|node| Some((node, StatementKind::of(node)?)))
+        .collect();
+    for (messages, kind) in [
+        ("BANKMSGSRSV1", StatementKind::Bank),
+        ("CREDITCARDMSGSRSV1", StatementKind::CreditCard),
+    ] {
+        let nodes: Vec<_> = statements
+            .iter()
+            .filter(|(_, of)| *of == kind)
+            .map(|(node, _)| *node)
+            .collect();
+        if nodes.is_empty() {
+            continue;
+        }
+        ofx.open(messages)?;
+        for (uid, node) in nodes.into_iter().enumerate() {
+            let entries = transactions.iter().filter(|t| t.direction(node).is_some());
+            ofx.statement(node, kind, uid, entries, owner, (first, last))?;
+        }
+        ofx.close(messages)?;
+    }
+    ofx.close("OFX")
+}
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum StatementKind {
+    Bank,
+    CreditCard,
+}
+impl StatementKind {
+    fn of(node: &Node) -> Option<Self> {
+        match node {
+            Node::ProperAccount(_) | Node::SubAccount(_) => Some(Self::Bank),
+            Node::Other(_) => Some(Self::CreditCard),
+            Node::Terminal(_) | Node::Atm(_) => None,
+        }
+    }
+}
+
+struct OfxWriter<W: Write> {
+    out: W,
+    depth: usize,
+}
+impl<W: Write> OfxWriter<W> {
+    fn open(&mut self, tag: &str) -> io::Result<()> {
+        writeln!(self.out, "{:indent$}<{}>", "", tag, indent = self.depth * 2)?;
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn close(&mut self, tag: &str) -> io::Result<()> {
+        self.depth -= 1;
+        writeln!(
+            self.out,
+            "{:indent$}</{}>",
+            "",
+            tag,
+            indent = self.depth * 2
+        )
+    }
+
+    fn leaf<T: ToString>(&mut self, tag: &str, value: T) -> io::Result<()> {
+        writeln!(
+            self.out,
+            "{:indent$}<{tag}>{}</{tag}>",
+            "",
+            escape(&value.to_string()),
+            indent = self.depth * 2
+        )
+    }
+
+    fn status(&mut self) -> io::Result<()> {
+        self.open("STATUS")?;
+        self.leaf("CODE", 0)?;
+        self.leaf("SEVERITY", "INFO")?;
+        self.close("STATUS")
+    }
+
+    /// `<BANKACCTFROM>`/`<BANKACCTTO>` and friends for `node`.
+    fn account(&mut self, tag: &str, node: &Node) -> io::Result<()> {
+        let (bank, account, account_type) = match node {
+            Node::ProperAccount(acc) => (
+                acc.iban.bban().bank_identifier().map(String::from),
+                acc.iban.as_str().to_string(),
+                "CHECKING",
+            ),
+            Node::SubAccount(acc) => (
+                acc.parent_account
+                    .iban
+                    .bban()
+                    .bank_identifier()
+                    .map(String::from),
+                acc.bsan.clone(),
+                match acc.account_type {
+                    Some(AccountType::Saving) => "SAVINGS",
+                    Some(AccountType::Deposit) => "CD",
+                    _ => "CHECKING",
+                },
+            ),
+            Node::Other(id) => {
+                self.open(tag)?;
+                self.leaf("ACCTID", id)?;
+                return self.close(tag);
+            }
+            Node::Terminal(_) | Node::Atm(_) => return Ok(()),
+        };
+        self.open(tag)?;
+        self.leaf("BANKID", bank.unwrap_or_default())?;
+        self.leaf("ACCTID", account)?;
+        self.leaf("ACCTTYPE", account_type)?;
+        self.close(tag)
+    }
+
+    fn statement<'t>(
+        &mut self,
+        node: &Node,
+        kind: StatementKind,
+        uid: usize,
+        transactions: impl Iterator<Item = &'t Transaction>,
+        owner: &Owner,
+        (first, last): (Option<NaiveDate>, Option<NaiveDate>),
+    ) -> io::Result<()> {
+        let (envelope, statement, account) = match kind {
+            StatementKind::Bank => ("STMTTRNRS", "STMTRS", "BANKACCTFROM"),
+            StatementKind::CreditCard => ("CCSTMTTRNRS", "CCSTMTRS", "CCACCTFROM"),
+        };
+        self.open(envelope)?;
+        self.leaf("TRNUID", uid)?;
+        self.status()?;
+        self.open(statement)?;
+        self.leaf("CURDEF", CURRENCY)?;
+        self.account(account, node)?;
+        self.open("BANKTRANLIST")?;
+        self.leaf("DTSTART", first.unwrap_or_default().format(DATE_FORMAT))?;
+        self.leaf("DTEND", last.unwrap_or_default().format(DATE_FORMAT))?;
+        for transaction in transactions {
+            self.entry(transaction, node, owner)?;
+        }
+        self.close("BANKTRANLIST")?;
+        self.close(statement)?;
+        self.close(envelope)
+    }
+
+    fn entry(&mut self, transaction: &Transaction, node: &Node, owner: &Owner) -> io::Result<()> {
+        let (counterparty, sign) = match transaction.direction(node) {
+            Some(Direction::Incoming) => (&transaction.source, ""),
+            _ => (&transaction.sink, "-"),
+        };
+        let transaction_type = match counterparty {
+            Node::Atm(_) => "ATM",
+            Node::Terminal(_) => "POS",
+            _ if owner.owns(&counterparty.id()) => "XFER",
+            _ if sign.is_empty() => "CREDIT",
+            _ => "DEBIT",
+        };
+        self.open("STMTTRN")?;
+        self.leaf("TRNTYPE", transaction_type)?;
+        self.leaf("DTPOSTED", transaction.date.format(DATE_FORMAT))?;
+        self.leaf("TRNAMT", format!("{}{}", sign, transaction.amount))?;
+        self.leaf(
+            "FITID",
+            transaction
+                .reference
+                .clone()
+                .unwrap_or_else(|| transaction.id().to_string()),
+        )?;
+        self.leaf("NAME", truncate(&payee(counterparty), 32))?;
+        if let Node::ProperAccount(_) = counterparty {
+            self.account("BANKACCTTO", counterparty)?;
+        }
+        if !transaction.description.is_empty() {
+            self.leaf("MEMO", truncate(&transaction.description, 255))?;
+        }
+        self.close("STMTTRN")
+    }
+}
+
+/// `Node::name` is generic for terminals and ATMs, their ID says more.
+fn payee(node: &Node) -> String {
+    match node {
+        Node::Terminal(id) | Node::Atm(id) | Node::Other(id) => id.clone(),
+        Node::ProperAccount(acc) if acc.name.is_empty() => acc.iban.to_string(),
+        _ => node.name(),
+    }
+}
+
+/// OFX limits the length of most text fields.
+fn truncate(text: &str, length: usize) -> String {
+    text.chars().take(length).collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        canonical::fixtures::*,
+        from_files::{importer::BankImporter, report::ImportReport, serializers::ofx::Ofx},
+    };
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn reads_back_with_the_importer() {
+        let me = owner(vec![checking()]);
+        let transactions = Transactions::new(vec![
+            Transaction {
+                reference: Some(String::from("F1")),
+                description: String::from("Invoice 1 & 2"),
+                ..transaction(date(2024, 1, 2), &checking(), &shop(), dec!(12.50))
+            },
+            transaction(date(2024, 1, 3), &joint(), &checking(), dec!(50)),
+            transaction(
+                date(2024, 1, 4),
+                &checking(),
+                &Node::Terminal(String::from("CT123456")),
+                dec!(3.20),
+            ),
+            // Not the owner's: left out.
+            transaction(date(2024, 1, 5), &joint(), &shop(), dec!(1)),
+        ]);
+        let mut written = Vec::new();
+        write(&transactions, &me, &mut written).unwrap();
+
+        let contents = String::from_utf8(written).unwrap();
+        let directory = directory("ofx-export", &[("export.ofx", &contents)]);
+        let mut report = ImportReport::default();
+        let read = Ofx
+            .import(&directory.join("export.ofx"), &me, &mut report)
+            .unwrap();
+        assert_eq!(report.rejected().count(), 0);
+
+        let originals = &transactions.data()[..3];
+        let fitids: Vec<_> = read.iter().map(|t| t.reference.clone().unwrap()).collect();
+        assert_eq!(
+            fitids,
+            [
+                String::from("F1"),
+                originals[1].id().to_string(),
+                originals[2].id().to_string(),
+            ]
+        );
+        for (read, original) in read.iter().zip(originals) {
+            assert_eq!(read.date, original.date);
+            assert_eq!(read.amount, original.amount);
+            assert_eq!(read.direction(&checking()), original.direction(&checking()));
+        }
+        assert_eq!(read[0].sink.id(), shop().id());
+        assert_eq!(read[0].description, "Invoice 1 & 2");
+        assert_eq!(read[1].source.id(), joint().id());
+        assert_eq!(read[2].sink, Node::Terminal(String::from("CT123456")));
+    }
+}
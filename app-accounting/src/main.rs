@@ -1,84 +1,1333 @@
-use crate::{
-    analysis::summaries,
-    canonical::{identify::*, transaction::*},
-    from_files::import::{profile_from_path, transactions_from_path},
+use accounting_core::{
+    analysis::{
+        balances, recurring, summaries,
+        transfers::{self, Transfer},
+    },
+    canonical::{
+        category::Category,
+        identify::*,
+        query::{Ownership, Query},
+        state::Owner,
+        transaction::*,
+    },
+    from_files::{
+        import::{import_with_progress, profile_from_path},
+        importer::Registry,
+        report::ImportReport,
+    },
+    rules,
+    store::{transactions_from_store, Store},
+    to_files::{beancount, ledger, ofx},
+};
+use chrono::NaiveDate;
+use clap::{Args as ClapArgs, Parser, Subcommand, ValueEnum};
+use color_eyre::{eyre::eyre, Result};
+use itertools::Itertools;
+use output::{Format, Table};
+use regex::Regex;
+use rust_decimal::Decimal;
+use serde_json::Value;
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
 };
-use clap::Parser;
-use color_eyre::Result;
-use itertools::{self, Itertools};
 
-mod analysis;
-mod canonical;
-mod from_files;
+mod output;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 pub struct Args {
-    /// Path to a .csv file or a directory that contains at least one .csv file.
-    #[arg(short, long)]
-    pub csv_path: std::path::PathBuf,
+    /// Path to a bank export, or a directory that contains at least one, to read the
+    /// transactions from. When omitted, they are read from `--database` instead.
+    #[arg(short, long, global = true)]
+    pub csv_path: Option<PathBuf>,
     /// Path to a profile .json file.
-    #[arg(short, long)]
-    pub profile_path: std::path::PathBuf,
+    #[arg(short, long, global = true)]
+    pub profile_path: Option<PathBuf>,
+    /// Name of a profile previously stored in `--database`. Used when no
+    /// `--profile-path` is given.
+    #[arg(short, long, global = true)]
+    pub owner: Option<String>,
+    /// Path to a SQLite database. Imported transactions and the profile are
+    /// stored here, so later runs don't have to read the bank exports again.
+    #[arg(short, long, global = true)]
+    pub database: Option<PathBuf>,
+    /// How to print the result.
+    #[arg(short, long, global = true, value_enum, default_value_t = Format::Table)]
+    pub format: Format,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Read bank exports and report on every record. The transactions are stored
+    /// in `--database` when one is given.
+    Import {
+        /// A bank export, or a directory that contains at least one.
+        path: PathBuf,
+    },
+    /// List transactions.
+    List {
+        #[command(flatten)]
+        filter: Filter,
+        /// Print at most this many transactions.
+        #[arg(short, long)]
+        limit: Option<usize>,
+    },
+    /// Every node, with how often it occurs and the money to and from it.
+    Nodes {
+        #[command(flatten)]
+        filter: Filter,
+    },
+    /// Income, expenses, internal transfers and balance chain breaks.
+    Summary {
+        #[command(flatten)]
+        filter: Filter,
+    },
+    /// Income, expenses and net per month, week or year, without internal transfers.
+    CashFlow {
+        #[arg(long, value_enum, default_value_t = PeriodArg::Month)]
+        period: PeriodArg,
+        /// A row per counterparty or per category in every period.
+        #[arg(long, value_enum)]
+        by: Option<BreakdownArg>,
+        #[command(flatten)]
+        filter: Filter,
+    },
+    /// The balance of every owned node and the net worth at the end of every day, from
+    /// the balances the bank reported and the profile's `opening_balances`.
+    Balances {
+        /// Only the balance at the end of every week, month or year.
+        #[arg(long, value_enum)]
+        period: Option<PeriodArg>,
+        /// Only the net worth, not the balance of every node.
+        #[arg(long)]
+        net_worth: bool,
+        /// Start at this date, e.g.: 2024-01-01. All transactions still count.
+        #[arg(long)]
+        from: Option<NaiveDate>,
+        /// End at this date.
+        #[arg(long)]
+        to: Option<NaiveDate>,
+    },
+    /// Payments and income that come back every week, month, quarter or year, with the
+    /// next expected date and amount, price changes and missed occurrences.
+    Recurring {
+        #[command(flatten)]
+        filter: Filter,
+    },
+    /// Every tag and how often it occurs, or the transactions with one tag.
+    Tag {
+        /// List the transactions with this tag, e.g.: `#boodschappen`.
+        tag: Option<String>,
+        #[command(flatten)]
+        filter: Filter,
+    },
+    /// Show the profile's nodes.
+    Profile {
+        /// Store the profile in `--database`.
+        #[arg(long)]
+        save: bool,
+    },
+    /// Show the profile's saved queries, for use with `--query`.
+    Queries,
+    /// The profile's tagging rules and how many transactions each tags. With `--trace`,
+    /// every tag a rule added instead, and which rule that was.
+    Rules {
+        #[arg(long)]
+        trace: bool,
+        #[command(flatten)]
+        filter: Filter,
+    },
+    /// The category tree with the money in every category, subcategories included.
+    Categories {
+        #[command(flatten)]
+        filter: Filter,
+    },
+    /// Put a single transaction in a category, whatever the rules say, and store the
+    /// profile in `--database`.
+    Categorize {
+        transaction: ID<Transaction>,
+        /// E.g.: "Food > Groceries". Leave out to let the rules decide again.
+        category: Option<Category>,
+    },
+    /// Write transactions to a statement or journal file, for other tools.
+    Export {
+        #[arg(value_enum)]
+        kind: ExportKind,
+        /// File to write to. Standard output when omitted.
+        path: Option<PathBuf>,
+        #[command(flatten)]
+        filter: Filter,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum ExportKind {
+    /// OFX statements of the profile's accounts.
+    Ofx,
+    /// A Beancount journal.
+    Beancount,
+    /// A ledger/hledger journal.
+    Ledger,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum PeriodArg {
+    /// Weeks from Monday to Sunday.
+    Week,
+    Month,
+    Year,
+}
+
+impl From<PeriodArg> for summaries::Period {
+    fn from(period: PeriodArg) -> Self {
+        match period {
+            PeriodArg::Week => Self::Week,
+            PeriodArg::Month => Self::Month,
+            PeriodArg::Year => Self::Year,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum BreakdownArg {
+    /// The nodes the owner received money from or paid.
+    Node,
+    /// The categories, with the money of subcategories added up.
+    Category,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum DirectionArg {
+    /// Money going to `--node`.
+    In,
+    /// Money leaving `--node`.
+    Out,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum OwnershipArg {
+    /// At least one of the nodes is owned.
+    Any,
+    /// Both nodes are owned: transfers between own accounts.
+    Internal,
+    /// Exactly one of the nodes is owned: income and expenses.
+    External,
+}
+
+/// Which transactions a command works on.
+#[derive(ClapArgs, Debug)]
+pub struct Filter {
+    /// Start from the profile's saved query with this name. The other options
+    /// replace its conditions, `--tag`s add to them.
+    #[arg(long)]
+    pub query: Option<String>,
+    /// First date to include, e.g.: 2024-01-01.
+    #[arg(long)]
+    pub from: Option<NaiveDate>,
+    /// Last date to include.
+    #[arg(long)]
+    pub to: Option<NaiveDate>,
+    /// Smallest amount to include.
+    #[arg(long)]
+    pub min_amount: Option<Decimal>,
+    /// Largest amount to include.
+    #[arg(long)]
+    pub max_amount: Option<Decimal>,
+    /// Only transactions from or to the node with this ID.
+    #[arg(long)]
+    pub node: Option<ID<Node>>,
+    /// Only money going to or leaving `--node`.
+    #[arg(long, value_enum, requires = "node")]
+    pub direction: Option<DirectionArg>,
+    /// Only transactions of which the profile owns these nodes.
+    #[arg(long, value_enum)]
+    pub ownership: Option<OwnershipArg>,
+    /// Only transactions with this tag, e.g.: `#boodschappen`. Repeat for more.
+    #[arg(long = "tag")]
+    pub tags: Vec<String>,
+    /// Only transactions with this bank transaction code, e.g.: `GT`.
+    #[arg(long)]
+    pub code: Option<String>,
+    /// Only transactions in this category or one of its subcategories.
+    #[arg(long)]
+    pub category: Option<Category>,
+    /// Only transactions whose description matches this regular expression.
+    #[arg(long)]
+    pub description: Option<Regex>,
+    /// Only transactions whose description contains this text, ignoring case.
+    #[arg(long, conflicts_with = "description")]
+    pub search: Option<String>,
+}
+impl Filter {
+    fn query(&self, owner: &Owner) -> Result<Query> {
+        let mut query = match &self.query {
+            Some(name) => owner
+                .queries
+                .get(name)
+                .cloned()
+                .ok_or_else(|| eyre!("The profile has no query named {:?}", name))?,
+            None => Query::default(),
+        };
+        if let Some(from) = self.from {
+            query = query.from(from);
+        }
+        if let Some(to) = self.to {
+            query = query.to(to);
+        }
+        if let Some(amount) = self.min_amount {
+            query = query.min_amount(amount);
+        }
+        if let Some(amount) = self.max_amount {
+            query = query.max_amount(amount);
+        }
+        if let Some(node) = self.node {
+            query = match self.direction {
+                Some(DirectionArg::In) => query.direction(Direction::Incoming, node),
+                Some(DirectionArg::Out) => query.direction(Direction::Outgoing, node),
+                None => query.node(node),
+            };
+        }
+        if let Some(ownership) = self.ownership {
+            query = query.ownership(match ownership {
+                OwnershipArg::Any => Ownership::Any,
+                OwnershipArg::Internal => Ownership::Internal,
+                OwnershipArg::External => Ownership::External,
+            });
+        }
+        for tag in &self.tags {
+            query = query.tag(format!("#{}", tag.trim_start_matches('#')));
+        }
+        if let Some(code) = &self.code {
+            query = query.code(code);
+        }
+        if let Some(category) = &self.category {
+            query = query.category(category.clone());
+        }
+        if let Some(search) = &self.search {
+            query = query.description(Regex::new(&format!("(?i){}", regex::escape(search)))?);
+        }
+        if let Some(pattern) = &self.description {
+            query = query.description(pattern.clone());
+        }
+        Ok(query)
+    }
 }
 
 fn main() -> Result<()> {
     color_eyre::install()?;
-
     let args = Args::parse();
-    println!("{:?}", args);
-
-    let me = profile_from_path(&args.profile_path);
-    if let Ok(transactions) = transactions_from_path(&args.csv_path, &me) {
-        println!("Accessing first 9 elements:");
-        for line in &transactions.data()[..9] {
-            print_csv_line(line, me.owns.first().unwrap());
-        }
-
-        let _filtered_data =
-            transactions.filter(|t| me.owns(&t.source.id()) || me.owns(&t.sink.id()));
-        let node_freq = summaries::node_frequencies(&transactions);
-        let node_freq: Vec<_> = node_freq.iter().sorted_by(|a, b| b.1.cmp(a.1)).collect();
-
-        for (id, count) in node_freq.iter() {
-            println!(
-                "{:?}: {count}",
-                match me.view(id) {
-                    Some(owned_node) => owned_node.to_string(),
-                    None => id.to_string(),
-                }
+
+    let mut store = match &args.database {
+        Some(path) => {
+            let store = Store::open(path)?;
+            eprintln!(
+                "Using database {:?} (schema version {})",
+                path,
+                store.schema_version()?
             );
+            Some(store)
+        }
+        None => None,
+    };
+
+    let mut me = match (&args.profile_path, &args.owner, &store) {
+        (Some(path), _, _) => profile_from_path(path)?,
+        (None, Some(name), Some(store)) => store
+            .load_owner(name)?
+            .ok_or_else(|| eyre!("No profile named {:?} in the database", name))?,
+        _ => {
+            return Err(eyre!(
+                "Expecting a --profile-path, or an --owner and a --database"
+            ))
         }
+    };
 
-        println!(
-            "Date range: {:?}",
-            [transactions.data().first(), transactions.data().last()].map(|t| t.unwrap().date)
+    for category in me.undefined_categories() {
+        eprintln!(
+            "Category {:?} isn't in the profile's categories",
+            category.to_string()
         );
+    }
 
-        return Ok(());
+    let table = match &args.command {
+        Command::Import { path } => {
+            let (transactions, report) = import(path, &me)?;
+            let stored = match &mut store {
+                Some(store) => {
+                    store.save_owner(&me)?;
+                    Value::from(store.save_transactions(transactions.iter())?)
+                }
+                None => Value::Null,
+            };
+            let mut table = Table::new(&[
+                "accepted",
+                "skipped",
+                "rejected",
+                "flagged",
+                "merged",
+                "transactions",
+                "stored",
+            ]);
+            table.row([
+                Value::from(report.accepted().count()),
+                Value::from(report.skipped().count()),
+                Value::from(report.rejected().count()),
+                Value::from(report.flagged().count()),
+                Value::from(report.merges.len()),
+                Value::from(transactions.len()),
+                stored,
+            ]);
+            table
+        }
+        Command::Profile { save } => {
+            if *save {
+                let store = store
+                    .as_mut()
+                    .ok_or_else(|| eyre!("Expecting a --database to save the profile in"))?;
+                store.save_owner(&me)?;
+                eprintln!("Saved profile {:?}", me.name);
+            }
+            let mut table = Table::new(&["owner", "id", "name", "kind", "account_name"]);
+            for node in &me.owns {
+                table.row([
+                    Value::from(me.name.clone()),
+                    Value::from(node.id().to_string()),
+                    Value::from(node.name()),
+                    Value::from(kind(node)),
+                    me.account_names
+                        .get(&node.id())
+                        .map_or(Value::Null, |name| Value::from(name.clone())),
+                ]);
+            }
+            table
+        }
+        Command::Queries => {
+            let mut table = Table::new(&["name", "query"]);
+            for (name, query) in &me.queries {
+                table.row([Value::from(name.clone()), serde_json::to_value(query)?]);
+            }
+            table
+        }
+        Command::Rules { trace, filter } => {
+            let transactions = load_transactions(&args, &store, &me)?;
+            let selected = transactions.query(&filter.query(&me)?, &me);
+            if *trace {
+                rule_trace(&selected)
+            } else {
+                rule_counts(&selected, &me)?
+            }
+        }
+        Command::Categories { filter } => {
+            let transactions = load_transactions(&args, &store, &me)?;
+            categories(&transactions.query(&filter.query(&me)?, &me), &me)
+        }
+        Command::Categorize {
+            transaction,
+            category,
+        } => {
+            let store = store
+                .as_mut()
+                .ok_or_else(|| eyre!("Expecting a --database to save the profile in"))?;
+            match category {
+                Some(category) => me.categorized.insert(*transaction, category.clone()),
+                None => me.categorized.remove(transaction),
+            };
+            store.save_owner(&me)?;
+            eprintln!("Saved profile {:?}", me.name);
+            let mut table = Table::new(&["transaction", "category"]);
+            table.row([
+                Value::from(transaction.to_string()),
+                category
+                    .as_ref()
+                    .map_or(Value::Null, |category| Value::from(category.to_string())),
+            ]);
+            table
+        }
+        Command::List { filter, limit } => {
+            let transactions = load_transactions(&args, &store, &me)?;
+            let selected = transactions.query(&filter.query(&me)?, &me);
+            list(selected.iter().take(limit.unwrap_or(usize::MAX)), &me)
+        }
+        Command::Nodes { filter } => {
+            let transactions = load_transactions(&args, &store, &me)?;
+            nodes(&transactions.query(&filter.query(&me)?, &me), &me)
+        }
+        Command::Summary { filter } => {
+            let transactions = load_transactions(&args, &store, &me)?;
+            summary(&transactions.query(&filter.query(&me)?, &me), &me)
+        }
+        Command::CashFlow { period, by, filter } => {
+            let transactions = load_transactions(&args, &store, &me)?;
+            let selection = transactions.query(&filter.query(&me)?, &me);
+            cash_flow(&selection, &me, (*period).into(), *by)
+        }
+        Command::Recurring { filter } => {
+            let transactions = load_transactions(&args, &store, &me)?;
+            recurring(&transactions.query(&filter.query(&me)?, &me), &me)
+        }
+        Command::Balances {
+            period,
+            net_worth,
+            from,
+            to,
+        } => {
+            let transactions = load_transactions(&args, &store, &me)?;
+            let timeline = balances::timeline(&transactions, &me);
+            for node in &me.owns {
+                if timeline
+                    .nodes
+                    .get(&node.id())
+                    .is_some_and(|series| !series.anchored)
+                {
+                    eprintln!(
+                        "No known balance of {:?}, taking it to be empty before its first transaction",
+                        node_name(node, &me)
+                    );
+                }
+            }
+            daily_balances(
+                &timeline,
+                &me,
+                period.map(Into::into),
+                *net_worth,
+                *from,
+                *to,
+            )
+        }
+        Command::Tag { tag, filter } => {
+            let transactions = load_transactions(&args, &store, &me)?;
+            let selected = transactions.query(&filter.query(&me)?, &me);
+            match tag {
+                Some(tag) => {
+                    let tag = format!("#{}", tag.trim_start_matches('#'));
+                    list(selected.iter().filter(|t| t.tags().contains(&tag)), &me)
+                }
+                None => tags(&selected),
+            }
+        }
+        Command::Export { kind, path, filter } => {
+            let transactions = load_transactions(&args, &store, &me)?;
+            let selected = transactions.query(&filter.query(&me)?, &me);
+            let Some(path) = path else {
+                // The export is the output.
+                return Ok(export(*kind, &selected, &me, io::stdout().lock())?);
+            };
+            export(*kind, &selected, &me, File::create(path)?)?;
+            let mut table = Table::new(&["kind", "path", "transactions"]);
+            table.row([
+                Value::from(format!("{:?}", kind).to_lowercase()),
+                Value::from(path.display().to_string()),
+                Value::from(selected.len()),
+            ]);
+            table
+        }
+    };
+    table.write(args.format, io::stdout().lock())?;
+    Ok(())
+}
+
+/// The transactions in `--csv-path` when given, the ones in `--database` otherwise.
+fn load_transactions<'a>(
+    args: &Args,
+    store: &Option<Store>,
+    owner: &Owner,
+) -> Result<Transactions<'a>> {
+    match (&args.csv_path, store) {
+        (Some(path), _) => Ok(import(path, owner)?.0),
+        (None, Some(store)) => {
+            // Rule tags aren't stored: the rules may have changed since.
+            let mut transactions = transactions_from_store(store)?;
+            rules::apply(&mut transactions, owner);
+            Ok(transactions)
+        }
+        (None, None) => Err(eyre!("Expecting a --csv-path and/or a --database")),
+    }
+}
+
+/// Import the bank exports at `path`, reporting progress and problems on standard error.
+fn import<'a>(path: &Path, owner: &Owner) -> Result<(Transactions<'a>, ImportReport)> {
+    let (transactions, report) =
+        import_with_progress(path, owner, &Registry::default(), |progress| {
+            eprintln!("> {:?} ({})", progress.path, progress.importer)
+        })?;
+    eprint!("{}", report);
+    Ok((transactions, report))
+}
+
+fn export<'a, P: View<'a>, W: Write>(
+    kind: ExportKind,
+    transactions: &'a Transactions<'a, P>,
+    owner: &Owner,
+    out: W,
+) -> io::Result<()> {
+    let out = BufWriter::new(out);
+    match kind {
+        ExportKind::Ofx => ofx::write(transactions, owner, out),
+        ExportKind::Beancount => beancount::write(transactions, owner, out),
+        ExportKind::Ledger => ledger::write(transactions, owner, out),
+    }
+}
+
+fn list<'t>(transactions: impl Iterator<Item = &'t Transaction>, owner: &Owner) -> Table {
+    let mut table = Table::new(&[
+        "id",
+        "date",
+        "amount",
+        "direction",
+        "source",
+        "sink",
+        "category",
+        "tags",
+        "description",
+    ]);
+    for transaction in transactions {
+        let source = owner.owns(&transaction.source.id());
+        let sink = owner.owns(&transaction.sink.id());
+        let (amount, direction) = match (source, sink) {
+            (true, true) => (transaction.amount, "internal"),
+            (false, true) => (transaction.amount, "in"),
+            (true, false) => (-transaction.amount, "out"),
+            (false, false) => (transaction.amount, ""),
+        };
+        table.row([
+            transaction.id().to_string(),
+            transaction.date.to_string(),
+            amount.to_string(),
+            direction.to_string(),
+            node_name(&transaction.source, owner),
+            node_name(&transaction.sink, owner),
+            transaction
+                .category
+                .as_ref()
+                .map(Category::to_string)
+                .unwrap_or_default(),
+            transaction.tags().into_iter().sorted().join(" "),
+            transaction.description.clone(),
+        ]);
+    }
+    table
+}
+
+fn nodes<'a, P: View<'a>>(transactions: &'a Transactions<'a, P>, owner: &Owner) -> Table {
+    let frequencies = summaries::node_frequencies(transactions);
+    let flows = summaries::cash_flow_per_node(transactions, owner);
+    let nodes = transactions
+        .iter()
+        .flat_map(|transaction| [&transaction.source, &transaction.sink])
+        .unique_by(|node| node.id())
+        .sorted_by(|a, b| {
+            let count = |node: &Node| frequencies.get(&node.id()).copied().unwrap_or_default();
+            count(b)
+                .cmp(&count(a))
+                .then_with(|| a.id().to_string().cmp(&b.id().to_string()))
+        });
+
+    let mut table = Table::new(&[
+        "id",
+        "name",
+        "kind",
+        "owned",
+        "transactions",
+        "incoming",
+        "outgoing",
+    ]);
+    for node in nodes {
+        let flow = flows.get(&node.id());
+        table.row([
+            Value::from(node.id().to_string()),
+            Value::from(node_name(node, owner)),
+            Value::from(kind(node)),
+            Value::from(owner.owns(&node.id())),
+            Value::from(frequencies.get(&node.id()).copied().unwrap_or_default()),
+            flow.map_or(Value::Null, |flow| Value::from(flow.incoming.to_string())),
+            flow.map_or(Value::Null, |flow| Value::from(flow.outgoing.to_string())),
+        ]);
+    }
+    table
+}
+
+fn summary<'a, P: View<'a>>(transactions: &'a Transactions<'a, P>, owner: &Owner) -> Table {
+    let totals = summaries::income_and_expenses(transactions, owner);
+    let matched = transfers::match_transfers(transactions, owner, transfers::WINDOW);
+    let breaks = balances::check_chain(transactions);
+    for chain_break in &breaks {
+        eprintln!("Balance chain break: {}", chain_break);
+    }
+
+    let mut table = Table::new(&[
+        "from",
+        "to",
+        "transactions",
+        "income",
+        "expenses",
+        "net",
+        "internal_transfers",
+        "unmatched_transfer_legs",
+        "balance_breaks",
+    ]);
+    table.row([
+        transactions
+            .iter()
+            .next()
+            .map_or(Value::Null, |t| Value::from(t.date.to_string())),
+        transactions
+            .iter()
+            .next_back()
+            .map_or(Value::Null, |t| Value::from(t.date.to_string())),
+        Value::from(transactions.len()),
+        Value::from(totals.income.to_string()),
+        Value::from(totals.expenses.to_string()),
+        Value::from((totals.income - totals.expenses).to_string()),
+        Value::from(matched.len()),
+        Value::from(unmatched_legs(transactions, &matched, owner)),
+        Value::from(breaks.len()),
+    ]);
+    table
+}
+
+/// The internal transactions that aren't a leg of one of `matched`: the export of the
+/// other account wasn't imported, or it booked the transfer more than
+/// `transfers::WINDOW` later.
+fn unmatched_legs<'t, I>(transactions: I, matched: &[Transfer], owner: &Owner) -> usize
+where
+    I: IntoIterator<Item = &'t Transaction>,
+{
+    let legs: HashSet<*const Transaction> = matched
+        .iter()
+        .flat_map(|transfer| [transfer.sent as *const _, transfer.received as *const _])
+        .collect();
+    transactions
+        .into_iter()
+        .filter(|t| owner.is_internal(t) && !legs.contains(&(*t as *const _)))
+        .count()
+}
+
+fn categories<'a, P: View<'a>>(transactions: &'a Transactions<'a, P>, owner: &Owner) -> Table {
+    let mut table = Table::new(&[
+        "category",
+        "incoming",
+        "outgoing",
+        "total_incoming",
+        "total_outgoing",
+        "total_net",
+    ]);
+    for (category, flow) in summaries::cash_flow_per_category(transactions, owner) {
+        // Indented by depth, so the table shows the tree.
+        let name = match &category {
+            Some(category) => format!("{}{}", "  ".repeat(category.depth()), category.name()),
+            None => String::from("(none)"),
+        };
+        table.row([
+            Value::from(name),
+            Value::from(flow.own.incoming.to_string()),
+            Value::from(flow.own.outgoing.to_string()),
+            Value::from(flow.total.incoming.to_string()),
+            Value::from(flow.total.outgoing.to_string()),
+            Value::from(flow.total.net().to_string()),
+        ]);
     }
-    panic!("Couldn't parse transactions successfully.");
+    table
 }
 
-fn print_csv_line(line: &Transaction, perspective: &Node) {
-    println!("\n+==================+");
-    println!(
-        "| {} | ({})",
-        line.id(),
-        match line.direction(perspective) {
-            Some(direction) => match direction {
-                Direction::Incoming => "+",
-                Direction::Outgoing => "-",
+fn cash_flow<'a, P: View<'a>>(
+    transactions: &'a Transactions<'a, P>,
+    owner: &Owner,
+    period: summaries::Period,
+    by: Option<BreakdownArg>,
+) -> Table {
+    let mut columns = vec!["from", "to"];
+    match by {
+        Some(BreakdownArg::Node) => columns.push("node"),
+        Some(BreakdownArg::Category) => columns.push("category"),
+        None => {}
+    }
+    columns.extend(["incoming", "outgoing", "net"]);
+    let mut table = Table::new(&columns);
+    let nodes: HashMap<ID<Node>, &Node> = transactions
+        .iter()
+        .flat_map(|t| [&t.source, &t.sink])
+        .map(|node| (node.id(), node))
+        .collect();
+    for (start, report) in summaries::cash_flow_per_period(transactions, owner, period) {
+        let mut row = |name: Option<String>, flow: &summaries::Flow| {
+            let dates = [start, period.end(start)].map(|date| Value::from(date.to_string()));
+            let amounts = [flow.incoming, flow.outgoing, flow.net()]
+                .map(|amount| Value::from(amount.to_string()));
+            table.row(
+                dates
+                    .into_iter()
+                    .chain(name.map(Value::from))
+                    .chain(amounts),
+            );
+        };
+        match by {
+            None => row(None, &report.total),
+            Some(BreakdownArg::Node) => {
+                // Most money first.
+                let flows = report.nodes.iter().sorted_by_key(|(id, flow)| {
+                    (
+                        std::cmp::Reverse(flow.incoming + flow.outgoing),
+                        id.to_string(),
+                    )
+                });
+                for (id, flow) in flows {
+                    let name = nodes
+                        .get(id)
+                        .map_or_else(String::new, |node| node_name(node, owner));
+                    row(Some(name), flow);
+                }
+            }
+            Some(BreakdownArg::Category) => {
+                for (category, flow) in &report.categories {
+                    let name = category
+                        .as_ref()
+                        .map_or("(none)".to_string(), Category::to_string);
+                    row(Some(name), &flow.total);
+                }
+            }
+        }
+    }
+    table
+}
+
+/// The balances of `timeline` at the end of every day from `from` to `to`, or only of
+/// the last day of every `period` (and the last day with a balance).
+fn daily_balances(
+    timeline: &balances::Timeline,
+    owner: &Owner,
+    period: Option<summaries::Period>,
+    net_worth: bool,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Table {
+    let shown = |day: &NaiveDate| {
+        from.is_none_or(|from| *day >= from)
+            && to.is_none_or(|to| *day <= to)
+            && period.is_none_or(|period| {
+                period.end(*day) == *day || Some(day) == timeline.net_worth.keys().next_back()
+            })
+    };
+    let mut table = Table::new(&["date", "node", "balance"]);
+    for (day, total) in timeline.net_worth.iter().filter(|(day, _)| shown(day)) {
+        if !net_worth {
+            for node in &owner.owns {
+                let balance = timeline.nodes[&node.id()].balances[day];
+                table.row([
+                    Value::from(day.to_string()),
+                    Value::from(node_name(node, owner)),
+                    Value::from(balance.to_string()),
+                ]);
+            }
+        }
+        table.row([
+            Value::from(day.to_string()),
+            Value::from("net worth"),
+            Value::from(total.to_string()),
+        ]);
+    }
+    table
+}
+
+fn recurring<'a, P: View<'a>>(transactions: &'a Transactions<'a, P>, owner: &Owner) -> Table {
+    let mut table = Table::new(&[
+        "counterparty",
+        "direction",
+        "interval",
+        "occurrences",
+        "extra",
+        "first",
+        "last",
+        "ended",
+        "next_date",
+        "next_amount",
+        "price_changes",
+        "missed",
+        "mandate",
+    ]);
+    for series in recurring::detect(transactions, owner) {
+        let price_changes = series
+            .price_changes
+            .iter()
+            .map(|change| format!("{}: {} -> {}", change.date, change.from, change.to))
+            .join(", ");
+        table.row([
+            Value::from(node_name(series.counterparty, owner)),
+            Value::from(match series.direction {
+                Direction::Incoming => "in",
+                Direction::Outgoing => "out",
+            }),
+            Value::from(format!("{:?}", series.interval).to_lowercase()),
+            Value::from(series.occurrences.len()),
+            Value::from(series.extra.len()),
+            Value::from(series.occurrences[0].date.to_string()),
+            Value::from(series.occurrences.last().unwrap().date.to_string()),
+            Value::from(series.ended),
+            series
+                .next_date
+                .map_or(Value::Null, |date| Value::from(date.to_string())),
+            Value::from(series.next_amount.to_string()),
+            Value::from(price_changes),
+            Value::from(series.missed.iter().join(", ")),
+            series.mandate.map_or(Value::Null, Value::from),
+        ]);
+    }
+    table
+}
+
+fn tags<'a, P: View<'a>>(transactions: &'a Transactions<'a, P>) -> Table {
+    let counts = transactions
+        .iter()
+        .flat_map(|transaction| transaction.tags())
+        .counts();
+    let mut table = Table::new(&["tag", "transactions"]);
+    for (tag, count) in counts
+        .into_iter()
+        .sorted_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)))
+    {
+        table.row([Value::from(tag), Value::from(count)]);
+    }
+    table
+}
+
+fn rule_counts<'a, P: View<'a>>(
+    transactions: &'a Transactions<'a, P>,
+    owner: &Owner,
+) -> Result<Table> {
+    let mut table = Table::new(&["name", "tags", "when", "transactions"]);
+    for rule in &owner.rules {
+        let matches = transactions
+            .iter()
+            .filter(|t| rule.matches(t, owner))
+            .count();
+        table.row([
+            Value::from(rule.name.clone()),
+            Value::from(rule.tags.join(" ")),
+            serde_json::to_value(&rule.when)?,
+            Value::from(matches),
+        ]);
+    }
+    Ok(table)
+}
+
+fn rule_trace<'a, P: View<'a>>(transactions: &'a Transactions<'a, P>) -> Table {
+    let mut table = Table::new(&["id", "date", "tag", "rule", "description"]);
+    for transaction in transactions {
+        for (tag, rule) in &transaction.rule_tags {
+            table.row([
+                transaction.id().to_string(),
+                transaction.date.to_string(),
+                tag.clone(),
+                rule.clone(),
+                transaction.description.clone(),
+            ]);
+        }
+    }
+    table
+}
+
+/// The profile's name for owned nodes. Terminals and ATMs are told apart by their ID.
+fn node_name(node: &Node, owner: &Owner) -> String {
+    match (owner.name_of(&node.id()), node) {
+        (Some(name), _) => name,
+        (None, Node::Terminal(id) | Node::Atm(id)) => format!("{} {}", node.name(), id),
+        (None, _) => node.name(),
+    }
+}
+
+fn kind(node: &Node) -> &'static str {
+    match node {
+        Node::ProperAccount(_) => "account",
+        Node::SubAccount(_) => "sub-account",
+        Node::Terminal(_) => "terminal",
+        Node::Atm(_) => "atm",
+        Node::Other(_) => "other",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use accounting_core::canonical::{
+        account::Account,
+        rule::{Conditions, Rule},
+    };
+    use iban::Iban;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+    use std::collections::{BTreeMap, HashMap};
+
+    fn account(iban: &str, name: &str) -> Node {
+        Node::ProperAccount(Account {
+            iban: Iban::parse(iban).unwrap(),
+            name: String::from(name),
+        })
+    }
+
+    fn checking() -> Node {
+        account("NL91ABNA0417164300", "Checking")
+    }
+
+    fn joint() -> Node {
+        account("NL02ABNA0123456789", "Joint")
+    }
+
+    fn shop() -> Node {
+        account("NL44RABO0123456789", "Shop")
+    }
+
+    fn me() -> Owner {
+        Owner {
+            name: String::from("Me"),
+            owns: vec![checking(), joint()],
+            account_names: HashMap::new(),
+            queries: BTreeMap::from([(
+                String::from("groceries"),
+                Query::default().tag("#boodschappen").max_amount(dec!(100)),
+            )]),
+            rules: vec![Rule {
+                name: String::from("Shop"),
+                tags: vec![String::from("#shop")],
+                category: "Shopping".parse().ok(),
+                when: Conditions {
+                    counterparty: Some(shop().id()),
+                    ..Default::default()
+                },
+            }],
+            categories: vec!["Shopping > Food".parse().unwrap()],
+            categorized: HashMap::new(),
+            opening_balances: Vec::new(),
+        }
+    }
+
+    /// On `day` of January 2024, with `description`.
+    fn transaction(
+        day: u32,
+        source: &Node,
+        sink: &Node,
+        amount: Decimal,
+        description: &str,
+    ) -> Transaction {
+        Transaction {
+            date: NaiveDate::from_ymd_opt(2024, 1, day).unwrap(),
+            source: source.clone(),
+            sink: sink.clone(),
+            amount,
+            inherent_tags: String::new(),
+            description: String::from(description),
+            sequence: None,
+            reference: None,
+            end_to_end: None,
+            mandate: None,
+            balance: None,
+            code: None,
+            occurrence: 0,
+            rule_tags: BTreeMap::new(),
+            category: None,
+        }
+    }
+
+    /// The rows of `table` as JSON objects.
+    fn rows(table: &Table) -> Vec<Value> {
+        let mut out = Vec::new();
+        table.write(Format::Json, &mut out).unwrap();
+        serde_json::from_slice(&out).unwrap()
+    }
+
+    #[derive(Parser)]
+    struct FilterArgs {
+        #[command(flatten)]
+        filter: Filter,
+    }
+
+    fn filter(args: &[&str]) -> Filter {
+        FilterArgs::parse_from([&["test"], args].concat()).filter
+    }
+
+    fn matches(args: &[&str], transaction: &Transaction) -> bool {
+        let query = filter(args).query(&me()).unwrap();
+        query.matches(transaction, &me())
+    }
+
+    #[test]
+    fn filter_matches_every_field() {
+        let payment = Transaction {
+            inherent_tags: String::from("#boodschappen"),
+            code: Some(String::from("BA")),
+            ..transaction(10, &checking(), &shop(), dec!(25), "Weekly Groceries")
+        };
+        let shop = shop().id().to_string();
+        assert!(matches(&[], &payment));
+        assert!(matches(
+            &["--from", "2024-01-10", "--to", "2024-01-10"],
+            &payment
+        ));
+        assert!(!matches(&["--from", "2024-01-11"], &payment));
+        assert!(!matches(&["--to", "2024-01-09"], &payment));
+        assert!(matches(
+            &["--min-amount", "25", "--max-amount", "25"],
+            &payment
+        ));
+        assert!(!matches(&["--min-amount", "26"], &payment));
+        assert!(matches(&["--node", &shop, "--direction", "in"], &payment));
+        assert!(!matches(&["--node", &shop, "--direction", "out"], &payment));
+        assert!(!matches(&["--node", &joint().id().to_string()], &payment));
+        assert!(matches(&["--ownership", "external"], &payment));
+        assert!(!matches(&["--ownership", "internal"], &payment));
+        assert!(matches(&["--tag", "boodschappen"], &payment));
+        assert!(!matches(&["--tag", "#huur"], &payment));
+        assert!(matches(&["--code", "ba"], &payment));
+        assert!(!matches(&["--code", "GT"], &payment));
+        assert!(matches(&["--description", "^Weekly"], &payment));
+        assert!(matches(&["--search", "groceries"], &payment));
+        assert!(!matches(&["--search", "rent"], &payment));
+    }
+
+    #[test]
+    fn options_refine_a_saved_query() {
+        let query = filter(&[
+            "--query",
+            "groceries",
+            "--max-amount",
+            "50",
+            "--tag",
+            "markt",
+        ])
+        .query(&me())
+        .unwrap();
+        assert_eq!(query.tags, ["#boodschappen", "#markt"]);
+        assert_eq!(query.max_amount, Some(dec!(50)));
+        assert!(filter(&["--query", "rent"]).query(&me()).is_err());
+    }
+
+    #[test]
+    fn summary_counts_unmatched_transfer_legs() {
+        let transactions = Transactions::new(vec![
+            transaction(2, &checking(), &shop(), dec!(25), "Groceries"),
+            transaction(3, &shop(), &checking(), dec!(5), "Refund"),
+            // Both legs of a transfer, and one whose other leg wasn't imported.
+            transaction(4, &checking(), &joint(), dec!(100), "To joint"),
+            transaction(5, &checking(), &joint(), dec!(100), "From checking"),
+            transaction(20, &joint(), &checking(), dec!(40), "Back"),
+        ]);
+        let summary = &rows(&summary(&transactions, &me()))[0];
+        assert_eq!(summary["transactions"], 5);
+        assert_eq!(summary["income"], "5");
+        assert_eq!(summary["expenses"], "25");
+        assert_eq!(summary["internal_transfers"], 1);
+        assert_eq!(summary["unmatched_transfer_legs"], 1);
+    }
+
+    #[test]
+    fn lists_amounts_from_the_owners_side() {
+        let transactions = [
+            transaction(2, &checking(), &shop(), dec!(25), ""),
+            transaction(3, &shop(), &checking(), dec!(5), ""),
+            transaction(4, &checking(), &joint(), dec!(100), ""),
+            transaction(5, &shop(), &shop(), dec!(1), ""),
+        ];
+        let listed: Vec<(Value, Value)> = rows(&list(transactions.iter(), &me()))
+            .into_iter()
+            .map(|row| (row["amount"].clone(), row["direction"].clone()))
+            .collect();
+        assert_eq!(
+            listed,
+            [
+                ("-25".into(), "out".into()),
+                ("5".into(), "in".into()),
+                ("100".into(), "internal".into()),
+                ("1".into(), "".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn terminals_are_named_with_their_id() {
+        let terminal = Node::Terminal(String::from("AB1234"));
+        assert_eq!(node_name(&terminal, &me()), "Payment Terminal AB1234");
+        assert_eq!(node_name(&shop(), &me()), "Shop");
+    }
+
+    #[test]
+    fn rules_count_and_trace_their_tags() {
+        let mut transactions = Transactions::new(vec![
+            transaction(2, &checking(), &shop(), dec!(25), "Groceries"),
+            transaction(3, &shop(), &checking(), dec!(5), "Refund"),
+            transaction(4, &checking(), &joint(), dec!(100), "To joint"),
+        ]);
+        rules::apply(&mut transactions, &me());
+        let counts = rows(&rule_counts(&transactions, &me()).unwrap());
+        assert_eq!(counts[0]["name"], "Shop");
+        assert_eq!(counts[0]["when"]["counterparty"], shop().id().to_string());
+        assert_eq!(counts[0]["transactions"], 2);
+        let trace: Vec<Value> = rows(&rule_trace(&transactions))
+            .into_iter()
+            .map(|row| row["description"].clone())
+            .collect();
+        assert_eq!(trace, ["Groceries", "Refund"]);
+    }
+
+    #[test]
+    fn categories_are_indented_by_depth() {
+        let mut transactions = Transactions::new(vec![
+            transaction(2, &checking(), &shop(), dec!(25), "Groceries"),
+            transaction(3, &shop(), &checking(), dec!(5), "Refund"),
+            transaction(
+                4,
+                &joint(),
+                &account("NL20INGB0001234567", "Gym"),
+                dec!(30),
+                "",
+            ),
+        ]);
+        rules::apply(&mut transactions, &me());
+        let listed: Vec<(Value, Value, Value)> = rows(&categories(&transactions, &me()))
+            .into_iter()
+            .map(|row| {
+                (
+                    row["category"].clone(),
+                    row["outgoing"].clone(),
+                    row["total_net"].clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            listed,
+            [
+                ("(none)".into(), "30".into(), "-30".into()),
+                ("Shopping".into(), "25".into(), "-20".into()),
+                ("  Food".into(), "0".into(), "0".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn cash_flow_has_a_row_per_period_and_breakdown() {
+        let gym = account("NL20INGB0001234567", "Gym");
+        let mut transactions = Transactions::new(vec![
+            transaction(2, &checking(), &shop(), dec!(25), "Groceries"),
+            transaction(3, &shop(), &checking(), dec!(5), "Refund"),
+            transaction(16, &joint(), &gym, dec!(40), ""),
+        ]);
+        rules::apply(&mut transactions, &me());
+        let listed = |period, by| -> Vec<Vec<Value>> {
+            rows(&cash_flow(&transactions, &me(), period, by))
+                .into_iter()
+                .map(|row| row.as_object().unwrap().values().cloned().collect())
+                .collect()
+        };
+        let row = |values: &[&str]| -> Vec<Value> { values.iter().map(|&v| v.into()).collect() };
+
+        assert_eq!(
+            listed(summaries::Period::Week, None),
+            [
+                row(&["2024-01-01", "2024-01-07", "5", "25", "-20"]),
+                row(&["2024-01-08", "2024-01-14", "0", "0", "0"]),
+                row(&["2024-01-15", "2024-01-21", "0", "40", "-40"]),
+            ]
+        );
+        assert_eq!(
+            listed(summaries::Period::Month, Some(BreakdownArg::Node)),
+            [
+                row(&["2024-01-01", "2024-01-31", "Gym", "0", "40", "-40"]),
+                row(&["2024-01-01", "2024-01-31", "Shop", "5", "25", "-20"]),
+            ]
+        );
+        assert_eq!(
+            listed(summaries::Period::Year, Some(BreakdownArg::Category)),
+            [
+                row(&["2024-01-01", "2024-12-31", "(none)", "0", "40", "-40"]),
+                row(&["2024-01-01", "2024-12-31", "Shopping", "5", "25", "-20"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn balances_show_the_end_of_every_period() {
+        let with_balance = |transaction: Transaction, amount| Transaction {
+            balance: Some(Balance {
+                of: checking().id(),
+                amount,
+            }),
+            ..transaction
+        };
+        let transactions = [
+            with_balance(
+                transaction(2, &checking(), &shop(), dec!(25), "Groceries"),
+                dec!(75),
+            ),
+            Transaction {
+                date: NaiveDate::from_ymd_opt(2024, 2, 3).unwrap(),
+                ..with_balance(
+                    transaction(1, &shop(), &checking(), dec!(5), "Refund"),
+                    dec!(80),
+                )
             },
-            None => "?",
-        }
-    );
-    println!("+==================+");
-    println!("| Amount:      {}", line.amount);
-    println!("| Date:        {}", line.date);
-    println!("| Source:      {}", line.source);
-    println!("| Sink:        {}", line.sink);
-    println!("| Tags:        {:?}", line.tags());
-    println!("| Description: {}", line.description);
-    println!("+------------------+");
+        ];
+        let timeline = balances::timeline(&transactions, &me());
+        let listed = |period, net_worth, from, to| -> Vec<(Value, Value, Value)> {
+            rows(&daily_balances(
+                &timeline,
+                &me(),
+                period,
+                net_worth,
+                from,
+                to,
+            ))
+            .into_iter()
+            .map(|row| {
+                (
+                    row["date"].clone(),
+                    row["node"].clone(),
+                    row["balance"].clone(),
+                )
+            })
+            .collect()
+        };
+
+        // The last day of January, and the last day with a balance.
+        assert_eq!(
+            listed(Some(summaries::Period::Month), true, None, None),
+            [
+                ("2024-01-31".into(), "net worth".into(), "75".into()),
+                ("2024-02-03".into(), "net worth".into(), "80".into()),
+            ]
+        );
+        let day = NaiveDate::from_ymd_opt(2024, 2, 2);
+        assert_eq!(
+            listed(None, false, day, day),
+            [
+                ("2024-02-02".into(), "Checking".into(), "75".into()),
+                ("2024-02-02".into(), "Joint".into(), "0".into()),
+                ("2024-02-02".into(), "net worth".into(), "75".into()),
+            ]
+        );
+        assert_eq!(listed(None, true, None, None).len(), 33);
+    }
+
+    #[test]
+    fn recurring_lists_every_series() {
+        let transactions: Vec<Transaction> = (1..=3)
+            .map(|month| Transaction {
+                date: NaiveDate::from_ymd_opt(2024, month, 5).unwrap(),
+                ..transaction(1, &checking(), &shop(), dec!(10), "Subscription")
+            })
+            .collect();
+        let listed = rows(&recurring(&Transactions::new(transactions), &me()));
+        let [series] = &listed[..] else {
+            panic!("expecting one series, got {:?}", listed)
+        };
+        assert_eq!(series["counterparty"], "Shop");
+        assert_eq!(series["direction"], "out");
+        assert_eq!(series["interval"], "monthly");
+        assert_eq!(series["occurrences"], 3);
+        assert_eq!(series["ended"], false);
+        assert_eq!(series["next_date"], "2024-04-05");
+        assert_eq!(series["next_amount"], "10");
+        assert_eq!(series["mandate"], Value::Null);
+    }
 }
@@ -0,0 +1,173 @@
+//! Printing the results of a command as an aligned table, JSON or CSV.
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+use std::io::{self, Write};
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Aligned columns, for people.
+    Table,
+    /// An array with an object per row, keyed by column.
+    Json,
+    /// A header line, then a line per row.
+    Csv,
+}
+
+/// Rows of cells under named columns. Cells are JSON values, so JSON output keeps
+/// numbers and booleans. Amounts are strings, so they aren't rounded.
+pub struct Table {
+    columns: Vec<&'static str>,
+    rows: Vec<Vec<Value>>,
+}
+impl Table {
+    pub fn new(columns: &[&'static str]) -> Self {
+        Self {
+            columns: columns.to_vec(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Add a row with a cell for every column, in order.
+    pub fn row<I>(&mut self, cells: I) -> &mut Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Value>,
+    {
+        let row: Vec<Value> = cells.into_iter().map(Into::into).collect();
+        debug_assert_eq!(row.len(), self.columns.len());
+        self.rows.push(row);
+        self
+    }
+
+    pub fn write<W: Write>(&self, format: Format, mut out: W) -> io::Result<()> {
+        match format {
+            Format::Table => self.write_table(&mut out),
+            Format::Json => {
+                let rows: Vec<Map<String, Value>> = self
+                    .rows
+                    .iter()
+                    .map(|row| {
+                        let cells = self.columns.iter().map(|column| column.to_string());
+                        cells.zip(row.iter().cloned()).collect()
+                    })
+                    .collect();
+                serde_json::to_writer_pretty(&mut out, &rows)?;
+                writeln!(out)
+            }
+            Format::Csv => {
+                let mut csv = csv::Writer::from_writer(out);
+                csv.write_record(&self.columns)?;
+                for row in &self.rows {
+                    csv.write_record(row.iter().map(text))?;
+                }
+                csv.flush()
+            }
+        }
+    }
+
+    fn write_table<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let cells: Vec<Vec<String>> = self
+            .rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|cell| text(cell).replace('\n', " "))
+                    .collect()
+            })
+            .collect();
+        let widths: Vec<usize> = (0..self.columns.len())
+            .map(|column| {
+                cells
+                    .iter()
+                    .map(|row| row[column].chars().count())
+                    .chain([self.columns[column].len()])
+                    .max()
+                    .unwrap_or_default()
+            })
+            .collect();
+        let line = |out: &mut W, row: &[&str]| {
+            let padded: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:width$}", cell, width = width))
+                .collect();
+            writeln!(out, "{}", padded.join("  ").trim_end())
+        };
+        line(out, &self.columns)?;
+        let rule: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+        line(out, &rule.iter().map(String::as_str).collect::<Vec<_>>())?;
+        for row in &cells {
+            line(out, &row.iter().map(String::as_str).collect::<Vec<_>>())?;
+        }
+        Ok(())
+    }
+}
+
+/// A cell as plain text: strings without quotes, nothing for null.
+fn text(cell: &Value) -> String {
+    match cell {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> Table {
+        let mut table = Table::new(&["name", "count", "owned", "note"]);
+        table.row([
+            Value::from("Checking"),
+            Value::from(12),
+            Value::from(true),
+            Value::Null,
+        ]);
+        table.row([
+            Value::from("Shop, Inc."),
+            Value::from(3),
+            Value::from(false),
+            Value::from("two\nlines"),
+        ]);
+        table
+    }
+
+    fn written(format: Format) -> String {
+        let mut out = Vec::new();
+        table().write(format, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn table_aligns_columns() {
+        assert_eq!(
+            written(Format::Table),
+            "name        count  owned  note\n\
+             ----------  -----  -----  ---------\n\
+             Checking    12     true\n\
+             Shop, Inc.  3      false  two lines\n"
+        );
+    }
+
+    #[test]
+    fn json_keeps_types_and_column_order() {
+        let json = written(Format::Json);
+        let rows: Vec<Map<String, Value>> = serde_json::from_str(&json).unwrap();
+        let columns: Vec<&String> = rows[0].keys().collect();
+        assert_eq!(columns, ["name", "count", "owned", "note"]);
+        assert_eq!(rows[0]["count"], 12);
+        assert_eq!(rows[0]["owned"], true);
+        assert_eq!(rows[0]["note"], Value::Null);
+    }
+
+    #[test]
+    fn csv_quotes_where_needed() {
+        assert_eq!(
+            written(Format::Csv),
+            "name,count,owned,note\n\
+             Checking,12,true,\n\
+             \"Shop, Inc.\",3,false,\"two\nlines\"\n"
+        );
+    }
+}
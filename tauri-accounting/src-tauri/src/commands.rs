@@ -0,0 +1,837 @@
+//! The commands the frontend invokes. They share one `Session` through `tauri::State`:
+//! the loaded profile and the transactions imported with it.
+use crate::dto::*;
+use accounting_core::{
+    analysis::{
+        balances, recurring,
+        summaries::{self, Period},
+    },
+    canonical::{
+        category::Category,
+        identify::*,
+        query::Query,
+        rule::Rule,
+        state::{OpeningBalance, Owner},
+        transaction::*,
+    },
+    from_files::{
+        error::ImportError,
+        import::{import_with_progress, profile_from_path},
+        importer::Registry,
+    },
+    rules,
+};
+use serde::{Serialize, Serializer};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::BufWriter,
+    path::PathBuf,
+    str::FromStr,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+use tauri::{AppHandle, Manager, State};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("no profile is loaded")]
+    NoProfile,
+    #[error("no transactions are imported")]
+    NoTransactions,
+    #[error("invalid node ID {0:?}")]
+    InvalidId(String),
+    #[error("invalid transaction ID {0:?}")]
+    InvalidTransactionId(String),
+    #[error("the profile has no query named {0:?}")]
+    UnknownQuery(String),
+    #[error("the profile has no node {0}")]
+    NotInProfile(ID<Node>),
+    #[error("no imported transaction has node {0}")]
+    UnknownNode(ID<Node>),
+    #[error("node {0} is named by what identifies it, so it can't be renamed")]
+    Unnamed(ID<Node>),
+    #[error(transparent)]
+    Import(#[from] ImportError),
+    #[error("the import stopped unexpectedly: {0}")]
+    Task(#[from] tauri::Error),
+    #[error("cannot write the profile: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("cannot write the profile: {0}")]
+    Json(#[from] serde_json::Error),
+}
+/// The frontend gets the message.
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Default)]
+pub struct Session {
+    owner: Option<Owner>,
+    transactions: Option<Transactions<'static>>,
+}
+impl Session {
+    fn owner(&self) -> Result<&Owner> {
+        self.owner.as_ref().ok_or(Error::NoProfile)
+    }
+    fn owner_mut(&mut self) -> Result<&mut Owner> {
+        self.owner.as_mut().ok_or(Error::NoProfile)
+    }
+    fn transactions(&self) -> Result<&Transactions<'static>> {
+        self.transactions.as_ref().ok_or(Error::NoTransactions)
+    }
+
+    fn rename_node(&mut self, id: ID<Node>, name: String) -> Result<&Owner> {
+        let owner = self.owner_mut()?;
+        let node = owner
+            .owns
+            .iter_mut()
+            .find(|node| node.id() == id)
+            .ok_or(Error::NotInProfile(id))?;
+        match node {
+            Node::ProperAccount(account) => account.name = name,
+            Node::SubAccount(account) => account.name = name,
+            // Their name is their ID, renaming them would make them another node.
+            Node::Terminal(_) | Node::Atm(_) | Node::Other(_) => return Err(Error::Unnamed(id)),
+        }
+        Ok(owner)
+    }
+
+    /// Owning a node takes it from the imported transactions, so it must be in one.
+    fn set_owned(&mut self, id: ID<Node>, owned: bool) -> Result<&Owner> {
+        let node = if owned {
+            let node = self
+                .transactions()?
+                .iter()
+                .flat_map(|transaction| [&transaction.source, &transaction.sink])
+                .find(|node| node.id() == id)
+                .ok_or(Error::UnknownNode(id))?;
+            Some(node.clone())
+        } else {
+            None
+        };
+        let owner = self.owner_mut()?;
+        match node {
+            Some(node) if !owner.owns(&id) => owner.owns.push(node),
+            Some(_) => {}
+            None => owner.owns.retain(|node| node.id() != id),
+        }
+        Ok(owner)
+    }
+
+    fn save_query(&mut self, name: String, query: Query) -> Result<&Owner> {
+        let owner = self.owner_mut()?;
+        owner.queries.insert(name, query);
+        Ok(owner)
+    }
+
+    fn delete_query(&mut self, name: String) -> Result<&Owner> {
+        let owner = self.owner_mut()?;
+        owner
+            .queries
+            .remove(&name)
+            .ok_or(Error::UnknownQuery(name))?;
+        Ok(owner)
+    }
+
+    /// Overrides the rules for transaction `id`, or lets them decide again when
+    /// `category` is absent.
+    fn categorize(&mut self, id: ID<Transaction>, category: Option<Category>) -> Result<&Owner> {
+        let owner = self.owner_mut()?;
+        match category {
+            Some(category) => owner.categorized.insert(id, category),
+            None => owner.categorized.remove(&id),
+        };
+        self.apply_rules();
+        self.owner()
+    }
+
+    fn cash_flow_report(
+        &self,
+        filter: TransactionFilter,
+        period: Period,
+    ) -> Result<Vec<PeriodCashFlow>> {
+        let owner = self.owner()?;
+        let matches = Matcher::new(filter, owner)?;
+        let transactions = self.transactions()?;
+        let filtered = transactions.filter(|transaction| matches.transaction(transaction, owner));
+        let nodes = nodes_by_id(transactions);
+        Ok(summaries::cash_flow_per_period(&filtered, owner, period)
+            .iter()
+            .map(|(start, flow)| {
+                let mut flows: Vec<_> = flow
+                    .nodes
+                    .iter()
+                    .filter_map(|(id, flow)| Some((*nodes.get(id)?, flow.clone())))
+                    .collect();
+                sort_flows(&mut flows);
+                let flows = flows
+                    .into_iter()
+                    .map(|(node, flow)| NodeFlow::new(node, &flow, owner))
+                    .collect();
+                PeriodCashFlow::new(*start, period, flow, flows)
+            })
+            .collect())
+    }
+
+    fn balance_timeline(&self) -> Result<BalanceTimeline> {
+        let owner = self.owner()?;
+        let timeline = balances::timeline(self.transactions()?, owner);
+        Ok(BalanceTimeline {
+            nodes: owner
+                .owns
+                .iter()
+                .filter_map(|node| {
+                    let series = timeline.nodes.get(&node.id())?;
+                    Some(NodeBalances::new(node, series, owner))
+                })
+                .collect(),
+            net_worth: DayBalance::all(&timeline.net_worth),
+        })
+    }
+
+    fn recurring(&self, filter: TransactionFilter) -> Result<Vec<RecurringDto>> {
+        let owner = self.owner()?;
+        let matches = Matcher::new(filter, owner)?;
+        let transactions = self.transactions()?;
+        let filtered = transactions.filter(|transaction| matches.transaction(transaction, owner));
+        Ok(recurring::detect(&filtered, owner)
+            .iter()
+            .map(|recurring| RecurringDto::new(recurring, owner))
+            .collect())
+    }
+
+    /// Returns the number of transactions with a rule tag, 0 without transactions.
+    fn apply_rules(&mut self) -> usize {
+        match (&self.owner, &mut self.transactions) {
+            (Some(owner), Some(transactions)) => rules::apply(transactions, owner),
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct AppState(Mutex<Session>);
+impl AppState {
+    /// A panic during another command doesn't leave the session in an invalid state:
+    /// every command only replaces parts of it once it's done.
+    fn session(&self) -> MutexGuard<'_, Session> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Load the profile .json file at `path`. Transactions imported with a previous
+/// profile are dropped.
+#[tauri::command]
+pub fn load_profile(state: State<'_, AppState>, path: PathBuf) -> Result<ProfileDto> {
+    let owner = profile_from_path(&path)?;
+    let profile = ProfileDto::new(&owner);
+    let mut session = state.session();
+    session.owner = Some(owner);
+    session.transactions = None;
+    Ok(profile)
+}
+
+/// Write the loaded profile, with its edits, to `path`.
+#[tauri::command]
+pub fn save_profile(state: State<'_, AppState>, path: PathBuf) -> Result<()> {
+    let session = state.session();
+    let file = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(file, session.owner()?)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn profile(state: State<'_, AppState>) -> Result<ProfileDto> {
+    Ok(ProfileDto::new(state.session().owner()?))
+}
+
+#[tauri::command]
+pub fn rename_owner(state: State<'_, AppState>, name: String) -> Result<ProfileDto> {
+    let mut session = state.session();
+    let owner = session.owner_mut()?;
+    owner.name = name;
+    Ok(ProfileDto::new(owner))
+}
+
+/// Rename the account or sub-account `id` of the profile.
+#[tauri::command]
+pub fn rename_node(state: State<'_, AppState>, id: String, name: String) -> Result<ProfileDto> {
+    let mut session = state.session();
+    let owner = session.rename_node(node_id(&id)?, name)?;
+    Ok(ProfileDto::new(owner))
+}
+
+/// Add node `id` of the imported transactions to the profile, or remove it.
+#[tauri::command]
+pub fn set_owned(state: State<'_, AppState>, id: String, owned: bool) -> Result<ProfileDto> {
+    let mut session = state.session();
+    let owner = session.set_owned(node_id(&id)?, owned)?;
+    Ok(ProfileDto::new(owner))
+}
+
+/// Save `query` in the profile under `name`, replacing the query with that name.
+#[tauri::command]
+pub fn save_query(state: State<'_, AppState>, name: String, query: Query) -> Result<ProfileDto> {
+    let mut session = state.session();
+    let owner = session.save_query(name, query)?;
+    Ok(ProfileDto::new(owner))
+}
+
+#[tauri::command]
+pub fn delete_query(state: State<'_, AppState>, name: String) -> Result<ProfileDto> {
+    let mut session = state.session();
+    let owner = session.delete_query(name)?;
+    Ok(ProfileDto::new(owner))
+}
+
+/// Replace the profile's tagging rules, and tag the imported transactions with them.
+#[tauri::command]
+pub fn set_rules(state: State<'_, AppState>, rules: Vec<Rule>) -> Result<ProfileDto> {
+    let mut session = state.session();
+    session.owner_mut()?.rules = rules;
+    session.apply_rules();
+    Ok(ProfileDto::new(session.owner()?))
+}
+
+/// Tag the imported transactions with the profile's rules again, e.g. after the
+/// profile's nodes changed. Returns the number of transactions with a rule tag.
+#[tauri::command]
+pub fn apply_rules(state: State<'_, AppState>) -> Result<usize> {
+    let mut session = state.session();
+    session.transactions()?;
+    Ok(session.apply_rules())
+}
+
+/// Replace the profile's categories, and categorize the imported transactions again.
+#[tauri::command]
+pub fn set_categories(state: State<'_, AppState>, categories: Vec<Category>) -> Result<ProfileDto> {
+    let mut session = state.session();
+    session.owner_mut()?.categories = categories;
+    session.apply_rules();
+    Ok(ProfileDto::new(session.owner()?))
+}
+
+/// Put the transaction with ID `id` in `category` whatever the rules say, or let the
+/// rules decide again when `category` is absent.
+#[tauri::command]
+pub fn categorize(
+    state: State<'_, AppState>,
+    id: String,
+    category: Option<Category>,
+) -> Result<ProfileDto> {
+    let id: ID<Transaction> =
+        ID::from_str(&id).map_err(|_| Error::InvalidTransactionId(id.clone()))?;
+    let mut session = state.session();
+    let owner = session.categorize(id, category)?;
+    Ok(ProfileDto::new(owner))
+}
+
+/// Replace the profile's opening balances, see `balance_timeline`.
+#[tauri::command]
+pub fn set_opening_balances(
+    state: State<'_, AppState>,
+    opening_balances: Vec<OpeningBalance>,
+) -> Result<ProfileDto> {
+    let mut session = state.session();
+    let owner = session.owner_mut()?;
+    owner.opening_balances = opening_balances;
+    Ok(ProfileDto::new(owner))
+}
+
+/// Import the bank exports in the directory (or the single file) at `path` with the
+/// loaded profile, replacing previously imported transactions. Emits an
+/// `import-progress` event with an `ImportProgress` before reading every file.
+///
+/// The files are read on a blocking thread with a copy of the profile, so other
+/// commands can use the session in the meantime.
+#[tauri::command]
+pub async fn import_folder(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    path: PathBuf,
+) -> Result<ImportSummary> {
+    let owner = state.session().owner()?.clone();
+    let (transactions, report) = tauri::async_runtime::spawn_blocking(move || {
+        import_with_progress(&path, &owner, &Registry::default(), |progress| {
+            // A window that isn't listening doesn't stop the import.
+            let _ = app.emit("import-progress", ImportProgress::from(progress));
+        })
+    })
+    .await??;
+    let summary = ImportSummary::new(&report, transactions.data());
+    state.session().transactions = Some(transactions);
+    Ok(summary)
+}
+
+/// The transactions that pass `filter`, `limit` at a time starting at `offset`.
+#[tauri::command]
+pub fn transactions(
+    state: State<'_, AppState>,
+    filter: Option<TransactionFilter>,
+    offset: usize,
+    limit: usize,
+) -> Result<TransactionPage> {
+    let session = state.session();
+    let owner = session.owner()?;
+    let matches = Matcher::new(filter.unwrap_or_default(), owner)?;
+    let selected: Vec<&Transaction> = session
+        .transactions()?
+        .iter()
+        .filter(|transaction| matches.transaction(transaction, owner))
+        .collect();
+    Ok(TransactionPage {
+        total: selected.len(),
+        offset,
+        transactions: selected
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(|transaction| TransactionDto::new(transaction, owner))
+            .collect(),
+    })
+}
+
+/// How many transactions every node takes part in, most frequent first.
+#[tauri::command]
+pub fn node_frequencies(
+    state: State<'_, AppState>,
+    filter: Option<TransactionFilter>,
+) -> Result<Vec<NodeFrequency>> {
+    let session = state.session();
+    let owner = session.owner()?;
+    let matches = Matcher::new(filter.unwrap_or_default(), owner)?;
+    let transactions = session.transactions()?;
+    let filtered = transactions.filter(|transaction| matches.transaction(transaction, owner));
+    let nodes = nodes_by_id(transactions);
+    let mut frequencies: Vec<NodeFrequency> = summaries::node_frequencies(&filtered)
+        .into_iter()
+        .filter_map(|(id, count)| {
+            Some(NodeFrequency {
+                node: NodeDto::new(nodes.get(&id)?, owner),
+                count,
+            })
+        })
+        .collect();
+    frequencies.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.node.id.cmp(&b.node.id))
+    });
+    Ok(frequencies)
+}
+
+/// Income, expenses and the money to and from every node and in every category, without
+/// internal transfers.
+#[tauri::command]
+pub fn cash_flow(
+    state: State<'_, AppState>,
+    filter: Option<TransactionFilter>,
+) -> Result<CashFlow> {
+    let session = state.session();
+    let owner = session.owner()?;
+    let matches = Matcher::new(filter.unwrap_or_default(), owner)?;
+    let transactions = session.transactions()?;
+    let filtered = transactions.filter(|transaction| matches.transaction(transaction, owner));
+    let totals = summaries::income_and_expenses(&filtered, owner);
+    let nodes = nodes_by_id(transactions);
+    let mut flows: Vec<_> = summaries::cash_flow_per_node(&filtered, owner)
+        .into_iter()
+        .filter_map(|(id, flow)| Some((*nodes.get(&id)?, flow)))
+        .collect();
+    sort_flows(&mut flows);
+    Ok(CashFlow {
+        income: totals.income.to_string(),
+        expenses: totals.expenses.to_string(),
+        net: (totals.income - totals.expenses).to_string(),
+        nodes: flows
+            .into_iter()
+            .map(|(node, flow)| NodeFlow::new(node, &flow, owner))
+            .collect(),
+        categories: summaries::cash_flow_per_category(&filtered, owner)
+            .iter()
+            .map(|(category, flow)| CategoryFlowDto::new(category.as_ref(), flow))
+            .collect(),
+    })
+}
+
+/// Income, expenses and net of every `period` (a `week`, `month` or `year`), with the
+/// money to and from every node and in every category, without internal transfers.
+/// Periods without transactions between the first and the last are there too.
+#[tauri::command]
+pub fn cash_flow_report(
+    state: State<'_, AppState>,
+    filter: Option<TransactionFilter>,
+    period: Period,
+) -> Result<Vec<PeriodCashFlow>> {
+    state
+        .session()
+        .cash_flow_report(filter.unwrap_or_default(), period)
+}
+
+/// The balance of every node of the profile and the net worth at the end of every day,
+/// from the balances the banks reported and the profile's opening balances. Always of all
+/// imported transactions: a filtered selection wouldn't add up to a balance.
+#[tauri::command]
+pub fn balance_timeline(state: State<'_, AppState>) -> Result<BalanceTimeline> {
+    state.session().balance_timeline()
+}
+
+/// Payments and income of the profile that come back every week, month, quarter or
+/// year, by their next expected date.
+#[tauri::command]
+pub fn recurring(
+    state: State<'_, AppState>,
+    filter: Option<TransactionFilter>,
+) -> Result<Vec<RecurringDto>> {
+    state.session().recurring(filter.unwrap_or_default())
+}
+
+/// Largest flows (in plus out) first.
+fn sort_flows(flows: &mut [(&Node, summaries::Flow)]) {
+    flows.sort_by(|(a, flow_a), (b, flow_b)| {
+        let size = |flow: &summaries::Flow| flow.incoming + flow.outgoing;
+        size(flow_b)
+            .cmp(&size(flow_a))
+            .then_with(|| a.id().to_string().cmp(&b.id().to_string()))
+    });
+}
+
+fn node_id(id: &str) -> Result<ID<Node>> {
+    ID::from_str(id).map_err(|_| Error::InvalidId(id.to_string()))
+}
+
+/// Every node of `transactions`, to turn the IDs of summaries back into nodes.
+fn nodes_by_id<'t>(transactions: &'t Transactions<'t>) -> HashMap<ID<Node>, &'t Node> {
+    transactions
+        .iter()
+        .flat_map(|transaction| [&transaction.source, &transaction.sink])
+        .map(|node| (node.id(), node))
+        .collect()
+}
+
+/// A `TransactionFilter` with its saved query looked up.
+struct Matcher {
+    saved: Option<Query>,
+    query: Query,
+    search: Option<String>,
+}
+impl Matcher {
+    fn new(filter: TransactionFilter, owner: &Owner) -> Result<Self> {
+        let saved = filter
+            .saved
+            .map(|name| {
+                owner
+                    .queries
+                    .get(&name)
+                    .cloned()
+                    .ok_or(Error::UnknownQuery(name))
+            })
+            .transpose()?;
+        Ok(Self {
+            saved,
+            query: filter.query,
+            search: filter.search.map(|search| search.to_lowercase()),
+        })
+    }
+
+    fn transaction(&self, transaction: &Transaction, owner: &Owner) -> bool {
+        let name = |node: &Node| owner.name_of(&node.id()).unwrap_or_else(|| node.name());
+        self.saved
+            .as_ref()
+            .is_none_or(|saved| saved.matches(transaction, owner))
+            && self.query.matches(transaction, owner)
+            && self.search.as_ref().is_none_or(|search| {
+                [
+                    transaction.description.clone(),
+                    name(&transaction.source),
+                    name(&transaction.sink),
+                ]
+                .iter()
+                .any(|text| text.to_lowercase().contains(search))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dto::fixtures::*;
+    use accounting_core::canonical::rule::Conditions;
+    use chrono::{Datelike, NaiveDate};
+    use rust_decimal_macros::dec;
+
+    /// A session with `owns` as the profile and a payment, a refund and an internal
+    /// transfer imported.
+    fn session(owns: Vec<Node>) -> Session {
+        Session {
+            owner: Some(owner(owns)),
+            transactions: Some(Transactions::new(vec![
+                Transaction {
+                    description: String::from("Groceries"),
+                    ..transaction(2, &checking(), &shop(), dec!(12.50))
+                },
+                transaction(3, &shop(), &checking(), dec!(2)),
+                transaction(4, &checking(), &joint(), dec!(100)),
+            ])),
+        }
+    }
+
+    fn matching(session: &Session, filter: &str) -> Vec<u32> {
+        let owner = session.owner().unwrap();
+        let matcher = Matcher::new(serde_json::from_str(filter).unwrap(), owner).unwrap();
+        session
+            .transactions()
+            .unwrap()
+            .iter()
+            .filter(|transaction| matcher.transaction(transaction, owner))
+            .map(|transaction| transaction.date.day())
+            .collect()
+    }
+
+    #[test]
+    fn matcher_filters_on_every_field() {
+        let both = session(vec![checking(), joint()]);
+        let session = session(vec![checking()]);
+        assert_eq!(matching(&session, "{}"), [2, 3, 4]);
+        assert_eq!(matching(&session, r#"{"from":"2024-01-03"}"#), [3, 4]);
+        assert_eq!(matching(&session, r#"{"to":"2024-01-03"}"#), [2, 3]);
+        let node = format!(r#"{{"node":"{}"}}"#, joint().id());
+        assert_eq!(matching(&session, &node), [4]);
+        assert_eq!(matching(&session, r#"{"max_amount":"50"}"#), [2, 3]);
+        assert_eq!(matching(&both, r#"{"ownership":"internal"}"#), [4]);
+        assert_eq!(matching(&session, r#"{"search":"GROCER"}"#), [2]);
+        assert_eq!(matching(&session, r#"{"search":"joint"}"#), [4]);
+    }
+
+    #[test]
+    fn matcher_searches_the_profiles_names() {
+        let mut session = session(vec![checking()]);
+        session
+            .rename_node(checking().id(), String::from("Household"))
+            .unwrap();
+        assert_eq!(matching(&session, r#"{"search":"household"}"#), [2, 3, 4]);
+    }
+
+    #[test]
+    fn matcher_combines_a_saved_query_with_the_filter() {
+        let mut session = session(vec![checking()]);
+        let outgoing = Query::default().direction(Direction::Outgoing, checking().id());
+        session.save_query(String::from("out"), outgoing).unwrap();
+        assert_eq!(matching(&session, r#"{"saved":"out"}"#), [2, 4]);
+        assert_eq!(
+            matching(&session, r#"{"saved":"out","from":"2024-01-03"}"#),
+            [4]
+        );
+    }
+
+    #[test]
+    fn matcher_rejects_unknown_queries() {
+        let session = session(vec![checking()]);
+        let filter = serde_json::from_str(r#"{"saved":"rent"}"#).unwrap();
+        assert!(matches!(
+            Matcher::new(filter, session.owner().unwrap()),
+            Err(Error::UnknownQuery(_))
+        ));
+    }
+
+    #[test]
+    fn saves_and_deletes_queries() {
+        let mut session = session(vec![checking()]);
+        let owner = session
+            .save_query(String::from("large"), Query::default().min_amount(dec!(50)))
+            .unwrap();
+        assert_eq!(owner.queries.len(), 1);
+        let owner = session.delete_query(String::from("large")).unwrap();
+        assert!(owner.queries.is_empty());
+        assert!(matches!(
+            session.delete_query(String::from("large")),
+            Err(Error::UnknownQuery(_))
+        ));
+    }
+
+    #[test]
+    fn renames_accounts_only() {
+        let terminal = Node::Terminal(String::from("AB1234"));
+        let mut session = session(vec![checking(), terminal.clone()]);
+        let owner = session
+            .rename_node(checking().id(), String::from("Household"))
+            .unwrap();
+        assert_eq!(owner.owns[0].name(), "Household");
+        assert!(matches!(
+            session.rename_node(terminal.id(), String::from("Shop")),
+            Err(Error::Unnamed(_))
+        ));
+        assert!(matches!(
+            session.rename_node(shop().id(), String::from("Shop")),
+            Err(Error::NotInProfile(_))
+        ));
+    }
+
+    #[test]
+    fn owns_nodes_of_the_imported_transactions() {
+        let mut session = session(vec![checking()]);
+        let owner = session.set_owned(joint().id(), true).unwrap();
+        assert_eq!(owner.owns, [checking(), joint()]);
+        // Owning it again doesn't add it twice.
+        let owner = session.set_owned(joint().id(), true).unwrap();
+        assert_eq!(owner.owns.len(), 2);
+        let owner = session.set_owned(checking().id(), false).unwrap();
+        assert_eq!(owner.owns, [joint()]);
+
+        let unknown = account("NL20INGB0001234567", "Other").id();
+        assert!(matches!(
+            session.set_owned(unknown, true),
+            Err(Error::UnknownNode(_))
+        ));
+    }
+
+    #[test]
+    fn editing_needs_a_profile() {
+        let mut session = Session::default();
+        assert!(matches!(
+            session.set_owned(checking().id(), false),
+            Err(Error::NoProfile)
+        ));
+    }
+
+    #[test]
+    fn rules_tag_the_imported_transactions() {
+        let mut session = session(vec![checking()]);
+        assert_eq!(session.apply_rules(), 0);
+        session.owner_mut().unwrap().rules = vec![Rule {
+            name: String::from("Shop"),
+            tags: vec![String::from("#shop")],
+            category: None,
+            when: Conditions {
+                counterparty: Some(shop().id()),
+                ..Default::default()
+            },
+        }];
+        assert_eq!(session.apply_rules(), 2);
+        assert_eq!(matching(&session, r##"{"tags":["#shop"]}"##), [2, 3]);
+        assert_eq!(Session::default().apply_rules(), 0);
+    }
+
+    #[test]
+    fn categorize_overrides_the_rules() {
+        let mut session = session(vec![checking()]);
+        session.owner_mut().unwrap().rules = vec![Rule {
+            name: String::from("Shop"),
+            tags: Vec::new(),
+            category: Some("Shopping".parse().unwrap()),
+            when: Conditions {
+                counterparty: Some(shop().id()),
+                ..Default::default()
+            },
+        }];
+        session.apply_rules();
+        assert_eq!(matching(&session, r#"{"category":"Shopping"}"#), [2, 3]);
+
+        let refund = session.transactions().unwrap().iter().nth(1).unwrap().id();
+        let owner = session
+            .categorize(refund, Some("Income".parse().unwrap()))
+            .unwrap();
+        assert_eq!(owner.categorized.len(), 1);
+        assert_eq!(matching(&session, r#"{"category":"Shopping"}"#), [2]);
+        assert_eq!(matching(&session, r#"{"category":"Income"}"#), [3]);
+
+        let owner = session.categorize(refund, None).unwrap();
+        assert!(owner.categorized.is_empty());
+        assert_eq!(matching(&session, r#"{"category":"Shopping"}"#), [2, 3]);
+    }
+
+    #[test]
+    fn cash_flow_report_has_the_filtered_periods() {
+        let session = session(vec![checking()]);
+        let filter = |json: &str| serde_json::from_str(json).unwrap();
+        let weeks = session
+            .cash_flow_report(filter("{}"), Period::Week)
+            .unwrap();
+        assert_eq!(weeks.len(), 1);
+        assert_eq!(
+            (weeks[0].from.as_str(), weeks[0].to.as_str()),
+            ("2024-01-01", "2024-01-07")
+        );
+        assert_eq!(weeks[0].expenses, "112.50");
+        let names: Vec<&str> = weeks[0]
+            .nodes
+            .iter()
+            .map(|flow| flow.node.name.as_str())
+            .collect();
+        assert_eq!(names, ["Joint", "Shop"]);
+
+        let months = session
+            .cash_flow_report(filter(r#"{"from":"2024-01-03"}"#), Period::Month)
+            .unwrap();
+        assert_eq!(months[0].to, "2024-01-31");
+        assert_eq!(
+            (months[0].income.as_str(), months[0].net.as_str()),
+            ("2", "-98")
+        );
+        assert_eq!(months[0].categories[0].category, None);
+    }
+
+    #[test]
+    fn balance_timeline_follows_the_profiles_nodes() {
+        let mut session = session(vec![checking(), joint()]);
+        let transactions = session.transactions.as_mut().unwrap();
+        *transactions = Transactions::new(
+            transactions
+                .iter()
+                .cloned()
+                .map(|transaction| Transaction {
+                    balance: (transaction.sink == joint()).then(|| Balance {
+                        of: joint().id(),
+                        amount: dec!(150),
+                    }),
+                    ..transaction
+                })
+                .collect(),
+        );
+        session.owner_mut().unwrap().opening_balances = vec![OpeningBalance {
+            node: checking().id(),
+            date: NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+            amount: dec!(500),
+        }];
+        let timeline = session.balance_timeline().unwrap();
+        let names: Vec<&str> = timeline
+            .nodes
+            .iter()
+            .map(|nodes| nodes.node.name.as_str())
+            .collect();
+        assert_eq!(names, ["Checking", "Joint"]);
+        assert!(timeline.nodes.iter().all(|nodes| nodes.anchored));
+        let last = |balances: &[DayBalance]| balances.last().unwrap().balance.clone();
+        assert_eq!(last(&timeline.nodes[0].balances), "389.50");
+        assert_eq!(last(&timeline.nodes[1].balances), "150");
+        assert_eq!(last(&timeline.net_worth), "539.50");
+        assert_eq!(timeline.net_worth[0].date, "2024-01-01");
+    }
+
+    #[test]
+    fn recurring_payments_of_the_filtered_transactions() {
+        let mut session = session(vec![checking()]);
+        session.transactions = Some(Transactions::new(
+            [1, 8, 15, 22]
+                .into_iter()
+                .map(|day| transaction(day, &checking(), &shop(), dec!(12.50)))
+                .collect(),
+        ));
+        let filter = |json: &str| serde_json::from_str(json).unwrap();
+        let found = session.recurring(filter("{}")).unwrap();
+        let [weekly] = &found[..] else {
+            panic!("expecting one series")
+        };
+        assert_eq!(weekly.counterparty.name, "Shop");
+        assert_eq!(weekly.direction, "outgoing");
+        assert_eq!(weekly.interval, recurring::Interval::Weekly);
+        assert_eq!(weekly.occurrences.len(), 4);
+        assert_eq!(weekly.next_date.as_deref(), Some("2024-01-29"));
+        let found = session.recurring(filter(r#"{"to":"2024-01-08"}"#)).unwrap();
+        assert!(found.is_empty());
+    }
+}
@@ -0,0 +1,586 @@
+//! Serializable views of the core types, as the frontend receives them. Amounts are
+//! strings so JavaScript doesn't round them, dates are `YYYY-MM-DD`.
+use accounting_core::{
+    analysis::{
+        balances::Series,
+        recurring::{Interval, Recurring},
+        summaries::{CategoryFlow, Flow, Period, PeriodFlow},
+    },
+    canonical::{
+        category::Category,
+        identify::*,
+        query::Query,
+        rule::Rule,
+        state::{OpeningBalance, Owner},
+        transaction::*,
+    },
+    from_files::{import::Progress, report::ImportReport},
+};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeDto {
+    pub id: String,
+    /// The profile's name for owned nodes.
+    pub name: String,
+    /// `account`, `subAccount`, `terminal`, `atm` or `other`.
+    pub kind: &'static str,
+    pub details: String,
+    pub owned: bool,
+}
+impl NodeDto {
+    pub fn new(node: &Node, owner: &Owner) -> Self {
+        let owned = owner.view(&node.id());
+        let node = owned.unwrap_or(node);
+        Self {
+            id: node.id().to_string(),
+            name: node.name(),
+            kind: match node {
+                Node::ProperAccount(_) => "account",
+                Node::SubAccount(_) => "subAccount",
+                Node::Terminal(_) => "terminal",
+                Node::Atm(_) => "atm",
+                Node::Other(_) => "other",
+            },
+            details: node.display_details(),
+            owned: owned.is_some(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileDto {
+    pub name: String,
+    pub owns: Vec<NodeDto>,
+    pub queries: BTreeMap<String, Query>,
+    pub rules: Vec<Rule>,
+    pub categories: Vec<Category>,
+    /// Categories chosen for single transactions, by transaction ID.
+    pub categorized: BTreeMap<String, Category>,
+    pub opening_balances: Vec<OpeningBalance>,
+}
+impl ProfileDto {
+    pub fn new(owner: &Owner) -> Self {
+        Self {
+            name: owner.name.clone(),
+            owns: owner
+                .owns
+                .iter()
+                .map(|node| NodeDto::new(node, owner))
+                .collect(),
+            queries: owner.queries.clone(),
+            rules: owner.rules.clone(),
+            categories: owner.categories.clone(),
+            categorized: owner
+                .categorized
+                .iter()
+                .map(|(id, category)| (id.to_string(), category.clone()))
+                .collect(),
+            opening_balances: owner.opening_balances.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionDto {
+    pub id: String,
+    pub date: String,
+    pub source: NodeDto,
+    pub sink: NodeDto,
+    pub amount: String,
+    /// `incoming` or `outgoing` for the owner, `internal` between two owned nodes,
+    /// absent when neither node is owned.
+    pub direction: Option<&'static str>,
+    pub tags: Vec<String>,
+    /// The tags that rules added, with the name of the rule that added each.
+    pub tagged_by: BTreeMap<String, String>,
+    /// E.g.: `"Food > Groceries"`.
+    pub category: Option<String>,
+    pub description: String,
+}
+impl TransactionDto {
+    pub fn new(transaction: &Transaction, owner: &Owner) -> Self {
+        let mut tags: Vec<String> = transaction.tags().into_iter().collect();
+        tags.sort();
+        Self {
+            id: transaction.id().to_string(),
+            date: transaction.date.to_string(),
+            source: NodeDto::new(&transaction.source, owner),
+            sink: NodeDto::new(&transaction.sink, owner),
+            amount: transaction.amount.to_string(),
+            direction: match (
+                owner.owns(&transaction.source.id()),
+                owner.owns(&transaction.sink.id()),
+            ) {
+                (true, true) => Some("internal"),
+                (false, true) => Some("incoming"),
+                (true, false) => Some("outgoing"),
+                (false, false) => None,
+            },
+            tags,
+            tagged_by: transaction.rule_tags.clone(),
+            category: transaction.category.as_ref().map(Category::to_string),
+            description: transaction.description.clone(),
+        }
+    }
+}
+
+/// Which transactions to return. Every field is optional, absent fields don't filter.
+/// The fields of a `Query` (as it is saved in the profile) can be given directly.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TransactionFilter {
+    /// Name of a saved query of the profile that transactions must match as well.
+    pub saved: Option<String>,
+    #[serde(flatten)]
+    pub query: Query,
+    /// Text that the description or one of the node names contains, ignoring case.
+    pub search: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionPage {
+    /// Number of transactions that pass the filter, on all pages together.
+    pub total: usize,
+    pub offset: usize,
+    pub transactions: Vec<TransactionDto>,
+}
+
+/// Payload of the `import-progress` event, sent before every file is read.
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportProgress {
+    pub file: String,
+    pub importer: &'static str,
+    /// 0-based.
+    pub index: usize,
+    pub total: usize,
+}
+impl From<Progress<'_>> for ImportProgress {
+    fn from(progress: Progress) -> Self {
+        Self {
+            file: progress.path.display().to_string(),
+            importer: progress.importer,
+            index: progress.index,
+            total: progress.total,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSummary {
+    pub accepted: usize,
+    pub skipped: usize,
+    pub rejected: usize,
+    pub flagged: usize,
+    pub merged: usize,
+    /// Unique transactions after the import.
+    pub transactions: usize,
+    pub first_date: Option<String>,
+    pub last_date: Option<String>,
+    /// A line per record that wasn't accepted, and per merged duplicate.
+    pub problems: Vec<String>,
+}
+impl ImportSummary {
+    pub fn new(report: &ImportReport, transactions: &[Transaction]) -> Self {
+        // The report's own lines, without its one line summary.
+        let problems = report
+            .to_string()
+            .lines()
+            .skip(1)
+            .map(|line| line.trim_start_matches("> ").to_string())
+            .collect();
+        Self {
+            accepted: report.accepted().count(),
+            skipped: report.skipped().count(),
+            rejected: report.rejected().count(),
+            flagged: report.flagged().count(),
+            merged: report.merges.len(),
+            transactions: transactions.len(),
+            first_date: transactions.first().map(|t| t.date.to_string()),
+            last_date: transactions.last().map(|t| t.date.to_string()),
+            problems,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeFrequency {
+    pub node: NodeDto,
+    pub count: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeFlow {
+    pub node: NodeDto,
+    pub incoming: String,
+    pub outgoing: String,
+    pub net: String,
+}
+impl NodeFlow {
+    pub fn new(node: &Node, flow: &Flow, owner: &Owner) -> Self {
+        Self {
+            node: NodeDto::new(node, owner),
+            incoming: flow.incoming.to_string(),
+            outgoing: flow.outgoing.to_string(),
+            net: flow.net().to_string(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryFlowDto {
+    /// Absent for transactions without a category.
+    pub category: Option<String>,
+    /// Number of parents.
+    pub depth: usize,
+    /// Money in transactions in the category itself.
+    pub incoming: String,
+    pub outgoing: String,
+    /// Money in transactions in the category or one of its subcategories.
+    pub total_incoming: String,
+    pub total_outgoing: String,
+    pub total_net: String,
+}
+impl CategoryFlowDto {
+    pub fn new(category: Option<&Category>, flow: &CategoryFlow) -> Self {
+        Self {
+            category: category.map(Category::to_string),
+            depth: category.map_or(0, Category::depth),
+            incoming: flow.own.incoming.to_string(),
+            outgoing: flow.own.outgoing.to_string(),
+            total_incoming: flow.total.incoming.to_string(),
+            total_outgoing: flow.total.outgoing.to_string(),
+            total_net: flow.total.net().to_string(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CashFlow {
+    pub income: String,
+    pub expenses: String,
+    pub net: String,
+    /// Largest flows (in plus out) first.
+    pub nodes: Vec<NodeFlow>,
+    /// The uncategorized transactions first, then the profile's category tree in order,
+    /// parents before their subcategories.
+    pub categories: Vec<CategoryFlowDto>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeriodCashFlow {
+    /// First day of the period.
+    pub from: String,
+    /// Last day of the period.
+    pub to: String,
+    pub income: String,
+    pub expenses: String,
+    pub net: String,
+    /// Largest flows (in plus out) first.
+    pub nodes: Vec<NodeFlow>,
+    /// Only the categories with transactions in the period, and their parents.
+    pub categories: Vec<CategoryFlowDto>,
+}
+impl PeriodCashFlow {
+    pub fn new(start: NaiveDate, period: Period, flow: &PeriodFlow, nodes: Vec<NodeFlow>) -> Self {
+        Self {
+            from: start.to_string(),
+            to: period.end(start).to_string(),
+            income: flow.total.incoming.to_string(),
+            expenses: flow.total.outgoing.to_string(),
+            net: flow.total.net().to_string(),
+            nodes,
+            categories: flow
+                .categories
+                .iter()
+                .map(|(category, flow)| CategoryFlowDto::new(category.as_ref(), flow))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DayBalance {
+    pub date: String,
+    pub balance: String,
+}
+impl DayBalance {
+    pub fn all<T: ToString>(balances: &BTreeMap<NaiveDate, T>) -> Vec<Self> {
+        balances
+            .iter()
+            .map(|(date, balance)| Self {
+                date: date.to_string(),
+                balance: balance.to_string(),
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeBalances {
+    pub node: NodeDto,
+    /// False when no balance of the node is known: it is taken to be empty before its
+    /// first transaction.
+    pub anchored: bool,
+    /// The balance at the end of every day.
+    pub balances: Vec<DayBalance>,
+}
+impl NodeBalances {
+    pub fn new(node: &Node, series: &Series, owner: &Owner) -> Self {
+        Self {
+            node: NodeDto::new(node, owner),
+            anchored: series.anchored,
+            balances: DayBalance::all(&series.balances),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BalanceTimeline {
+    /// In the order of the profile.
+    pub nodes: Vec<NodeBalances>,
+    pub net_worth: Vec<DayBalance>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriceChangeDto {
+    /// Of the first occurrence with the new amount.
+    pub date: String,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecurringDto {
+    pub counterparty: NodeDto,
+    /// `incoming` or `outgoing` for the owner.
+    pub direction: &'static str,
+    pub interval: Interval,
+    /// Of a SEPA direct debit.
+    pub mandate: Option<String>,
+    pub creditor: Option<String>,
+    /// IDs of the transactions, by date.
+    pub occurrences: Vec<String>,
+    /// IDs of the payments off the schedule, by date.
+    pub extra: Vec<String>,
+    pub first_date: String,
+    pub last_date: String,
+    pub price_changes: Vec<PriceChangeDto>,
+    pub missed: Vec<String>,
+    pub ended: bool,
+    /// None when the series ended.
+    pub next_date: Option<String>,
+    pub next_amount: String,
+}
+impl RecurringDto {
+    pub fn new(recurring: &Recurring, owner: &Owner) -> Self {
+        let date = |transaction: Option<&&Transaction>| {
+            transaction.map(|t| t.date.to_string()).unwrap_or_default()
+        };
+        let ids = |transactions: &[&Transaction]| {
+            transactions
+                .iter()
+                .map(|transaction| transaction.id().to_string())
+                .collect()
+        };
+        Self {
+            counterparty: NodeDto::new(recurring.counterparty, owner),
+            direction: match recurring.direction {
+                Direction::Incoming => "incoming",
+                Direction::Outgoing => "outgoing",
+            },
+            interval: recurring.interval,
+            mandate: recurring.mandate.clone(),
+            creditor: recurring.creditor.clone(),
+            occurrences: ids(&recurring.occurrences),
+            extra: ids(&recurring.extra),
+            first_date: date(recurring.occurrences.first()),
+            last_date: date(recurring.occurrences.last()),
+            price_changes: recurring
+                .price_changes
+                .iter()
+                .map(|change| PriceChangeDto {
+                    date: change.date.to_string(),
+                    from: change.from.to_string(),
+                    to: change.to.to_string(),
+                })
+                .collect(),
+            missed: recurring.missed.iter().map(NaiveDate::to_string).collect(),
+            ended: recurring.ended,
+            next_date: recurring.next_date.map(|date| date.to_string()),
+            next_amount: recurring.next_amount.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fixtures::*, *};
+    use chrono::NaiveDate;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn nodes_have_the_profiles_name() {
+        let owner = Owner {
+            owns: vec![account("NL91ABNA0417164300", "Household")],
+            ..owner(vec![])
+        };
+        let node = NodeDto::new(&checking(), &owner);
+        assert_eq!(node.name, "Household");
+        assert_eq!(node.kind, "account");
+        assert!(node.owned);
+        let node = NodeDto::new(&Node::Terminal(String::from("AB1234")), &owner);
+        assert_eq!(node.kind, "terminal");
+        assert!(!node.owned);
+    }
+
+    #[test]
+    fn direction_is_from_the_owners_side() {
+        let owner = owner(vec![checking(), joint()]);
+        let direction = |source: &Node, sink: &Node| {
+            TransactionDto::new(&transaction(1, source, sink, dec!(1)), &owner).direction
+        };
+        assert_eq!(direction(&checking(), &shop()), Some("outgoing"));
+        assert_eq!(direction(&shop(), &checking()), Some("incoming"));
+        assert_eq!(direction(&checking(), &joint()), Some("internal"));
+        assert_eq!(direction(&shop(), &shop()), None);
+    }
+
+    #[test]
+    fn transactions_serialize_for_the_frontend() {
+        let payment = Transaction {
+            inherent_tags: String::from("#weekly #food"),
+            rule_tags: [(String::from("#shop"), String::from("Shop"))].into(),
+            category: Some("Shopping > Food".parse().unwrap()),
+            ..transaction(2, &checking(), &shop(), dec!(12.50))
+        };
+        let json =
+            serde_json::to_value(TransactionDto::new(&payment, &owner(vec![checking()]))).unwrap();
+        assert_eq!(json["date"], "2024-01-02");
+        assert_eq!(json["amount"], "12.50");
+        assert_eq!(
+            json["tags"],
+            serde_json::json!(["#food", "#shop", "#weekly"])
+        );
+        assert_eq!(json["taggedBy"], serde_json::json!({"#shop": "Shop"}));
+        assert_eq!(json["category"], "Shopping > Food");
+        assert_eq!(json["sink"]["name"], "Shop");
+        assert_eq!(json["source"]["owned"], true);
+    }
+
+    #[test]
+    fn category_flows_have_their_depth() {
+        let food: Category = "Shopping > Food".parse().unwrap();
+        let flow = CategoryFlow {
+            own: Flow {
+                incoming: dec!(2),
+                outgoing: dec!(10),
+            },
+            total: Flow {
+                incoming: dec!(2),
+                outgoing: dec!(15),
+            },
+        };
+        let json = serde_json::to_value(CategoryFlowDto::new(Some(&food), &flow)).unwrap();
+        assert_eq!(json["category"], "Shopping > Food");
+        assert_eq!(json["depth"], 1);
+        assert_eq!(json["outgoing"], "10");
+        assert_eq!(json["totalNet"], "-13");
+        let none = CategoryFlowDto::new(None, &CategoryFlow::default());
+        assert_eq!((none.category, none.depth), (None, 0));
+    }
+
+    #[test]
+    fn filter_fields_are_optional() {
+        let filter: TransactionFilter = serde_json::from_str(r#"{"from":"2024-01-01"}"#).unwrap();
+        assert_eq!(filter.query.from, NaiveDate::from_ymd_opt(2024, 1, 1));
+        assert!(filter.saved.is_none() && filter.search.is_none());
+        assert_eq!(
+            filter.query,
+            Query::default().from(filter.query.from.unwrap())
+        );
+        let invalid = serde_json::from_str::<TransactionFilter>(r#"{"from":"2024-13-01"}"#);
+        assert!(invalid.is_err());
+    }
+}
+
+/// Nodes, transactions and owners for the tests of the commands and their DTOs.
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use accounting_core::canonical::{account::*, state::Owner, transaction::*};
+    use chrono::NaiveDate;
+    use iban::Iban;
+    use rust_decimal::Decimal;
+    use std::collections::{BTreeMap, HashMap};
+
+    pub fn account(iban: &str, name: &str) -> Node {
+        Node::ProperAccount(Account {
+            iban: Iban::parse(iban).unwrap(),
+            name: String::from(name),
+        })
+    }
+
+    pub fn checking() -> Node {
+        account("NL91ABNA0417164300", "Checking")
+    }
+
+    pub fn joint() -> Node {
+        account("NL02ABNA0123456789", "Joint")
+    }
+
+    pub fn shop() -> Node {
+        account("NL44RABO0123456789", "Shop")
+    }
+
+    /// On `day` of January 2024, without anything that's optional.
+    pub fn transaction(day: u32, source: &Node, sink: &Node, amount: Decimal) -> Transaction {
+        Transaction {
+            date: NaiveDate::from_ymd_opt(2024, 1, day).unwrap(),
+            source: source.clone(),
+            sink: sink.clone(),
+            amount,
+            inherent_tags: String::new(),
+            description: String::new(),
+            sequence: None,
+            reference: None,
+            end_to_end: None,
+            mandate: None,
+            balance: None,
+            code: None,
+            occurrence: 0,
+            rule_tags: BTreeMap::new(),
+            category: None,
+        }
+    }
+
+    pub fn owner(owns: Vec<Node>) -> Owner {
+        Owner {
+            name: String::from("Me"),
+            owns,
+            account_names: HashMap::new(),
+            queries: BTreeMap::new(),
+            rules: Vec::new(),
+            categories: Vec::new(),
+            categorized: HashMap::new(),
+            opening_balances: Vec::new(),
+        }
+    }
+}
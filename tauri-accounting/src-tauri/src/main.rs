@@ -1,6 +1,9 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod commands;
+mod dto;
+
 // Learn more about Tauri commands at https://tauri.app/v1/guides/features/command
 #[tauri::command]
 fn greet(name: &str) -> String {
@@ -11,7 +14,30 @@ fn greet(name: &str) -> String {
 fn main() {
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
-        .invoke_handler(tauri::generate_handler![greet])
+        .manage(commands::AppState::default())
+        .invoke_handler(tauri::generate_handler![
+            greet,
+            commands::load_profile,
+            commands::save_profile,
+            commands::profile,
+            commands::rename_owner,
+            commands::rename_node,
+            commands::set_owned,
+            commands::save_query,
+            commands::delete_query,
+            commands::set_rules,
+            commands::apply_rules,
+            commands::set_categories,
+            commands::categorize,
+            commands::import_folder,
+            commands::transactions,
+            commands::node_frequencies,
+            commands::cash_flow,
+            commands::cash_flow_report,
+            commands::set_opening_balances,
+            commands::balance_timeline,
+            commands::recurring,
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }